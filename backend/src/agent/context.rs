@@ -0,0 +1,31 @@
+use crate::dtos::AgentRequest;
+
+/// Page text attached to a prompt: the user's selection, sent in place of the
+/// page content, or the page content.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageText<'a> {
+    Selection(&'a str),
+    Content(&'a str),
+}
+
+impl<'a> PageText<'a> {
+    /// The request's selection, or else its page content when not empty.
+    pub fn of(request: &'a AgentRequest) -> Option<Self> {
+        match (
+            request.selection.as_deref(),
+            request.page_content.as_deref(),
+        ) {
+            (Some(selection), _) => Some(Self::Selection(selection)),
+            (None, Some(content)) if !content.is_empty() => Some(Self::Content(content)),
+            _ => None,
+        }
+    }
+
+    /// Appends the text to `query` under its heading.
+    pub fn attach_to(&self, query: &str) -> String {
+        match self {
+            Self::Selection(text) => format!("{}\n\n## Selected Text\n{}", query, text),
+            Self::Content(text) => format!("{}\n\n## Page Content\n{}", query, text),
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Keeps the model from wasting turns: stops a run early when it keeps making
+//! the same call to a function that doesn't exist, and answers a context tool
+//! called again with the same arguments, with no action in between, with a
+//! nudge instead of re-running it.
+//! Identical calls the model makes in a single turn run only once; the calls
+//! of a turn run one after another in the order the model declared them.
+//! Depending on the request's `on_tool_error`, a failed tool ends the run.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rig::agent::{CancelSignal, StreamingPromptHook};
+use rig::completion::{CompletionModel, ToolDefinition};
+use rig::message::Message;
+use rig::tool::{ToolDyn, ToolError};
+use rig::wasm_compat::WasmBoxedFuture;
+
+use crate::dtos::agent::OnToolError;
+
+/// Number of identical unknown-function calls after which the run is stopped.
+pub const MAX_UNKNOWN_CALL_REPEATS: usize = 3;
+
+/// Read-only tools whose result can't have changed when called again with the
+/// same arguments before any other tool runs.
+const CONTEXT_TOOLS: &[&str] = &[
+    "get_page_content",
+    "get_interactive_elements",
+    "get_selection",
+    "count_elements",
+    "get_performance",
+    "full_page_screenshot",
+    "capture_region",
+    "capture_element",
+];
+
+/// Returned instead of the result of a repeated context tool call.
+pub const REPEATED_CALL_NUDGE: &str = "You already have this information from your previous call with the same arguments. Proceed with the task using it instead of calling the tool again.";
+
+/// Returned instead of running a call identical to an earlier one in the same turn.
+pub const DUPLICATE_CALL_RESULT: &str =
+    "Skipped: this call is identical to an earlier call in the same turn, which already ran.";
+
+#[derive(Clone)]
+pub struct ToolCallGuard {
+    known_tools: Arc<HashSet<String>>,
+    /// Times each unknown `(name, args)` call was made
+    unknown_calls: Arc<Mutex<HashMap<(String, String), usize>>>,
+    /// Context tool calls since the last call to any other tool, as `(name, args)`
+    context_calls: Arc<Mutex<HashSet<(String, String)>>>,
+    /// Calls made since the model's latest response, as `(name, args)`
+    turn_calls: Arc<Mutex<HashSet<(String, String)>>>,
+    error_policy: OnToolError,
+    /// The first failed tool call, as `(name, error)`, when failures end the run
+    tool_failure: Arc<Mutex<Option<(String, String)>>>,
+}
+
+impl ToolCallGuard {
+    pub fn new(known_tools: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            known_tools: Arc::new(known_tools.into_iter().collect()),
+            unknown_calls: Arc::default(),
+            context_calls: Arc::default(),
+            turn_calls: Arc::default(),
+            error_policy: OnToolError::Continue,
+            tool_failure: Arc::default(),
+        }
+    }
+
+    /// Sets what happens after a tool fails.
+    pub fn with_error_policy(mut self, policy: OnToolError) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Records a failed tool call, unless failures are left to the model.
+    pub fn record_failure(&self, name: &str, error: &ToolError) {
+        if self.error_policy == OnToolError::Continue {
+            return;
+        }
+        let error = match error {
+            ToolError::ToolCallError(inner) => inner.to_string(),
+            other => other.to_string(),
+        };
+        self.tool_failure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert((name.to_string(), error));
+    }
+
+    /// The reason to end the run after a recorded tool failure: the error for
+    /// `stop`, a question on how to proceed for `ask`.
+    pub fn failure_reason(&self) -> Option<String> {
+        let failure = self
+            .tool_failure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (name, error) = failure.as_ref()?;
+        match self.error_policy {
+            OnToolError::Continue => None,
+            OnToolError::Stop => Some(format!("Dihentikan karena {} gagal: {}", name, error)),
+            OnToolError::Ask => Some(format!(
+                "{} gagal: {}. Bagaimana saya harus melanjutkan? Anda bisa menyelesaikan langkah ini secara manual lalu melanjutkan run ini, atau memberi instruksi lain.",
+                name, error
+            )),
+        }
+    }
+
+    /// Whether the run ended to ask the user how to proceed.
+    pub fn asks_user(&self) -> bool {
+        self.error_policy == OnToolError::Ask
+            && self
+                .tool_failure
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .is_some()
+    }
+
+    /// Wraps tools so a context tool called again with the same arguments
+    /// returns `REPEATED_CALL_NUDGE` instead of running.
+    pub fn wrap_tools(&self, tools: Vec<Box<dyn ToolDyn>>) -> Vec<Box<dyn ToolDyn>> {
+        tools
+            .into_iter()
+            .map(|inner| {
+                Box::new(GuardedTool {
+                    inner,
+                    guard: self.clone(),
+                }) as Box<dyn ToolDyn>
+            })
+            .collect()
+    }
+
+    /// Forgets the calls of the previous turn; called before each model request.
+    pub fn start_turn(&self) {
+        self.turn_calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Records a call for the current turn. Returns whether an identical call
+    /// was already made in it.
+    pub fn is_duplicate_in_turn(&self, name: &str, args: &str) -> bool {
+        let call = (name.to_string(), args.to_string());
+        !self
+            .turn_calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(call)
+    }
+
+    /// Records a call made to a tool. Returns the nudge when it repeats any
+    /// context tool call made since the last action; an action may change the
+    /// page, so it starts over.
+    pub fn repeated_call_nudge(&self, name: &str, args: &str) -> Option<&'static str> {
+        let mut context_calls = self
+            .context_calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !CONTEXT_TOOLS.contains(&name) {
+            context_calls.clear();
+            return None;
+        }
+        let repeated = !context_calls.insert((name.to_string(), args.to_string()));
+        repeated.then_some(REPEATED_CALL_NUDGE)
+    }
+
+    /// Records a tool call. Returns the reason to stop once the same unknown
+    /// call has been made `MAX_UNKNOWN_CALL_REPEATS` times.
+    pub fn record_call(&self, name: &str, args: &str) -> Option<String> {
+        if self.known_tools.contains(name) {
+            return None;
+        }
+
+        let mut calls = self
+            .unknown_calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let count = calls
+            .entry((name.to_string(), args.to_string()))
+            .or_default();
+        *count += 1;
+
+        (*count >= MAX_UNKNOWN_CALL_REPEATS).then(|| {
+            format!(
+                "Maaf, model terus memanggil fungsi yang tidak dikenal ({}). Proses dihentikan.",
+                name
+            )
+        })
+    }
+}
+
+impl<M: CompletionModel> StreamingPromptHook<M> for ToolCallGuard {
+    async fn on_completion_call(
+        &self,
+        _prompt: &Message,
+        _history: &[Message],
+        _cancel_sig: CancelSignal,
+    ) {
+        self.start_turn();
+    }
+
+    async fn on_tool_call(
+        &self,
+        tool_name: &str,
+        _tool_call_id: Option<String>,
+        args: &str,
+        cancel_sig: CancelSignal,
+    ) {
+        if let Some(reason) = self.record_call(tool_name, args) {
+            tracing::warn!(
+                "Stopping run: repeated calls to unknown function {}",
+                tool_name
+            );
+            cancel_sig.cancel_with_reason(&reason);
+        }
+    }
+
+    async fn on_tool_result(
+        &self,
+        tool_name: &str,
+        _tool_call_id: Option<String>,
+        _args: &str,
+        _result: &str,
+        cancel_sig: CancelSignal,
+    ) {
+        if let Some(reason) = self.failure_reason() {
+            tracing::warn!("Ending run after {} failed", tool_name);
+            cancel_sig.cancel_with_reason(&reason);
+        }
+    }
+}
+
+struct GuardedTool {
+    inner: Box<dyn ToolDyn>,
+    guard: ToolCallGuard,
+}
+
+impl ToolDyn for GuardedTool {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn definition<'a>(&'a self, prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+        self.inner.definition(prompt)
+    }
+
+    fn call<'a>(&'a self, args: String) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+        let name = self.inner.name();
+        if self.guard.is_duplicate_in_turn(&name, &args) {
+            tracing::info!("Skipping duplicate call to {} in the same turn", name);
+            return Box::pin(async {
+                serde_json::to_string(DUPLICATE_CALL_RESULT).map_err(ToolError::JsonError)
+            });
+        }
+        match self.guard.repeated_call_nudge(&name, &args) {
+            Some(nudge) => {
+                tracing::info!("Repeated call to {}, nudging the model to proceed", name);
+                Box::pin(async move { serde_json::to_string(nudge).map_err(ToolError::JsonError) })
+            }
+            None => {
+                let call = self.inner.call(args);
+                let guard = self.guard.clone();
+                Box::pin(async move {
+                    let result = call.await;
+                    if let Err(error) = &result {
+                        guard.record_failure(&name, error);
+                    }
+                    result
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> ToolCallGuard {
+        ToolCallGuard::new(["click_element".to_string()])
+    }
+
+    #[test]
+    fn test_repeated_unknown_call_stops_run() {
+        let guard = guard();
+        for _ in 1..MAX_UNKNOWN_CALL_REPEATS {
+            assert!(guard.record_call("open_tab", r#"{"url":"a"}"#).is_none());
+        }
+        let reason = guard.record_call("open_tab", r#"{"url":"a"}"#).unwrap();
+        assert!(reason.contains("open_tab"));
+    }
+
+    #[test]
+    fn test_known_and_varied_calls_continue() {
+        let guard = guard();
+        for i in 0..MAX_UNKNOWN_CALL_REPEATS * 2 {
+            assert!(guard.record_call("click_element", r#"{"ref":1}"#).is_none());
+            // Different arguments each time aren't the same bad call
+            assert!(guard.record_call("open_tab", &i.to_string()).is_none());
+        }
+    }
+
+    /// Counts how often it actually runs.
+    struct CountingTool(&'static str, Arc<Mutex<usize>>);
+
+    impl ToolDyn for CountingTool {
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn definition<'a>(&'a self, _prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+            Box::pin(async {
+                ToolDefinition {
+                    name: self.0.to_string(),
+                    description: String::new(),
+                    parameters: serde_json::json!({}),
+                }
+            })
+        }
+
+        fn call<'a>(&'a self, _args: String) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+            *self.1.lock().unwrap() += 1;
+            Box::pin(async { Ok("\"[button ref=1]\"".to_string()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_consecutive_calls_get_nudge() {
+        let runs = Arc::new(Mutex::new(0));
+        let guard = ToolCallGuard::new(["get_interactive_elements".to_string()]);
+        let tools = guard.wrap_tools(vec![Box::new(CountingTool(
+            "get_interactive_elements",
+            runs.clone(),
+        ))]);
+        let tool = &tools[0];
+
+        // Each call follows a new model response
+        guard.start_turn();
+        let first = tool.call(r#"{"limit":50}"#.to_string()).await.unwrap();
+        assert_eq!(first, "\"[button ref=1]\"");
+        guard.start_turn();
+        let second = tool.call(r#"{"limit":50}"#.to_string()).await.unwrap();
+        assert_eq!(second, serde_json::to_string(REPEATED_CALL_NUDGE).unwrap());
+        assert_eq!(*runs.lock().unwrap(), 1);
+
+        // Different arguments run the tool again
+        guard.start_turn();
+        tool.call(r#"{"limit":100}"#.to_string()).await.unwrap();
+        assert_eq!(*runs.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_calls_in_one_turn_run_once() {
+        let runs = Arc::new(Mutex::new(0));
+        let guard = ToolCallGuard::new(["click_element".to_string()]);
+        let tools = guard.wrap_tools(vec![Box::new(CountingTool("click_element", runs.clone()))]);
+        let tool = &tools[0];
+
+        guard.start_turn();
+        tool.call(r#"{"ref":1}"#.to_string()).await.unwrap();
+        let second = tool.call(r#"{"ref":1}"#.to_string()).await.unwrap();
+        assert_eq!(
+            second,
+            serde_json::to_string(DUPLICATE_CALL_RESULT).unwrap()
+        );
+        assert_eq!(*runs.lock().unwrap(), 1);
+
+        // The same click in the next turn is a new action
+        guard.start_turn();
+        tool.call(r#"{"ref":1}"#.to_string()).await.unwrap();
+        assert_eq!(*runs.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_repeated_actions_are_not_nudged() {
+        let guard = guard();
+        // Clicking the same element twice (e.g. "next" twice) is a real action
+        assert!(
+            guard
+                .repeated_call_nudge("click_element", r#"{"ref":1}"#)
+                .is_none()
+        );
+        assert!(
+            guard
+                .repeated_call_nudge("click_element", r#"{"ref":1}"#)
+                .is_none()
+        );
+        // An action in between means the page may have changed
+        assert!(
+            guard
+                .repeated_call_nudge("get_page_content", "{}")
+                .is_none()
+        );
+        assert!(
+            guard
+                .repeated_call_nudge("click_element", r#"{"ref":1}"#)
+                .is_none()
+        );
+        assert!(
+            guard
+                .repeated_call_nudge("get_page_content", "{}")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_alternating_context_calls_get_nudge() {
+        let guard = guard();
+        let page = ("get_page_content", "{}");
+        let elements = ("get_interactive_elements", r#"{"limit":50}"#);
+
+        // Page, elements, page, elements: both repeats are caught
+        assert!(guard.repeated_call_nudge(page.0, page.1).is_none());
+        assert!(guard.repeated_call_nudge(elements.0, elements.1).is_none());
+        assert_eq!(
+            guard.repeated_call_nudge(page.0, page.1),
+            Some(REPEATED_CALL_NUDGE)
+        );
+        assert_eq!(
+            guard.repeated_call_nudge(elements.0, elements.1),
+            Some(REPEATED_CALL_NUDGE)
+        );
+
+        // After an action both may be read again
+        assert!(
+            guard
+                .repeated_call_nudge("click_element", r#"{"ref":1}"#)
+                .is_none()
+        );
+        assert!(guard.repeated_call_nudge(page.0, page.1).is_none());
+        assert!(guard.repeated_call_nudge(elements.0, elements.1).is_none());
+    }
+
+    /// Always fails, like a click on an element that is gone.
+    struct FailingTool;
+
+    impl ToolDyn for FailingTool {
+        fn name(&self) -> String {
+            "click_element".to_string()
+        }
+
+        fn definition<'a>(&'a self, _prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+            Box::pin(async {
+                ToolDefinition {
+                    name: "click_element".to_string(),
+                    description: String::new(),
+                    parameters: serde_json::json!({}),
+                }
+            })
+        }
+
+        fn call<'a>(&'a self, _args: String) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+            Box::pin(async { Err(ToolError::ToolCallError("Element ref=3 not found".into())) })
+        }
+    }
+
+    async fn fail_once(policy: OnToolError) -> ToolCallGuard {
+        let guard = ToolCallGuard::new(["click_element".to_string()]).with_error_policy(policy);
+        let tools = guard.wrap_tools(vec![Box::new(FailingTool)]);
+        assert!(tools[0].call(r#"{"ref":3}"#.to_string()).await.is_err());
+        guard
+    }
+
+    #[tokio::test]
+    async fn test_continue_leaves_failures_to_the_model() {
+        let guard = fail_once(OnToolError::Continue).await;
+        assert_eq!(guard.failure_reason(), None);
+        assert!(!guard.asks_user());
+    }
+
+    #[tokio::test]
+    async fn test_stop_ends_run_with_the_error() {
+        let guard = fail_once(OnToolError::Stop).await;
+        assert_eq!(
+            guard.failure_reason().as_deref(),
+            Some("Dihentikan karena click_element gagal: Element ref=3 not found")
+        );
+        assert!(!guard.asks_user());
+    }
+
+    #[tokio::test]
+    async fn test_ask_ends_run_with_a_question() {
+        let guard = fail_once(OnToolError::Ask).await;
+        let reason = guard.failure_reason().unwrap();
+        assert!(reason.starts_with("click_element gagal: Element ref=3 not found."));
+        assert!(reason.contains("Bagaimana saya harus melanjutkan?"));
+        assert!(guard.asks_user());
+    }
+}
@@ -0,0 +1,191 @@
+//! Classifies what a query wants before the agent is built, so questions about
+//! the current page are answered without registering browser tools.
+
+use crate::dtos::AgentRequest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intent {
+    /// Asks about the page; answerable from the attached content
+    Question,
+    /// Asks the agent to do something in the browser
+    Action,
+}
+
+pub trait IntentClassifier {
+    fn classify(&self, query: &str) -> Intent;
+}
+
+/// Words that ask the agent to act on the page (Indonesian and English).
+const ACTION_WORDS: &[&str] = &[
+    "klik",
+    "tekan",
+    "buka",
+    "isi",
+    "ketik",
+    "tulis",
+    "hapus",
+    "pilih",
+    "centang",
+    "gulir",
+    "scroll",
+    "kirim",
+    "navigasi",
+    "cari",
+    "kembali",
+    "login",
+    "masuk",
+    "daftar",
+    "unduh",
+    "click",
+    "press",
+    "open",
+    "fill",
+    "type",
+    "clear",
+    "select",
+    "check",
+    "uncheck",
+    "submit",
+    "navigate",
+    "search",
+    "go",
+    "back",
+    "download",
+    "screenshot",
+];
+
+/// Words that start a question or a request to explain the page.
+const QUESTION_WORDS: &[&str] = &[
+    "apa",
+    "apakah",
+    "siapa",
+    "kapan",
+    "dimana",
+    "mengapa",
+    "kenapa",
+    "bagaimana",
+    "berapa",
+    "jelaskan",
+    "rangkum",
+    "ringkas",
+    "terjemahkan",
+    "what",
+    "who",
+    "when",
+    "where",
+    "why",
+    "how",
+    "which",
+    "explain",
+    "summarize",
+    "summarise",
+    "translate",
+    "is",
+    "are",
+    "does",
+];
+
+/// Cheap keyword classifier, run on every request instead of a model call.
+///
+/// Any action word makes the query an action. Otherwise it is a question when
+/// it opens with a question word or ends with `?`; anything else is treated as
+/// an action so tools stay available when unsure.
+pub struct KeywordClassifier;
+
+impl IntentClassifier for KeywordClassifier {
+    fn classify(&self, query: &str) -> Intent {
+        let query = query.trim().to_lowercase();
+        let words: Vec<&str> = query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        if words.iter().any(|word| ACTION_WORDS.contains(word)) {
+            return Intent::Action;
+        }
+        let opens_with_question = words
+            .first()
+            .is_some_and(|word| QUESTION_WORDS.contains(word));
+        if opens_with_question || query.ends_with('?') {
+            Intent::Question
+        } else {
+            Intent::Action
+        }
+    }
+}
+
+/// Whether browser tools should be registered for a request. Questions skip
+/// them when the page content or a selection is attached to answer from.
+pub fn needs_tools(classifier: &impl IntentClassifier, request: &AgentRequest) -> bool {
+    if !request.wants_tools() {
+        return false;
+    }
+    let has_page_context = request.selection.is_some()
+        || request
+            .page_content
+            .as_deref()
+            .is_some_and(|content| !content.is_empty());
+
+    !has_page_context || classifier.classify(&request.query) == Intent::Action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Classifies by prefix, standing in for a real classifier.
+    struct MockClassifier;
+
+    impl IntentClassifier for MockClassifier {
+        fn classify(&self, query: &str) -> Intent {
+            if query.starts_with("what") {
+                Intent::Question
+            } else {
+                Intent::Action
+            }
+        }
+    }
+
+    fn request(query: &str) -> AgentRequest {
+        serde_json::from_value(serde_json::json!({
+            "query": query,
+            "session_id": "s1",
+            "page_content": "Welcome to the store",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_questions_skip_tools() {
+        assert!(!needs_tools(
+            &MockClassifier,
+            &request("what is this page about")
+        ));
+        assert!(needs_tools(&MockClassifier, &request("click submit")));
+
+        // Without page content to answer from, questions still get tools
+        let mut no_content = request("what is this page about");
+        no_content.page_content = None;
+        assert!(needs_tools(&MockClassifier, &no_content));
+    }
+
+    #[test]
+    fn test_keyword_classifier() {
+        let classifier = KeywordClassifier;
+        assert_eq!(
+            classifier.classify("What is this page about?"),
+            Intent::Question
+        );
+        assert_eq!(classifier.classify("rangkum halaman ini"), Intent::Question);
+        assert_eq!(classifier.classify("Click submit"), Intent::Action);
+        assert_eq!(
+            classifier.classify("tolong klik tombol daftar"),
+            Intent::Action
+        );
+        assert_eq!(
+            classifier.classify("what happens if I click buy?"),
+            Intent::Action
+        );
+        assert_eq!(classifier.classify("lanjutkan"), Intent::Action);
+    }
+}
@@ -0,0 +1,141 @@
+//! Rough language detection of page text, used to tell the model what it is
+//! translating from.
+
+use crate::agent::context::PageText;
+use crate::dtos::AgentRequest;
+
+/// Languages recognized by `detect_language`: code, English name and common
+/// function words.
+const LANGUAGES: &[(&str, &str, &[&str])] = &[
+    (
+        "id",
+        "Indonesian",
+        &[
+            "yang", "dan", "di", "ini", "itu", "dengan", "untuk", "dari", "tidak", "ke", "akan",
+            "adalah",
+        ],
+    ),
+    (
+        "en",
+        "English",
+        &[
+            "the", "and", "of", "to", "is", "in", "that", "for", "with", "this", "are", "not",
+        ],
+    ),
+    (
+        "es",
+        "Spanish",
+        &[
+            "el", "la", "de", "que", "y", "en", "los", "las", "por", "con", "para", "una",
+        ],
+    ),
+    (
+        "fr",
+        "French",
+        &[
+            "le", "la", "les", "de", "et", "des", "est", "une", "pour", "dans", "que", "pas",
+        ],
+    ),
+    (
+        "de",
+        "German",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "mit", "den", "zu", "ein", "eine", "auf",
+        ],
+    ),
+];
+
+/// Minimum number of function words needed before a language is reported.
+const MIN_MATCHES: usize = 3;
+
+/// Detects the language of `text` by counting common function words.
+/// Returns the language's English name, or `None` when unsure.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .take(2000)
+        .collect();
+
+    LANGUAGES
+        .iter()
+        .map(|(_, name, stopwords)| {
+            let matches = words.iter().filter(|word| stopwords.contains(word)).count();
+            (*name, matches)
+        })
+        .filter(|(_, matches)| *matches >= MIN_MATCHES)
+        .max_by_key(|(_, matches)| *matches)
+        .map(|(name, _)| name)
+}
+
+/// English name for a language code, or the input unchanged when unknown
+/// (so `translate_to` also accepts names like "Japanese").
+pub fn language_name(code: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(known, _, _)| known.eq_ignore_ascii_case(code.trim()))
+        .map_or(code.trim(), |(_, name, _)| name)
+}
+
+/// Instruction asking the model to translate the page and its answer into
+/// `target`, naming the detected source language of `page_text` when known.
+pub fn translation_instruction(target: &str, page_text: Option<&str>) -> String {
+    let target = language_name(target);
+    let mut instruction =
+        format!("Translate the page content into {target} and write your whole answer in {target}");
+    if let Some(source) = page_text.and_then(detect_language) {
+        instruction.push_str(&format!(" (the page is in {source})"));
+    }
+    instruction
+}
+
+/// The translation instruction for a request's `translate_to`, if set.
+pub fn request_translation(request: &AgentRequest) -> Option<String> {
+    let page_text = request
+        .selection
+        .as_deref()
+        .or(request.page_content.as_deref());
+    request
+        .translate_to
+        .as_deref()
+        .map(|target| translation_instruction(target, page_text))
+}
+
+/// The text to translate when `translate_to` is set: the selection, or else
+/// the page content. The no-tools paths attach it to the query.
+pub fn translation_source(request: &AgentRequest) -> Option<PageText<'_>> {
+    request
+        .translate_to
+        .as_ref()
+        .and_then(|_| PageText::of(request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDONESIAN_PAGE: &str = "Ini adalah halaman resmi dari toko kami. Produk yang dijual dikirim ke seluruh Indonesia dengan cepat dan tidak mahal.";
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language(INDONESIAN_PAGE), Some("Indonesian"));
+        assert_eq!(
+            detect_language("This is the official page of the store and it ships in a day."),
+            Some("English")
+        );
+        assert_eq!(detect_language("Login"), None);
+    }
+
+    #[test]
+    fn test_translation_targets_english_for_indonesian_page() {
+        let instruction = translation_instruction("en", Some(INDONESIAN_PAGE));
+        assert_eq!(
+            instruction,
+            "Translate the page content into English and write your whole answer in English (the page is in Indonesian)"
+        );
+
+        // Unknown codes are passed through as language names
+        assert!(translation_instruction("Japanese", None).ends_with("answer in Japanese"));
+    }
+}
@@ -1 +1,8 @@
-
+pub mod context;
+pub mod guard;
+pub mod instruction;
+pub mod intent;
+pub mod language;
+pub mod preamble;
+pub mod reasoning;
+pub mod steps;
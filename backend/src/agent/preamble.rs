@@ -0,0 +1,324 @@
+use crate::agent::language::request_translation;
+use crate::dtos::AgentRequest;
+use crate::dtos::agent::{ResponseFormat, Verbosity};
+
+const INTRO: &str = "You are a browser automation assistant. You can control the browser using tools AND see/analyze screenshots.";
+
+const SAFE_MODE_INTRO: &str =
+    "You are a browser assistant. You can read the page using tools AND see/analyze screenshots.";
+
+const NO_TOOLS_INTRO: &str = "You are a browser assistant. Answer from the page content or selected text attached to the user's message AND the screenshot when provided.";
+
+const TOOLS_HEADING: &str = "\n\n## Available Tools\n";
+
+const ACTION_TOOLS: &str = r#"### Action Tools
+- `navigate_to(url)`: Navigate to a URL (e.g., "https://google.com")
+- `click_element(ref)`: Click an element using its Ref ID number
+- `type_text(ref, text)`: Type text into an input field using its Ref ID
+- `clear_field(ref)`: Empty an input field using its Ref ID
+- `set_checked(ref, checked)`: Check or uncheck a checkbox/radio button. Use instead of clicking so the result doesn't depend on the current state
+- `upload_file(ref, file_name)`: Attach a file the user sent with their message to a file input. Only available when the user attached files
+- `for_each(items, tool, args)`: Run one of `click_element`, `type_text`, `clear_field` or `set_checked` for every item of a list in a single call, with `{{item}}` in the JSON `args` replaced by each item (e.g. items ["12", "15"], args {"ref": "{{item}}"}). Use for bulk tasks instead of repeating the action
+- `scroll_to(x, y)`: Scroll the page (window) to coordinates
+- `scroll_element(ref, dx, dy)`: Scroll inside a scrollable container (chat pane, results panel, data grid) by an offset. Use when the content is in an inner scroll region that `scroll_to` doesn't move
+- `go_history(delta)`: Go back (negative) or forward (positive) by N pages in the tab's history
+- `handle_dialog(action, prompt_text)`: Accept or dismiss a native alert/confirm/prompt dialog blocking the page
+
+"#;
+
+const CONTEXT_TOOLS: &str = r#"### Context Tools (use these FIRST when needed)
+- `get_interactive_elements(limit)`: Scan page for buttons, inputs, links. **CALL THIS FIRST** before clicking or typing.
+- `get_page_content(max_length)`: Get page text content. Use when you need to read, summarize, or analyze text.
+- `get_selection()`: Get the text the user selected on the page, with its surrounding paragraph.
+- `count_elements(selector)`: Count elements matching a CSS selector. Use to verify the result of an action.
+- `get_performance()`: Get page load timing (TTFB, DOMContentLoaded, load, LCP) and the slowest resources. Use when asked why a page is slow.
+- `get_live_regions()`: Get the text of ARIA live regions and what they recently announced (including toasts that already disappeared). Use to verify what a screen reader announced after an action.
+- `get_console_logs(levels?)`: Get JavaScript console errors and warnings (including uncaught errors) logged since you started working on the page, optionally only one level (e.g. ["error"]). Use when asked what errors the page is throwing.
+- `full_page_screenshot(question)`: Capture the entire page, including parts outside the viewport, and see it as an image with your next message. Use for visual analysis of long pages.
+- `capture_region(x, y, w, h, question?)`: Screenshot a region of the page (page coordinates) and see it as an image with your next message, to describe it or answer `question`. Use to look at an area outside the provided screenshot.
+- `capture_element(ref, question?)`: Screenshot a single element (chart, image, captcha) and see it as an image with your next message, to describe it or answer `question`. Prefer this over a full screenshot when only one element matters.
+"#;
+
+const CAPABILITIES: &str = r#"
+## Your Capabilities
+1. **Browser Automation**: Control the browser using action tools
+2. **Visual Analysis**: When screenshot is provided, you CAN SEE and READ everything visible on screen
+3. **Dynamic Context**: Use context tools to get page data when needed
+"#;
+
+/// What an instruction is about; action instructions are left out when only
+/// context tools are declared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Uses {
+    ContextTools,
+    ActionTools,
+}
+
+const INSTRUCTIONS: &[(Uses, &str)] = &[
+    (
+        Uses::ActionTools,
+        "**Before clicking/typing**: Call `get_interactive_elements()` to find element Ref IDs",
+    ),
+    (
+        Uses::ContextTools,
+        "**Before reading/summarizing**: Call `get_page_content()` to get page text",
+    ),
+    (
+        Uses::ContextTools,
+        "When the user refers to \"this\" or selected text, call `get_selection()` first and answer from the selection rather than the whole page",
+    ),
+    (
+        Uses::ActionTools,
+        "When the user asks to go to a website, use `navigate_to`",
+    ),
+    (
+        Uses::ActionTools,
+        "When replacing a field's existing value, call `clear_field(ref)` before `type_text`",
+    ),
+    (
+        Uses::ContextTools,
+        "When the user asks about the page content (with screenshot), read the screenshot OR call `get_page_content()`",
+    ),
+    (
+        Uses::ActionTools,
+        "After an action, verify the result cheaply with `count_elements()` when the outcome is countable",
+    ),
+];
+
+const EXAMPLE_FLOWS: &str = r#"## Example Flows
+- User: "klik tombol login" → Call get_interactive_elements() → Find login button Ref ID → Call click_element(ref)
+- User: "rangkum halaman ini" → Call get_page_content() → Summarize the returned text
+- User: "jelaskan ini" → Call get_selection() → Explain the selected text (fall back to get_page_content() if nothing is selected)
+- User: "buka google" → Call navigate_to("https://google.com")
+- User: "kembali dua halaman" → Call go_history(-2)
+- User: "tambahkan item ke keranjang" → Call click_element(ref) → Call count_elements(".cart-item") to verify the item was added
+"#;
+
+/// Per-request knobs that change the agent preamble.
+#[derive(Debug, Default)]
+pub struct PreambleOptions {
+    pub verbosity: Verbosity,
+    pub format: Option<ResponseFormat>,
+    /// Instruction to translate the page and answer (`translate_to`)
+    pub translation: Option<String>,
+    /// Report the outcome without narrating individual tool calls
+    pub concise_actions: bool,
+    /// Only observation tools are available; action tools and flows are left out
+    pub safe_mode: bool,
+    /// No tools are declared (questions about the attached page), so no tool is mentioned
+    pub without_tools: bool,
+}
+
+impl PreambleOptions {
+    pub fn from_request(request: &AgentRequest) -> Self {
+        Self {
+            verbosity: request.verbosity,
+            format: request.format,
+            translation: request_translation(request),
+            concise_actions: request.concise_actions,
+            safe_mode: request.safe_mode,
+            without_tools: false,
+        }
+    }
+}
+
+const SAFE_MODE_INSTRUCTION: &str = "**SAFE MODE**: You can only observe this page. You have no tools to navigate, click, type or scroll, and must not claim to have changed anything. If the user asks you to act, say that safe mode is on and explain how they can do it themselves";
+
+const NO_TOOLS_INSTRUCTION: &str = "You have no tools for this message. Do not call any function; answer from the attached page content and screenshot";
+
+const CONCISE_ACTIONS_INSTRUCTION: &str = "Report only the final outcome in the user's terms (e.g. \"Filled in the login form\"). Do not narrate individual steps or mention tool names, Ref IDs or element numbers";
+
+pub fn build_preamble(options: &PreambleOptions) -> String {
+    let mut instructions: Vec<&str> = Vec::new();
+    if options.safe_mode {
+        instructions.push(SAFE_MODE_INSTRUCTION);
+    }
+    if options.without_tools {
+        instructions.push(NO_TOOLS_INSTRUCTION);
+    } else {
+        instructions.extend(
+            INSTRUCTIONS
+                .iter()
+                .filter(|(uses, _)| !options.safe_mode || *uses == Uses::ContextTools)
+                .map(|(_, instruction)| *instruction),
+        );
+    }
+    // A step-by-step account is exactly the narration concise_actions leaves out
+    let verbosity = match options.verbosity {
+        Verbosity::Detailed if options.concise_actions => Verbosity::Normal,
+        verbosity => verbosity,
+    };
+    instructions.push(verbosity_instruction(verbosity));
+    if options.concise_actions {
+        instructions.push(CONCISE_ACTIONS_INSTRUCTION);
+    }
+    if let Some(format) = options.format {
+        instructions.push(format_instruction(format));
+    }
+    if let Some(translation) = &options.translation {
+        instructions.push(translation);
+    }
+
+    if options.without_tools {
+        let mut preamble = format!("{}\n", NO_TOOLS_INTRO);
+        push_instructions(&mut preamble, &instructions);
+        return preamble;
+    }
+
+    let intro = if options.safe_mode {
+        SAFE_MODE_INTRO
+    } else {
+        INTRO
+    };
+    let mut preamble = format!("{}{}", intro, TOOLS_HEADING);
+    if !options.safe_mode {
+        preamble.push_str(ACTION_TOOLS);
+    }
+    preamble.push_str(CONTEXT_TOOLS);
+    if !options.safe_mode {
+        preamble.push_str(CAPABILITIES);
+    }
+    push_instructions(&mut preamble, &instructions);
+    if !options.safe_mode {
+        preamble.push('\n');
+        preamble.push_str(EXAMPLE_FLOWS);
+    }
+    preamble
+}
+
+fn push_instructions(preamble: &mut String, instructions: &[&str]) {
+    preamble.push_str("\n## Instructions\n");
+    for (i, instruction) in instructions.iter().enumerate() {
+        preamble.push_str(&format!("{}. {}\n", i + 1, instruction));
+    }
+}
+
+fn verbosity_instruction(verbosity: Verbosity) -> &'static str {
+    match verbosity {
+        Verbosity::Terse => "Respond with a single short sentence stating the outcome",
+        Verbosity::Normal => "Always respond with a brief confirmation of what you did",
+        Verbosity::Detailed => {
+            "Respond with a detailed step-by-step account of each action you took and what you observed"
+        }
+    }
+}
+
+fn format_instruction(format: ResponseFormat) -> &'static str {
+    match format {
+        ResponseFormat::Text => "Respond in plain text without any Markdown formatting",
+        ResponseFormat::Markdown => {
+            "Format your response in Markdown (headings, lists, bold) where it helps readability"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_preamble_keeps_brief_confirmation() {
+        let preamble = build_preamble(&PreambleOptions::default());
+        assert!(preamble.contains("8. Always respond with a brief confirmation of what you did"));
+        assert!(!preamble.contains("Markdown"));
+    }
+
+    #[test]
+    fn test_verbosity_changes_preamble() {
+        let terse = build_preamble(&PreambleOptions {
+            verbosity: Verbosity::Terse,
+            ..Default::default()
+        });
+        assert!(terse.contains("single short sentence"));
+        assert!(!terse.contains("brief confirmation"));
+
+        let detailed = build_preamble(&PreambleOptions {
+            verbosity: Verbosity::Detailed,
+            ..Default::default()
+        });
+        assert!(detailed.contains("detailed step-by-step account"));
+        assert!(!detailed.contains("brief confirmation"));
+    }
+
+    #[test]
+    fn test_format_changes_preamble() {
+        let text = build_preamble(&PreambleOptions {
+            format: Some(ResponseFormat::Text),
+            ..Default::default()
+        });
+        assert!(text.contains("9. Respond in plain text"));
+
+        let markdown = build_preamble(&PreambleOptions {
+            format: Some(ResponseFormat::Markdown),
+            ..Default::default()
+        });
+        assert!(markdown.contains("9. Format your response in Markdown"));
+    }
+
+    #[test]
+    fn test_concise_actions_adds_directive() {
+        let default = build_preamble(&PreambleOptions::default());
+        assert!(!default.contains(CONCISE_ACTIONS_INSTRUCTION));
+
+        let concise = build_preamble(&PreambleOptions {
+            concise_actions: true,
+            ..Default::default()
+        });
+        assert!(concise.contains(&format!("9. {}", CONCISE_ACTIONS_INSTRUCTION)));
+
+        // It overrides a detailed step-by-step account
+        let detailed = build_preamble(&PreambleOptions {
+            verbosity: Verbosity::Detailed,
+            concise_actions: true,
+            ..Default::default()
+        });
+        assert!(!detailed.contains("step-by-step"));
+        assert!(detailed.contains(CONCISE_ACTIONS_INSTRUCTION));
+    }
+
+    #[test]
+    fn test_safe_mode_leaves_out_actions() {
+        let safe = build_preamble(&PreambleOptions {
+            safe_mode: true,
+            ..Default::default()
+        });
+        assert!(safe.contains(&format!("1. {}", SAFE_MODE_INSTRUCTION)));
+        assert!(safe.contains("`get_page_content(max_length)`"));
+        assert!(!safe.contains("`click_element(ref)`"));
+        assert!(!safe.contains("`navigate_to(url)`"));
+        assert!(!safe.contains("Example Flows"));
+        // Nothing tells the model to act
+        assert!(!safe.contains("You can control the browser"));
+        assert!(!safe.contains("Before clicking/typing"));
+        assert!(!safe.contains("navigate_to"));
+        assert!(!safe.contains("clear_field"));
+        assert!(safe.contains("2. **Before reading/summarizing**"));
+
+        let default = build_preamble(&PreambleOptions::default());
+        assert!(default.contains("`click_element(ref)`"));
+        assert!(default.contains("You can control the browser"));
+        assert!(default.contains("4. When the user asks to go to a website, use `navigate_to`"));
+        assert!(!default.contains("SAFE MODE"));
+    }
+
+    #[test]
+    fn test_without_tools_mentions_no_tools() {
+        let preamble = build_preamble(&PreambleOptions {
+            without_tools: true,
+            ..Default::default()
+        });
+        assert!(preamble.starts_with(NO_TOOLS_INTRO));
+        assert!(preamble.contains(&format!("1. {}", NO_TOOLS_INSTRUCTION)));
+        for tool in [
+            "get_page_content",
+            "get_selection",
+            "get_interactive_elements",
+            "click_element",
+            "navigate_to",
+        ] {
+            assert!(!preamble.contains(tool), "{} is mentioned", tool);
+        }
+        assert!(!preamble.contains("Available Tools"));
+        assert!(!preamble.contains("Example Flows"));
+    }
+}
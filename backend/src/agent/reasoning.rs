@@ -0,0 +1,57 @@
+//! Separating the model's reasoning from its final answer when a request
+//! asks to see it.
+
+/// Marks the start of the reasoning in a response.
+pub const REASONING_START: &str = "<reasoning>";
+/// Marks the end of the reasoning; the answer follows.
+pub const REASONING_END: &str = "</reasoning>";
+
+/// Asks the model to write its reasoning, delimited, before the answer.
+pub const REASONING_INSTRUCTION: &str = "Before answering, think step by step and write your reasoning between <reasoning> and </reasoning>. Then write the final answer after </reasoning>, without repeating the reasoning.";
+
+/// Splits a response into its reasoning and answer. Without a complete
+/// delimited block, the whole response is the answer.
+pub fn split_reasoning(response: &str) -> (Option<String>, String) {
+    let Some((before, rest)) = response.split_once(REASONING_START) else {
+        return (None, response.trim().to_string());
+    };
+    let Some((reasoning, after)) = rest.split_once(REASONING_END) else {
+        return (None, response.trim().to_string());
+    };
+
+    let answer = format!("{}{}", before.trim(), after.trim_end());
+    (
+        Some(reasoning.trim().to_string()).filter(|r| !r.is_empty()),
+        answer.trim().to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delimited_response_is_split() {
+        let (reasoning, answer) = split_reasoning(
+            "<reasoning>\nHalaman membahas harga. Yang termurah 10rb.\n</reasoning>\n\nHarga termurah Rp10.000.",
+        );
+        assert_eq!(
+            reasoning.as_deref(),
+            Some("Halaman membahas harga. Yang termurah 10rb.")
+        );
+        assert_eq!(answer, "Harga termurah Rp10.000.");
+    }
+
+    #[test]
+    fn test_undelimited_response_is_all_answer() {
+        assert_eq!(
+            split_reasoning("Harga termurah Rp10.000."),
+            (None, "Harga termurah Rp10.000.".to_string())
+        );
+        // An unclosed block isn't trusted as reasoning
+        assert_eq!(
+            split_reasoning("<reasoning>Harga termurah"),
+            (None, "<reasoning>Harga termurah".to_string())
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Structured record of a tool-enabled run: the text the model produced before
+//! each tool call, the call itself, and what the tool returned.
+
+use serde::Serialize;
+
+/// Maximum number of characters of a tool result kept in a step.
+pub const MAX_STEP_RESULT_CHARS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentStep {
+    pub thought: Option<String>,
+    pub action: String,
+    pub result: Option<String>,
+}
+
+#[derive(Default)]
+pub struct StepRecorder {
+    steps: Vec<AgentStep>,
+    pending_thought: String,
+}
+
+impl StepRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records streamed model text; it becomes the thought of the next tool call.
+    pub fn text(&mut self, text: &str) {
+        self.pending_thought.push_str(text);
+    }
+
+    pub fn tool_call(&mut self, name: &str, args: &serde_json::Value) {
+        let thought = std::mem::take(&mut self.pending_thought);
+        let thought = thought.trim();
+
+        self.steps.push(AgentStep {
+            thought: (!thought.is_empty()).then(|| thought.to_string()),
+            action: format!("{}({})", name, args),
+            result: None,
+        });
+    }
+
+    /// Attaches a tool result to the oldest call still waiting for one.
+    pub fn tool_result(&mut self, result: &str) {
+        if let Some(step) = self.steps.iter_mut().find(|s| s.result.is_none()) {
+            step.result = Some(result.chars().take(MAX_STEP_RESULT_CHARS).collect());
+        }
+    }
+
+    pub fn into_steps(self) -> Vec<AgentStep> {
+        self.steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_multi_tool_run_populates_steps() {
+        // Stream of a mocked model run: scan the page, click, then answer
+        let mut recorder = StepRecorder::new();
+        recorder.text("Saya perlu mencari tombol login dulu.");
+        recorder.tool_call("get_interactive_elements", &json!({ "limit": 50 }));
+        recorder.tool_result("Success. Data: [button ref=3 \"Login\"]");
+        recorder.text("Tombol login ada di ref 3.");
+        recorder.tool_call("click_element", &json!({ "ref": 3 }));
+        recorder.tool_result("Success. Data: clicked");
+        recorder.text("Sudah saya klik tombol login.");
+
+        let steps = recorder.into_steps();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(
+            steps[0],
+            AgentStep {
+                thought: Some("Saya perlu mencari tombol login dulu.".to_string()),
+                action: r#"get_interactive_elements({"limit":50})"#.to_string(),
+                result: Some("Success. Data: [button ref=3 \"Login\"]".to_string()),
+            }
+        );
+        assert_eq!(
+            steps[1].thought.as_deref(),
+            Some("Tombol login ada di ref 3.")
+        );
+        assert_eq!(steps[1].action, r#"click_element({"ref":3})"#);
+        assert_eq!(steps[1].result.as_deref(), Some("Success. Data: clicked"));
+    }
+
+    #[test]
+    fn test_step_without_thought_or_result() {
+        let mut recorder = StepRecorder::new();
+        recorder.tool_call("navigate_to", &json!({ "url": "https://google.com" }));
+
+        let steps = recorder.into_steps();
+        assert_eq!(steps[0].thought, None);
+        assert_eq!(steps[0].result, None);
+    }
+}
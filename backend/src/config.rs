@@ -1,23 +1,440 @@
 use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rig::message::{ImageMediaType, MimeType};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::llm::HttpClientConfig;
+use crate::llm::router::RoutingRules;
+use crate::utils::streaming::StreamGranularity;
 
 pub struct AppConfig {
     pub port: u16,
+    /// Where model calls go; the mock needs no API key or network
+    pub llm_provider: LlmProvider,
+    pub gemini_api_key: String,
+    /// Overrides the Gemini API base URL, e.g. to go through a proxy
+    pub gemini_base_url: Option<String>,
+    /// Text prepended to every model response (e.g. a compliance disclaimer)
+    pub response_prefix: Option<String>,
+    /// Text appended to every model response
+    pub response_suffix: Option<String>,
+    /// Image format assumed for data without a data-URL prefix or recognizable signature
+    pub default_image_type: ImageMediaType,
+    /// Timeouts, User-Agent and extra headers for model API calls
+    pub http: HttpClientConfig,
+    /// Minimum spacing between consecutive browser actions on a session
+    pub tool_min_delay: Duration,
+    /// Rules for choosing between the flash and pro models
+    pub routing: RoutingRules,
+    /// Names of browser tools not given to the agent
+    pub disabled_tools: Vec<String>,
+    /// Hosts the agent may navigate to (`*.` for subdomains); empty allows all
+    pub allowed_domains: Vec<String>,
+    /// Hosts the agent may never navigate to (`*.` for subdomains)
+    pub blocked_domains: Vec<String>,
+    /// Most elements of a `get_interactive_elements` scan shown to the model
+    pub max_interactive_elements: usize,
+    /// Gives the agent `upload_file` for files sent with the request
+    pub file_upload: bool,
+    /// Origins allowed by CORS; empty allows any origin
+    pub cors_origins: Vec<HeaderValue>,
+    /// How long identical no-tools requests reuse a cached answer; zero disables caching
+    pub response_cache_ttl: Duration,
+    /// Idle time after which SSE responses send a keepalive comment; zero disables them
+    pub sse_keepalive: Duration,
+    /// How streamed answers are regrouped into SSE events
+    pub stream_granularity: StreamGranularity,
 }
 
 impl AppConfig {
+    /// Loads `.env` and the process environment, panicking on invalid values
+    /// so misconfiguration stops the server at startup.
     pub fn from_env() -> Self {
         dotenvy::dotenv().ok();
 
-        // Validate that GEMINI_API_KEY is set (required by rig gemini client)
-        if env::var("GEMINI_API_KEY").is_err() {
-            panic!("GEMINI_API_KEY environment variable is required");
-        }
+        Self::from_vars(|key| env::var(key).ok())
+            .unwrap_or_else(|err| panic!("Invalid configuration: {}", err))
+    }
+
+    /// Parses and validates the configuration from `lookup`, which returns
+    /// the raw value of a variable if it is set.
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let vars = Vars(lookup);
 
+        let llm_provider = match vars.non_empty("LLM_PROVIDER").as_deref().map(str::trim) {
+            None | Some("gemini") => LlmProvider::Gemini,
+            Some("mock") => LlmProvider::Mock,
+            Some(other) => return Err(format!("Unsupported LLM_PROVIDER: {}", other)),
+        };
+        let gemini_api_key = match (vars.non_empty("GEMINI_API_KEY"), llm_provider) {
+            (Some(key), _) => key,
+            (None, LlmProvider::Mock) => "mock".to_string(),
+            (None, LlmProvider::Gemini) => {
+                return Err("GEMINI_API_KEY environment variable is required".to_string());
+            }
+        };
+
+        Ok(Self {
+            port: vars.number("PORT", 3000)?,
+            llm_provider,
+            gemini_api_key,
+            gemini_base_url: vars.non_empty("GEMINI_BASE_URL"),
+            response_prefix: vars.non_empty("RESPONSE_PREFIX"),
+            response_suffix: vars.non_empty("RESPONSE_SUFFIX"),
+            default_image_type: match vars.non_empty("DEFAULT_IMAGE_MIME") {
+                Some(mime) => ImageMediaType::from_mime_type(&mime)
+                    .ok_or_else(|| format!("Unsupported DEFAULT_IMAGE_MIME: {}", mime))?,
+                None => ImageMediaType::JPEG,
+            },
+            http: http_client_config(&vars)?,
+            tool_min_delay: Duration::from_millis(vars.number("TOOL_MIN_DELAY_MS", 0)?),
+            routing: routing_rules(&vars)?,
+            disabled_tools: vars.list("DISABLED_TOOLS"),
+            allowed_domains: vars.list("AGENT_ALLOWED_DOMAINS"),
+            blocked_domains: vars.list("NAV_BLOCKLIST"),
+            max_interactive_elements: max_interactive_elements(&vars)?,
+            file_upload: vars
+                .non_empty("ENABLE_FILE_UPLOAD")
+                .is_some_and(|value| value == "true" || value == "1"),
+            cors_origins: cors_origins(&vars)?,
+            response_cache_ttl: vars.secs("RESPONSE_CACHE_TTL_SECS", 0)?,
+            sse_keepalive: vars.secs("SSE_KEEPALIVE_SECS", 15)?,
+            stream_granularity: stream_granularity(&vars)?,
+        })
+    }
+}
+
+/// Backend for model calls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LlmProvider {
+    Gemini,
+    /// Canned, deterministic replies from a local stand-in for the Gemini API
+    Mock,
+}
+
+#[cfg(test)]
+impl AppConfig {
+    /// Defaults without reading the environment.
+    pub fn for_tests() -> Self {
         Self {
-            port: env::var("PORT")
-                .unwrap_or_else(|_| "3000".to_string())
+            port: 3000,
+            llm_provider: LlmProvider::Gemini,
+            gemini_api_key: "test-key".to_string(),
+            gemini_base_url: None,
+            response_prefix: None,
+            response_suffix: None,
+            default_image_type: ImageMediaType::JPEG,
+            http: HttpClientConfig::default(),
+            tool_min_delay: Duration::ZERO,
+            routing: RoutingRules::default(),
+            disabled_tools: Vec::new(),
+            allowed_domains: Vec::new(),
+            blocked_domains: Vec::new(),
+            max_interactive_elements: DEFAULT_MAX_INTERACTIVE_ELEMENTS,
+            file_upload: false,
+            cors_origins: Vec::new(),
+            response_cache_ttl: Duration::ZERO,
+            sse_keepalive: Duration::from_secs(15),
+            stream_granularity: StreamGranularity::Token,
+        }
+    }
+}
+
+const DEFAULT_MAX_INTERACTIVE_ELEMENTS: usize = 100;
+
+fn max_interactive_elements<F: Fn(&str) -> Option<String>>(
+    vars: &Vars<F>,
+) -> Result<usize, String> {
+    let max = vars.number("MAX_INTERACTIVE_ELEMENTS", DEFAULT_MAX_INTERACTIVE_ELEMENTS)?;
+    if max == 0 {
+        return Err("MAX_INTERACTIVE_ELEMENTS must be greater than zero".to_string());
+    }
+    Ok(max)
+}
+
+/// `token` (default), `sentence`, or a number of characters per chunk.
+fn stream_granularity<F: Fn(&str) -> Option<String>>(
+    vars: &Vars<F>,
+) -> Result<StreamGranularity, String> {
+    match vars
+        .non_empty("STREAM_GRANULARITY")
+        .as_deref()
+        .map(str::trim)
+    {
+        None | Some("token") => Ok(StreamGranularity::Token),
+        Some("sentence") => Ok(StreamGranularity::Sentence),
+        Some(value) => match value.parse::<usize>() {
+            Ok(size) if size > 0 => Ok(StreamGranularity::Chars(size)),
+            _ => Err(format!("Invalid STREAM_GRANULARITY: {}", value)),
+        },
+    }
+}
+
+fn http_client_config<F: Fn(&str) -> Option<String>>(
+    vars: &Vars<F>,
+) -> Result<HttpClientConfig, String> {
+    let defaults = HttpClientConfig::default();
+    let request_timeout = vars.secs("HTTP_REQUEST_TIMEOUT_SECS", 120)?;
+    if request_timeout.is_zero() {
+        return Err("HTTP_REQUEST_TIMEOUT_SECS must be greater than zero".to_string());
+    }
+
+    Ok(HttpClientConfig {
+        connect_timeout: vars.secs("HTTP_CONNECT_TIMEOUT_SECS", 10)?,
+        request_timeout,
+        user_agent: vars
+            .non_empty("HTTP_USER_AGENT")
+            .unwrap_or(defaults.user_agent),
+        headers: match vars.non_empty("HTTP_EXTRA_HEADERS") {
+            Some(value) => parse_headers(&value)?,
+            None => HeaderMap::new(),
+        },
+    })
+}
+
+/// Parses `Name: value` pairs separated by commas.
+fn parse_headers(value: &str) -> Result<HeaderMap, String> {
+    value
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (name, value) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid header in HTTP_EXTRA_HEADERS: {}", pair))?;
+            let name = HeaderName::try_from(name.trim())
+                .map_err(|_| format!("Invalid header name: {}", name.trim()))?;
+            let value = HeaderValue::try_from(value.trim())
+                .map_err(|_| format!("Invalid value for header {}", name))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn routing_rules<F: Fn(&str) -> Option<String>>(vars: &Vars<F>) -> Result<RoutingRules, String> {
+    let defaults = RoutingRules::default();
+    Ok(RoutingRules {
+        flash_model: vars
+            .non_empty("FLASH_MODEL")
+            .unwrap_or(defaults.flash_model),
+        pro_model: vars.non_empty("PRO_MODEL").unwrap_or(defaults.pro_model),
+        pro_min_chars: vars.number("ROUTE_PRO_MIN_CHARS", defaults.pro_min_chars)?,
+        pro_on_image: vars
+            .non_empty("ROUTE_PRO_ON_IMAGE")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(defaults.pro_on_image),
+        allowed_models: vars.list("ALLOWED_MODELS"),
+    })
+}
+
+/// Origins allowed by CORS, each a full origin such as `chrome-extension://<id>`.
+fn cors_origins<F: Fn(&str) -> Option<String>>(vars: &Vars<F>) -> Result<Vec<HeaderValue>, String> {
+    vars.list("CORS_ALLOWED_ORIGINS")
+        .into_iter()
+        .map(|origin| {
+            if !origin.contains("://") {
+                return Err(format!(
+                    "Invalid origin in CORS_ALLOWED_ORIGINS: {}",
+                    origin
+                ));
+            }
+            HeaderValue::try_from(origin.as_str())
+                .map_err(|_| format!("Invalid origin in CORS_ALLOWED_ORIGINS: {}", origin))
+        })
+        .collect()
+}
+
+/// Raw configuration values, looked up by variable name.
+struct Vars<F>(F);
+
+impl<F: Fn(&str) -> Option<String>> Vars<F> {
+    fn non_empty(&self, key: &str) -> Option<String> {
+        (self.0)(key).filter(|value| !value.trim().is_empty())
+    }
+
+    /// Reads a comma-separated list, empty when unset.
+    fn list(&self, key: &str) -> Vec<String> {
+        self.non_empty(key)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn number<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        match self.non_empty(key) {
+            Some(value) => value
+                .trim()
                 .parse()
-                .unwrap(),
+                .map_err(|_| format!("{} must be a number, got {:?}", key, value)),
+            None => Ok(default),
+        }
+    }
+
+    fn secs(&self, key: &str, default: u64) -> Result<Duration, String> {
+        self.number(key, default).map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<AppConfig, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        AppConfig::from_vars(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_defaults_with_only_api_key() {
+        let config = parse(&[("GEMINI_API_KEY", "key")]).unwrap();
+
+        assert_eq!(config.port, 3000);
+        assert_eq!(config.gemini_api_key, "key");
+        assert!(config.gemini_base_url.is_none());
+        assert!(matches!(config.default_image_type, ImageMediaType::JPEG));
+        assert_eq!(config.http.connect_timeout, Duration::from_secs(10));
+        assert_eq!(config.http.request_timeout, Duration::from_secs(120));
+        assert_eq!(config.tool_min_delay, Duration::ZERO);
+        assert_eq!(
+            config.routing.flash_model,
+            RoutingRules::default().flash_model
+        );
+        assert!(config.disabled_tools.is_empty());
+        assert_eq!(config.max_interactive_elements, 100);
+        assert!(!config.file_upload);
+        assert!(config.cors_origins.is_empty());
+        assert_eq!(config.response_cache_ttl, Duration::ZERO);
+        assert_eq!(config.sse_keepalive, Duration::from_secs(15));
+        assert_eq!(config.stream_granularity, StreamGranularity::Token);
+    }
+
+    #[test]
+    fn test_full_config() {
+        let config = parse(&[
+            ("GEMINI_API_KEY", "key"),
+            ("PORT", "8080"),
+            ("GEMINI_BASE_URL", "http://proxy.local"),
+            ("RESPONSE_PREFIX", "Note:"),
+            ("DEFAULT_IMAGE_MIME", "image/png"),
+            ("HTTP_CONNECT_TIMEOUT_SECS", "5"),
+            ("HTTP_REQUEST_TIMEOUT_SECS", "60"),
+            ("HTTP_USER_AGENT", "tests/1.0"),
+            ("HTTP_EXTRA_HEADERS", "X-Project: browser-ai, X-Team: qa"),
+            ("TOOL_MIN_DELAY_MS", "250"),
+            ("FLASH_MODEL", "flash-x"),
+            ("PRO_MODEL", "pro-x"),
+            ("ROUTE_PRO_MIN_CHARS", "800"),
+            ("ROUTE_PRO_ON_IMAGE", "true"),
+            ("ALLOWED_MODELS", "flash-x, pro-x"),
+            ("DISABLED_TOOLS", "handle_dialog,,capture_region"),
+            ("AGENT_ALLOWED_DOMAINS", "*.example.com"),
+            ("NAV_BLOCKLIST", "admin.example.com, *.facebook.com"),
+            ("MAX_INTERACTIVE_ELEMENTS", "40"),
+            ("ENABLE_FILE_UPLOAD", "true"),
+            (
+                "CORS_ALLOWED_ORIGINS",
+                "chrome-extension://abc, http://localhost:5173",
+            ),
+            ("RESPONSE_CACHE_TTL_SECS", "300"),
+            ("SSE_KEEPALIVE_SECS", "0"),
+            ("STREAM_GRANULARITY", "40"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.port, 8080);
+        assert_eq!(
+            config.gemini_base_url.as_deref(),
+            Some("http://proxy.local")
+        );
+        assert_eq!(config.response_prefix.as_deref(), Some("Note:"));
+        assert!(matches!(config.default_image_type, ImageMediaType::PNG));
+        assert_eq!(config.http.connect_timeout, Duration::from_secs(5));
+        assert_eq!(config.http.request_timeout, Duration::from_secs(60));
+        assert_eq!(config.http.user_agent, "tests/1.0");
+        assert_eq!(config.http.headers["x-team"], "qa");
+        assert_eq!(config.tool_min_delay, Duration::from_millis(250));
+        assert_eq!(config.routing.pro_model, "pro-x");
+        assert_eq!(config.routing.pro_min_chars, 800);
+        assert!(config.routing.pro_on_image);
+        assert_eq!(config.routing.allowed_models, vec!["flash-x", "pro-x"]);
+        assert_eq!(
+            config.disabled_tools,
+            vec!["handle_dialog", "capture_region"]
+        );
+        assert_eq!(config.allowed_domains, vec!["*.example.com"]);
+        assert_eq!(
+            config.blocked_domains,
+            vec!["admin.example.com", "*.facebook.com"]
+        );
+        assert_eq!(config.max_interactive_elements, 40);
+        assert!(config.file_upload);
+        let origins: Vec<_> = config
+            .cors_origins
+            .iter()
+            .map(|origin| origin.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            origins,
+            vec!["chrome-extension://abc", "http://localhost:5173"]
+        );
+        assert_eq!(config.response_cache_ttl, Duration::from_secs(300));
+        assert_eq!(config.sse_keepalive, Duration::ZERO);
+        assert_eq!(config.stream_granularity, StreamGranularity::Chars(40));
+    }
+
+    #[test]
+    fn test_missing_or_blank_api_key_is_rejected() {
+        assert!(parse(&[]).unwrap_err().contains("GEMINI_API_KEY"));
+        assert!(
+            parse(&[("GEMINI_API_KEY", "  ")])
+                .unwrap_err()
+                .contains("GEMINI_API_KEY")
+        );
+    }
+
+    #[test]
+    fn test_mock_provider_needs_no_api_key() {
+        let config = parse(&[("LLM_PROVIDER", "mock")]).unwrap();
+        assert_eq!(config.llm_provider, LlmProvider::Mock);
+        assert_eq!(config.gemini_api_key, "mock");
+    }
+
+    #[test]
+    fn test_invalid_values_name_the_variable() {
+        let cases = [
+            ("PORT", "eighty"),
+            ("TOOL_MIN_DELAY_MS", "-1"),
+            ("ROUTE_PRO_MIN_CHARS", "many"),
+            ("RESPONSE_CACHE_TTL_SECS", "5m"),
+            ("HTTP_REQUEST_TIMEOUT_SECS", "0"),
+            ("DEFAULT_IMAGE_MIME", "image/heic"),
+            ("HTTP_EXTRA_HEADERS", "no-colon"),
+            ("CORS_ALLOWED_ORIGINS", "localhost"),
+            ("STREAM_GRANULARITY", "word"),
+            ("MAX_INTERACTIVE_ELEMENTS", "0"),
+            ("LLM_PROVIDER", "openai"),
+        ];
+
+        for (key, value) in cases {
+            let err = parse(&[("GEMINI_API_KEY", "key"), (key, value)]).unwrap_err();
+            assert!(
+                err.contains(key) || err.contains(value),
+                "{}={} gave {:?}",
+                key,
+                value,
+                err
+            );
         }
     }
 }
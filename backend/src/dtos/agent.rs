@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AgentRequest {
@@ -7,11 +7,126 @@ pub struct AgentRequest {
     pub session_id: Option<String>,
     #[serde(default)]
     pub stream: bool,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
     pub image: Option<String>,
     pub custom_instruction: Option<String>,
     pub interactive_elements: Option<Vec<InteractiveElementDto>>,
     pub page_content: Option<String>,
+    pub page_url: Option<String>,
+    /// Text the user selected on the page; preferred over `page_content` when present
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub selection: Option<String>,
     pub history: Option<Vec<ChatMessageDto>>,
+    /// Set to false to skip the tool loop and answer with a single model call
+    #[serde(default = "default_true")]
+    pub tools: bool,
+    #[serde(default)]
+    pub verbosity: Verbosity,
+    pub format: Option<ResponseFormat>,
+    /// Private session: nothing from this request is stored server-side
+    #[serde(default)]
+    pub incognito: bool,
+    /// Hint for model routing; overrides the length and image rules
+    pub difficulty: Option<Difficulty>,
+    /// Model to use instead of the routed one; must be in `ALLOWED_MODELS`
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub model: Option<String>,
+    /// Language (code or name) to translate the page and answer into
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub translate_to: Option<String>,
+    /// Generation seed for more repeatable answers (not guaranteed by Gemini)
+    pub seed: Option<i64>,
+    /// Report only the outcome, without narrating tool calls or element refs
+    #[serde(default)]
+    pub concise_actions: bool,
+    /// Set to false to leave the page content and selection out of this request
+    #[serde(default = "default_true")]
+    pub use_content: bool,
+    /// Set to false to leave the screenshot out of this request
+    #[serde(default = "default_true")]
+    pub use_screenshot: bool,
+    /// Return the model's reasoning separately from the answer (non-streaming
+    /// answers without tools only)
+    #[serde(default)]
+    pub show_reasoning: bool,
+    /// What the agent does after a browser tool fails
+    #[serde(default)]
+    pub on_tool_error: OnToolError,
+    /// Read-only session: the agent only gets tools that observe the page
+    #[serde(default)]
+    pub safe_mode: bool,
+    /// Files the agent may attach to file inputs (needs `ENABLE_FILE_UPLOAD`)
+    pub files: Option<Vec<AttachedFileDto>>,
+}
+
+/// A file sent with the request for the `upload_file` tool.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachedFileDto {
+    pub name: String,
+    /// MIME type; taken from `data` when it is a data URL and this is absent
+    pub mime_type: Option<String>,
+    /// Content as a data URL or bare base64
+    pub data: String,
+}
+
+/// How much the agent should say about what it did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Terse,
+    #[default]
+    Normal,
+    Detailed,
+}
+
+/// What the agent does after a browser tool fails.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnToolError {
+    /// Let the model see the error and carry on
+    #[default]
+    Continue,
+    /// End the run, reporting the error after the progress so far
+    Stop,
+    /// End the run with a question on how to proceed; it can be resumed
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    Text,
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Hard,
+}
+
+impl AgentRequest {
+    /// Whether to run the tool-enabled agent (requires a WebSocket session).
+    pub fn wants_tools(&self) -> bool {
+        self.tools && self.session_id.is_some()
+    }
+
+    /// Drops the context pieces the request opted out of, so neither the
+    /// prompt, the cache key nor the audit log sees them.
+    pub fn strip_unused_context(&mut self) {
+        if !self.use_content {
+            self.page_content = None;
+            self.selection = None;
+        }
+        if !self.use_screenshot {
+            self.image = None;
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -26,3 +141,12 @@ pub struct InteractiveElementDto {
     pub role: String,
     pub name: String,
 }
+
+/// Treats empty or whitespace-only strings (e.g. `"image": ""`) as absent.
+pub(crate) fn empty_string_as_none<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.filter(|s| !s.trim().is_empty()))
+}
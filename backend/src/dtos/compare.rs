@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use super::AgentRequest;
+
+/// Most models a single comparison may run.
+pub const MAX_COMPARE_MODELS: usize = 4;
+
+#[derive(Debug, Deserialize)]
+pub struct CompareRequest {
+    /// The usual chat fields; `model` is ignored in favor of `models`
+    #[serde(flatten)]
+    pub request: AgentRequest,
+    pub models: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareResponse {
+    /// One result per model, in the order the models were given
+    pub results: Vec<ModelResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelResult {
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// Why this model failed; the other models' results are still returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Set for failures clients handle differently, e.g. `quota_exceeded`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+}
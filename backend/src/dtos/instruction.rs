@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct ValidateInstructionRequest {
+    pub instruction: String,
+}
+
+/// Sent for an accepted instruction; a rejected one gets an error response.
+#[derive(Debug, Serialize)]
+pub struct ValidateInstructionResponse {
+    pub valid: bool,
+    /// Length in characters
+    pub length: usize,
+    pub max_length: usize,
+}
@@ -1,3 +1,6 @@
 pub mod agent;
+pub mod compare;
+pub mod instruction;
+pub mod vision;
 
 pub use agent::AgentRequest;
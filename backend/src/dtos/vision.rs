@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use super::agent::empty_string_as_none;
+
+#[derive(Debug, Deserialize)]
+pub struct VisionRequest {
+    /// Image as a data URL or bare base64
+    pub image: String,
+    /// What to ask about the image; describes it when absent
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub question: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VisionResponse {
+    pub description: String,
+}
@@ -1 +1,270 @@
-// Error types module - reserved for future error handling implementations
+//! Uniform error responses.
+//!
+//! Handlers return errors in different shapes (plain strings, bare status
+//! codes, axum extractor rejections). The `uniform_errors` middleware rewrites
+//! every non-JSON error response into a single JSON body:
+//!
+//! ```json
+//! { "error": "internal_server_error", "message": "...", "request_id": "..." }
+//! ```
+//!
+//! Bodies that fail to parse as JSON are rejected by `ApiJson` with the same
+//! shape plus the `line`, `column` and byte `offset` of the problem. Handlers
+//! return an `ApiError` to set their own `error` code and `retry_after_secs`.
+
+use axum::{
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Header used to correlate an error body with server logs.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Largest error body that is read back into the `message` field.
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub error: String,
+    pub message: String,
+    pub request_id: String,
+    /// Where a request body failed to parse
+    #[serde(flatten)]
+    pub location: Option<JsonLocation>,
+    /// How long to wait before retrying, also sent as `Retry-After`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+/// Position in a JSON body: 1-based line and column, and 0-based byte offset.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JsonLocation {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+/// A handler error with its own `error` code, for failures clients handle
+/// differently from others with the same status (e.g. `quota_exceeded`).
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    /// Replaces the code derived from the status
+    pub code: Option<&'static str>,
+    pub retry_after: Option<Duration>,
+}
+
+impl From<(StatusCode, String)> for ApiError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        Self {
+            status,
+            message,
+            code: None,
+            retry_after: None,
+        }
+    }
+}
+
+/// What `uniform_errors` adds to the body of an `ApiError` response.
+#[derive(Clone, Copy)]
+struct ErrorDetails {
+    code: Option<&'static str>,
+    retry_after_secs: Option<u64>,
+}
+
+impl ApiError {
+    /// `retry_after` in whole seconds, rounded up.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        self.retry_after
+            .map(|delay| delay.as_millis().div_ceil(1000) as u64)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after_secs = self.retry_after_secs();
+        let mut response = (self.status, self.message).into_response();
+        response.extensions_mut().insert(ErrorDetails {
+            code: self.code,
+            retry_after_secs,
+        });
+        if let Some(secs) = retry_after_secs {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+/// The request's `X-Request-Id`, or a new one.
+pub fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Middleware converting error responses into an `ErrorBody`, keeping their status.
+pub async fn uniform_errors(request: Request, next: Next) -> Response {
+    let request_id = request_id(request.headers());
+
+    let response = next.run(request).await;
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) || is_json(&response) {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = to_bytes(body, MAX_ERROR_BODY_BYTES)
+        .await
+        .unwrap_or_default();
+    let reason = status.canonical_reason().unwrap_or("Error");
+    let message = match String::from_utf8_lossy(&bytes).trim() {
+        "" => reason.to_string(),
+        text => text.to_string(),
+    };
+
+    if status.is_server_error() {
+        tracing::error!("Request {} failed with {}: {}", request_id, status, message);
+    }
+
+    let details = parts.extensions.get::<ErrorDetails>().copied();
+    let body = ErrorBody {
+        error: match details.and_then(|details| details.code) {
+            Some(code) => code.to_string(),
+            None => reason.to_lowercase().replace([' ', '-'], "_"),
+        },
+        message,
+        request_id: request_id.clone(),
+        location: None,
+        retry_after_secs: details.and_then(|details| details.retry_after_secs),
+    };
+
+    let mut response = (status, axum::Json(body)).into_response();
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    // Preserve headers such as CORS, but not ones describing the old body
+    for (name, value) in parts.headers.iter() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            response.headers_mut().entry(name).or_insert(value.clone());
+        }
+    }
+    response
+}
+
+fn is_json(response: &Response<Body>) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, http::StatusCode, middleware, routing::get};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/bare", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .route(
+                "/text",
+                get(|| async { (StatusCode::BAD_GATEWAY, "upstream failed") }),
+            )
+            .route("/ok", get(|| async { "fine" }))
+            .route(
+                "/quota",
+                get(|| async {
+                    ApiError {
+                        status: StatusCode::TOO_MANY_REQUESTS,
+                        message: "Quota exceeded".to_string(),
+                        code: Some("quota_exceeded"),
+                        retry_after: Some(Duration::from_millis(36_200)),
+                    }
+                }),
+            )
+            .layer(middleware::from_fn(uniform_errors))
+    }
+
+    async fn send(uri: &str, request_id: Option<&str>) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, id);
+        }
+        let response = app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+        (status, json)
+    }
+
+    #[tokio::test]
+    async fn test_bare_status_gets_uniform_body() {
+        let (status, body) = send("/bare", Some("req-1")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error": "internal_server_error",
+                "message": "Internal Server Error",
+                "request_id": "req-1"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_text_error_becomes_message() {
+        let (status, body) = send("/text", None).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body["error"], "bad_gateway");
+        assert_eq!(body["message"], "upstream failed");
+        assert!(!body["request_id"].as_str().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_error_sets_code_and_retry_after() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/quota")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "37");
+
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "quota_exceeded");
+        assert_eq!(body["message"], "Quota exceeded");
+        assert_eq!(body["retry_after_secs"], 37);
+
+        // Other errors don't mention a retry
+        let (_, body) = send("/text", None).await;
+        assert!(body.get("retry_after_secs").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_success_is_untouched() {
+        let (status, body) = send("/ok", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::Value::Null);
+    }
+}
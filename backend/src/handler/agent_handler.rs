@@ -1,46 +1,141 @@
 use async_stream::stream;
 use axum::{
-    extract::{Json, State},
-    http::StatusCode,
-    response::{
-        IntoResponse,
-        sse::{Event, Sse},
-    },
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response, sse::Event},
 };
 use futures::StreamExt;
 use rig::OneOrMany;
-use rig::agent::MultiTurnStreamItem;
-use rig::client::{CompletionClient, ProviderClient};
-use rig::completion::GetTokenUsage;
-use rig::message::{AssistantContent, ImageMediaType, Message, UserContent};
-use rig::streaming::{StreamedAssistantContent, StreamingChat};
+use rig::agent::{AgentBuilder, MultiTurnStreamItem, StreamingError};
+use rig::client::CompletionClient;
+use rig::completion::{GetTokenUsage, PromptError};
+use rig::message::{AssistantContent, Message, ToolResultContent, UserContent};
+use rig::streaming::{StreamedAssistantContent, StreamedUserContent, StreamingChat};
 
-use rig::providers::gemini;
-
-use crate::tools::websocket::{
-    WsClickTool, WsGetInteractiveElementsTool, WsGetPageContentTool, WsNavigateTool, WsScrollTool,
-    WsTypeTool,
-};
+use crate::agent::context::PageText;
+use crate::agent::guard::ToolCallGuard;
+use crate::agent::instruction::validate_instruction;
+use crate::agent::intent::{KeywordClassifier, needs_tools};
+use crate::agent::language::{request_translation, translation_source};
+use crate::agent::preamble::{PreambleOptions, build_preamble};
+use crate::agent::reasoning::{REASONING_INSTRUCTION, split_reasoning};
+use crate::agent::steps::StepRecorder;
+use crate::tools::registry::{build_tools, read_only};
+use crate::tools::websocket::{MAX_UPLOAD_TOTAL_BYTES, attached_files_size};
+use std::collections::HashMap;
 use std::sync::Arc;
+use uuid::Uuid;
 
 use crate::dtos::AgentRequest;
+use crate::error::ApiError;
+use crate::llm::cache::ResponseCache;
+use crate::llm::gemini_error::{model_call_error, quota_exceeded_event, redact_api_key};
+use crate::llm::image_relay::{ImageRelay, PendingImages};
+use crate::llm::{generation_params, is_avif, prepare_image, unsupported_image_error};
 use crate::models::ChatResponse;
+use crate::models::audit::{AuditEntry, unix_now};
+use crate::models::run::AgentRun;
 use crate::state::AppState;
+use crate::utils::json::ApiJson;
+use crate::utils::response::{WRAP_SEPARATOR, wrap_response};
+use crate::utils::streaming::{ChunkBuffer, sse_response};
 
 // --- Main Handler ---
 
 pub async fn run_agent(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<AgentRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<AgentRequest>,
+) -> Result<impl IntoResponse, ApiError> {
     tracing::info!(
         "Agent request: {} (session_id: {:?})",
-        request.query,
+        if request.incognito {
+            "<incognito>"
+        } else {
+            &request.query
+        },
         request.session_id
     );
+    if let Some(instruction) = &request.custom_instruction {
+        validate_instruction(instruction)?;
+    }
+    if request.image.as_deref().is_some_and(is_avif) {
+        return Err(unsupported_image_error());
+    }
+    if let Some(files) = &request.files {
+        let size = attached_files_size(files);
+        if size > MAX_UPLOAD_TOTAL_BYTES {
+            return Err(ApiError {
+                status: StatusCode::PAYLOAD_TOO_LARGE,
+                message: format!(
+                    "The attached files total {} bytes; at most {} bytes are allowed",
+                    size, MAX_UPLOAD_TOTAL_BYTES
+                ),
+                code: Some("files_too_large"),
+                retry_after: None,
+            });
+        }
+    }
+
+    execute_agent(state, &headers, request, None).await
+}
+
+/// Continues a run after the user finished a step manually, reusing its stored context.
+pub async fn resume_agent(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let run = state
+        .agent_runs
+        .read()
+        .await
+        .get(&run_id)
+        .cloned()
+        .ok_or((StatusCode::NOT_FOUND, format!("Unknown run: {}", run_id)))?;
+
+    if state.get_connection(&run.session_id).await.is_none() {
+        return Err(ApiError::from((
+            StatusCode::CONFLICT,
+            "The browser session for this run is no longer connected".to_string(),
+        )));
+    }
+
+    tracing::info!(
+        "Resuming agent run {} (session_id: {})",
+        run_id,
+        run.session_id
+    );
+
+    execute_agent(state, &headers, run.resume_request(), Some(run_id)).await
+}
+
+/// Runs the agent for a request. `run_id` is set when resuming an existing run.
+///
+/// Model calls run inside this future or the returned SSE stream and are never
+/// spawned, so when the client disconnects axum drops them and the in-flight
+/// Gemini request is aborted instead of running to completion.
+async fn execute_agent(
+    state: Arc<AppState>,
+    headers: &HeaderMap,
+    mut request: AgentRequest,
+    run_id: Option<String>,
+) -> Result<Response, ApiError> {
+    request.strip_unused_context();
+    let (gemini, llm) = state.gemini_for_request(headers);
+    let llm = llm.with_seed(request.seed);
+    let model = state
+        .config
+        .routing
+        .select_model(&request)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    // If session_id is provided, use the tool-enabled agent with STREAMING
-    if let Some(session_id) = &request.session_id {
+    // If session_id is provided (and tools aren't disabled), use the tool-enabled agent with STREAMING
+    if let Some(session_id) = request
+        .session_id
+        .as_ref()
+        .filter(|_| request.wants_tools())
+    {
         tracing::info!(
             "Using streaming tool-enabled agent with session_id: {}",
             session_id
@@ -67,67 +162,104 @@ pub async fn run_agent(
             vec![]
         };
 
-        let client = gemini::Client::from_env();
-
-        let preamble = r#"You are a browser automation assistant. You can control the browser using tools AND see/analyze screenshots.
-
-## Available Tools
-### Action Tools
-- `navigate_to(url)`: Navigate to a URL (e.g., "https://google.com")
-- `click_element(ref)`: Click an element using its Ref ID number
-- `type_text(ref, text)`: Type text into an input field using its Ref ID
-- `scroll_to(x, y)`: Scroll the page to coordinates
-
-### Context Tools (use these FIRST when needed)
-- `get_interactive_elements(limit)`: Scan page for buttons, inputs, links. **CALL THIS FIRST** before clicking or typing.
-- `get_page_content(max_length)`: Get page text content. Use when you need to read, summarize, or analyze text.
-
-## Your Capabilities
-1. **Browser Automation**: Control the browser using action tools
-2. **Visual Analysis**: When screenshot is provided, you CAN SEE and READ everything visible on screen
-3. **Dynamic Context**: Use context tools to get page data when needed
-
-## Instructions
-1. **Before clicking/typing**: Call `get_interactive_elements()` to find element Ref IDs
-2. **Before reading/summarizing**: Call `get_page_content()` to get page text
-3. When the user asks to go to a website, use `navigate_to`
-4. When the user asks about the page content (with screenshot), read the screenshot OR call `get_page_content()`
-5. Always respond with a brief confirmation of what you did
-
-## Example Flows
-- User: "klik tombol login" → Call get_interactive_elements() → Find login button Ref ID → Call click_element(ref)
-- User: "rangkum halaman ini" → Call get_page_content() → Summarize the returned text
-- User: "buka google" → Call navigate_to("https://google.com")
-"#.to_string();
-
-        let agent = client
-            .agent(gemini::completion::GEMINI_2_5_FLASH)
-            .preamble(&preamble)
-            .tool(WsNavigateTool::new(state.clone(), session_id.clone()))
-            .tool(WsClickTool::new(state.clone(), session_id.clone()))
-            .tool(WsTypeTool::new(state.clone(), session_id.clone()))
-            .tool(WsScrollTool::new(state.clone(), session_id.clone()))
-            .tool(WsGetPageContentTool::new(state.clone(), session_id.clone()))
-            .tool(WsGetInteractiveElementsTool::new(
-                state.clone(),
-                session_id.clone(),
-            ))
-            .default_max_depth(20)
-            .build();
+        // Roles of elements the client already scanned, used to validate type_text/clear_field/set_checked targets
+        let element_roles: HashMap<i32, String> = request
+            .interactive_elements
+            .iter()
+            .flatten()
+            .map(|e| (e.id as i32, e.role.clone()))
+            .collect();
+
+        // Questions about the attached page are answered without browser tools,
+        // under a preamble that doesn't mention any
+        let use_tools = needs_tools(&KeywordClassifier, &request);
+        let preamble = build_preamble(&PreambleOptions {
+            without_tools: !use_tools,
+            ..PreambleOptions::from_request(&request)
+        });
+
+        // Keep the run's context so it can be resumed after a manual step
+        // (not for incognito requests, which can't be resumed)
+        let run_id = match run_id {
+            Some(run_id) => run_id,
+            None if request.incognito => Uuid::new_v4().to_string(),
+            None => {
+                let run_id = Uuid::new_v4().to_string();
+                state
+                    .agent_runs
+                    .write()
+                    .await
+                    .insert(run_id.clone(), AgentRun::new(session_id, &request));
+                run_id
+            }
+        };
+
+        // Screenshots the tools capture, sent to the agent's model with its next call
+        let images = PendingImages::default();
+        let tools = if use_tools {
+            let files = request.files.as_deref().unwrap_or_default();
+            let tools = build_tools(&state, session_id, element_roles, files, &images);
+            if request.safe_mode {
+                read_only(tools)
+            } else {
+                tools
+            }
+        } else {
+            tracing::info!("Query classified as a question, skipping tool registration");
+            Vec::new()
+        };
+        let guard = ToolCallGuard::new(tools.iter().map(|tool| tool.name()))
+            .with_error_policy(request.on_tool_error);
+        let tools = guard.wrap_tools(tools);
+        let run_guard = guard.clone();
+        let mut agent_builder =
+            AgentBuilder::new(ImageRelay::new(gemini.completion_model(&model), images))
+                .preamble(&preamble)
+                .tools(tools)
+                .default_max_depth(20);
+        if let Some(params) = generation_params(request.seed) {
+            agent_builder = agent_builder.additional_params(params);
+        }
+        let agent = agent_builder.build();
+
+        // Attach the user's selection in place of the full page content when present,
+        // otherwise the page content. It goes with every turn, since the client's
+        // history keeps only the typed messages.
+        let page_text = PageText::of(&request);
+        let mut query = match page_text {
+            Some(page_text) => page_text.attach_to(&request.query),
+            None => request.query.clone(),
+        };
+
+        // For revisited pages, tell the model what changed since the last snapshot
+        if let (Some(PageText::Content(_)), Some(content), Some(url)) = (
+            page_text,
+            request.page_content.as_deref(),
+            request.page_url.as_ref().filter(|_| !request.incognito),
+        ) {
+            let diff = state.page_snapshots.write().await.record(url, content);
+            if let Some(diff) = diff {
+                query.push_str(&format!("\n\n{}", diff.to_prompt()));
+            }
+        }
+
+        // Audit what page data goes to the model (metadata only)
+        let history_messages = chat_history.len();
+        if let Some(entry) =
+            AuditEntry::new(&request, &model, page_text, history_messages, unix_now())
+        {
+            state.audit_log.write().await.record(entry);
+        }
 
         // Build the prompt - either text-only or text+image
         let user_message: Message = if let Some(image_data) = &request.image {
-            // Strip data URL prefix if present
-            let base64_data = if let Some(pos) = image_data.find(",") {
-                &image_data[pos + 1..]
-            } else {
-                image_data.as_str()
-            };
+            let (media_type, base64_data) =
+                prepare_image(image_data, state.config.default_image_type.clone());
 
-            let mut content_parts = vec![UserContent::text(&request.query)];
+            let mut content_parts = vec![UserContent::text(&query)];
             content_parts.push(UserContent::image_base64(
                 base64_data,
-                Some(ImageMediaType::JPEG),
+                Some(media_type),
                 None,
             ));
 
@@ -136,29 +268,66 @@ pub async fn run_agent(
             }
         } else {
             Message::User {
-                content: OneOrMany::one(UserContent::text(&request.query)),
+                content: OneOrMany::one(UserContent::text(&query)),
             }
         };
 
         // Use stream_chat for streaming with tools
-        let mut agent_stream = agent.stream_chat(user_message, chat_history).await;
+        let mut agent_stream = agent
+            .stream_chat(user_message, chat_history)
+            .with_hook(guard)
+            .await;
+
+        let response_prefix = state.config.response_prefix.clone();
+        let response_suffix = state.config.response_suffix.clone();
+        let run_state = state.clone();
+        let run_query = request.query.clone();
+        let granularity = state.config.stream_granularity;
 
         let sse_stream = stream! {
             let mut full_response = String::new();
+            let mut chunks = ChunkBuffer::new(granularity);
             let mut token_usage: Option<(u64, u64, u64)> = None;
+            let mut steps = StepRecorder::new();
+
+            let run_info = format!(r#"{{"__type":"run","run_id":"{}"}}"#, run_id);
+            yield Ok::<_, String>(Event::default().event("run").data(run_info));
+
+            if let Some(prefix) = response_prefix {
+                yield Ok::<_, String>(Event::default().data(format!("{}{}", prefix, WRAP_SEPARATOR)));
+            }
 
             while let Some(chunk) = agent_stream.next().await {
                 match chunk {
                     Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text))) => {
                         full_response.push_str(&text.text);
-                        yield Ok::<_, String>(Event::default().data(&text.text));
+                        steps.text(&text.text);
+                        for chunk in chunks.push(&text.text) {
+                            yield Ok::<_, String>(Event::default().data(chunk));
+                        }
                     }
                     Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall(tool_call))) => {
+                        // Text before the tool call goes out before its event
+                        if let Some(chunk) = chunks.flush() {
+                            yield Ok::<_, String>(Event::default().data(chunk));
+                        }
+                        steps.tool_call(&tool_call.function.name, &tool_call.function.arguments);
+
                         // Notify frontend about tool execution
                         let tool_info = format!(r#"{{"__type":"tool","name":"{}","status":"calling"}}"#, tool_call.function.name);
                         yield Ok::<_, String>(Event::default().event("tool").data(tool_info));
                     }
-                    Ok(MultiTurnStreamItem::StreamUserItem(_user_content)) => {
+                    Ok(MultiTurnStreamItem::StreamUserItem(StreamedUserContent::ToolResult(result))) => {
+                        let result_text = result
+                            .content
+                            .iter()
+                            .filter_map(|content| match content {
+                                ToolResultContent::Text(text) => Some(text.text.as_str()),
+                                _ => None,
+                            })
+                            .collect::<String>();
+                        steps.tool_result(&result_text);
+
                         // Tool result - notify frontend
                         let result_info = r#"{"__type":"tool","status":"completed"}"#;
                         yield Ok::<_, String>(Event::default().event("tool").data(result_info));
@@ -176,21 +345,51 @@ pub async fn run_agent(
                         // Other variants (Reasoning, etc.)
                     }
                     Err(e) => {
-                        let error_str = e.to_string();
+                        if let Some(chunk) = chunks.flush() {
+                            yield Ok::<_, String>(Event::default().data(chunk));
+                        }
+                        let error_str = redact_api_key(&e.to_string());
                         tracing::warn!("Agent stream error: {}", error_str);
 
                         // Handle specific errors gracefully
-                        let error_msg = if error_str.contains("empty") || error_str.contains("no message") {
+                        // A run cancelled by the guard carries the reason to show
+                        let error_msg = if let StreamingError::Prompt(error) = &e
+                            && let PromptError::PromptCancelled { reason, .. } = error.as_ref()
+                        {
+                            reason.clone()
+                        } else if let Some(quota) = quota_exceeded_event(&error_str) {
+                            quota
+                        } else if error_str.contains("empty") || error_str.contains("no message") {
                             "Maaf, saya tidak yakin tindakan apa yang harus dilakukan.".to_string()
                         } else if error_str.contains("MaxDepth") || error_str.contains("depth") {
                             "Maaf, gagal menjalankan aksi browser. Coba refresh halaman.".to_string()
                         } else {
                             format!("Error: {}", error_str)
                         };
-                        yield Ok::<_, String>(Event::default().event("error").data(error_msg));
+                        // A run ended by on_tool_error=ask asks the user how to go on
+                        let event = if run_guard.asks_user() { "question" } else { "error" };
+                        yield Ok::<_, String>(Event::default().event(event).data(error_msg));
                     }
                 }
             }
+            if let Some(chunk) = chunks.flush() {
+                yield Ok::<_, String>(Event::default().data(chunk));
+            }
+
+            if let Some(suffix) = response_suffix {
+                yield Ok::<_, String>(Event::default().data(format!("{}{}", WRAP_SEPARATOR, suffix)));
+            }
+
+            // Reasoning steps for explainability: thought, action and result per tool call
+            let steps = steps.into_steps();
+            if !steps.is_empty() {
+                let steps_json = serde_json::json!({ "__type": "steps", "steps": &steps });
+                yield Ok::<_, String>(Event::default().event("steps").data(steps_json.to_string()));
+            }
+
+            if let Some(run) = run_state.agent_runs.write().await.get_mut(&run_id) {
+                run.record_turn(&run_query, &full_response, steps);
+            }
 
             // Send token usage at end
             if let Some((input, output, total)) = token_usage {
@@ -204,56 +403,1121 @@ pub async fn run_agent(
             yield Ok::<_, String>(Event::default().data("[DONE]"));
         };
 
-        Ok(Sse::new(sse_stream).into_response())
+        Ok(sse_response(sse_stream, state.config.sse_keepalive))
     } else {
-        // Legacy path (no tools, just chat)
+        // No-tools path: a single model call without tool declarations
+        // A requested translation, and the reasoning format for non-streaming
+        // answers, are added to the user's own instruction
+        let show_reasoning = request.show_reasoning && !request.stream;
+        let custom_instruction = [
+            request.custom_instruction.clone(),
+            request_translation(&request),
+            show_reasoning.then(|| REASONING_INSTRUCTION.to_string()),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(|a, b| format!("{}\n{}", a, b));
+        // The prompt is the query alone, with the text to translate when asked
+        let source = translation_source(&request);
+        let query = match source {
+            Some(source) => source.attach_to(&request.query),
+            None => request.query.clone(),
+        };
+        if let Some(entry) = AuditEntry::new(&request, &model, source, 0, unix_now()) {
+            state.audit_log.write().await.record(entry);
+        }
+
+        // Identical requests reuse a cached answer; any new context changes the
+        // key, and so does the API key, so users with their own keys never get
+        // each other's answers. Incognito requests are never cached.
+        let cache_key = (state.response_cache.is_enabled() && !request.incognito).then(|| {
+            let history = request
+                .history
+                .as_ref()
+                .map(|history| serde_json::to_string(history).unwrap_or_default());
+            ResponseCache::key(&[
+                Some(state.api_key_for_request(headers)),
+                Some(&model),
+                custom_instruction.as_deref(),
+                Some(&query),
+                request.image.as_deref(),
+                request.page_content.as_deref(),
+                history.as_deref(),
+                request.seed.map(|seed| seed.to_string()).as_deref(),
+            ])
+        });
+
         // TODO: Update state.llm.stream/complete to support chat history
         if request.stream {
+            let cached = match cache_key {
+                Some(key) => state.response_cache.get(key).await,
+                None => None,
+            };
+
             // Return SSE stream
-            let llm_stream = state.llm.stream(
-                &request.query,
-                request.custom_instruction.as_deref(),
-                request.image.as_deref(),
-            );
+            let llm_stream = cached.is_none().then(|| {
+                llm.stream(
+                    &model,
+                    &query,
+                    custom_instruction.as_deref(),
+                    request.image.as_deref(),
+                )
+            });
+
+            let response_prefix = state.config.response_prefix.clone();
+            let response_suffix = state.config.response_suffix.clone();
+            let cache_state = state.clone();
+            let granularity = state.config.stream_granularity;
 
             let stream = stream! {
-                let mut llm_stream = llm_stream;
-                while let Some(chunk) = llm_stream.next().await {
-                    match chunk {
-                        Ok(text) => {
-                            // Check if this is usage metadata (sent at end of stream)
-                            if text.starts_with(r#"{"__type":"usage""#) {
-                                yield Ok::<_, String>(Event::default().event("usage").data(text));
-                            } else {
-                                yield Ok::<_, String>(Event::default().data(text));
+                if let Some(prefix) = response_prefix {
+                    yield Ok::<_, String>(Event::default().data(format!("{}{}", prefix, WRAP_SEPARATOR)));
+                }
+
+                if let Some(answer) = cached {
+                    yield Ok::<_, String>(Event::default().data(answer));
+                    yield Ok::<_, String>(Event::default().event("cached").data(r#"{"__type":"cached","cached":true}"#));
+                }
+
+                if let Some(mut llm_stream) = llm_stream {
+                    let mut answer = String::new();
+                    let mut failed = false;
+                    let mut chunks = ChunkBuffer::new(granularity);
+                    while let Some(chunk) = llm_stream.next().await {
+                        match chunk {
+                            Ok(text) => {
+                                // Check if this is usage metadata (sent at end of stream)
+                                if text.starts_with(r#"{"__type":"usage""#) {
+                                    if let Some(chunk) = chunks.flush() {
+                                        yield Ok::<_, String>(Event::default().data(chunk));
+                                    }
+                                    yield Ok::<_, String>(Event::default().event("usage").data(text));
+                                } else {
+                                    answer.push_str(&text);
+                                    for chunk in chunks.push(&text) {
+                                        yield Ok::<_, String>(Event::default().data(chunk));
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                failed = true;
+                                if let Some(chunk) = chunks.flush() {
+                                    yield Ok::<_, String>(Event::default().data(chunk));
+                                }
+                                let data = quota_exceeded_event(&e).unwrap_or(e);
+                                yield Ok::<_, String>(Event::default().event("error").data(data));
                             }
                         }
-                        Err(e) => yield Ok::<_, String>(Event::default().event("error").data(e)),
                     }
+                    if let Some(chunk) = chunks.flush() {
+                        yield Ok::<_, String>(Event::default().data(chunk));
+                    }
+
+                    if let Some(key) = cache_key.filter(|_| !failed) {
+                        cache_state.response_cache.insert(key, answer).await;
+                    }
+                }
+
+                if let Some(suffix) = response_suffix {
+                    yield Ok::<_, String>(Event::default().data(format!("{}{}", WRAP_SEPARATOR, suffix)));
                 }
                 yield Ok::<_, String>(Event::default().data("[DONE]"));
             };
 
-            Ok(Sse::new(stream).into_response())
+            Ok(sse_response(stream, state.config.sse_keepalive))
         } else {
             // Return JSON
-            let response = state
-                .llm
-                .complete(
-                    &request.query,
-                    request.custom_instruction.as_deref(),
+            let complete = || {
+                llm.complete(
+                    &model,
+                    &query,
+                    custom_instruction.as_deref(),
                     request.image.as_deref(),
                 )
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            };
+            let (response, cached) = match cache_key {
+                Some(key) => {
+                    state
+                        .response_cache
+                        .get_or_try_insert_with(key, complete)
+                        .await
+                }
+                None => complete().await.map(|response| (response, false)),
+            }
+            .map_err(|e| model_call_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+            let (reasoning, response) = if show_reasoning {
+                split_reasoning(&response)
+            } else {
+                (None, response)
+            };
 
             Ok(Json(ChatResponse {
-                response,
+                reasoning,
+                response: wrap_response(
+                    &response,
+                    state.config.response_prefix.as_deref(),
+                    state.config.response_suffix.as_deref(),
+                ),
                 prompt_tokens: None,
                 response_tokens: None,
                 total_tokens: None,
+                cached,
             })
             .into_response())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::llm::mock::{
+        GeminiCall, GeminiCalls, gemini_response, serve_local, spawn_scripted_gemini, text_reply,
+    };
+    use crate::routes::app_router;
+    use crate::state::AppState;
+    use axum::response::{IntoResponse, Response};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    /// Sets its flag when dropped, i.e. when the request it lives in is cancelled.
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Serves the backend against the Gemini API at `gemini`.
+    async fn serve_backend(gemini: String) -> String {
+        let state = Arc::new(AppState::new(AppConfig {
+            gemini_base_url: Some(gemini),
+            ..AppConfig::for_tests()
+        }));
+        serve_local(app_router(state)).await.unwrap()
+    }
+
+    /// Serves the backend with `config`, returning its state for inspection.
+    async fn serve_backend_with(gemini: String, config: AppConfig) -> (String, Arc<AppState>) {
+        let state = Arc::new(AppState::new(AppConfig {
+            gemini_base_url: Some(gemini),
+            ..config
+        }));
+        let url = serve_local(app_router(state.clone())).await.unwrap();
+        (url, state)
+    }
+
+    /// A Gemini API that never answers, setting the returned flag when its
+    /// request is dropped.
+    async fn hanging_gemini() -> (String, Arc<AtomicBool>, GeminiCalls) {
+        let aborted = Arc::new(AtomicBool::new(false));
+        let flag = aborted.clone();
+        let (url, calls) = spawn_scripted_gemini(move |_| {
+            let flag = flag.clone();
+            async move {
+                let _guard = DropFlag(flag);
+                std::future::pending::<Response>().await
+            }
+        })
+        .await;
+        (url, aborted, calls)
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_aborts_model_call() {
+        let (gemini, aborted, calls) = hanging_gemini().await;
+        let backend = serve_backend(gemini).await;
+
+        // The client gives up (e.g. the browser is closed) while the model is working
+        let result = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({ "query": "halo", "tools": false }))
+            .timeout(Duration::from_millis(500))
+            .send()
+            .await;
+        assert!(result.is_err());
+        assert!(!calls.lock().unwrap().is_empty());
+
+        for _ in 0..50 {
+            if aborted.load(Ordering::SeqCst) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Upstream model call kept running after the client disconnected");
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_stops_streaming_agent_run() {
+        let (gemini, aborted, calls) = hanging_gemini().await;
+        let backend = serve_backend(gemini).await;
+
+        // The side panel is closed while the tool-enabled agent waits on the
+        // model: the SSE response is read for a while, then dropped
+        let read_events = async {
+            let mut response = reqwest::Client::new()
+                .post(format!("{}/agent/run", backend))
+                .json(&serde_json::json!({ "query": "klik tombol masuk", "session_id": "s1" }))
+                .send()
+                .await
+                .unwrap();
+            while let Ok(Some(_)) = response.chunk().await {}
+        };
+        let timed_out = tokio::time::timeout(Duration::from_millis(500), read_events).await;
+        assert!(timed_out.is_err());
+        assert!(!calls.lock().unwrap().is_empty());
+
+        for _ in 0..50 {
+            if aborted.load(Ordering::SeqCst) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Agent run kept calling the model after the client disconnected");
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_quota_is_reported_as_quota_exceeded() {
+        let (gemini, _) = spawn_scripted_gemini(|_| async {
+            (
+                axum::http::StatusCode::TOO_MANY_REQUESTS,
+                axum::Json(serde_json::json!({ "error": {
+                    "code": 429,
+                    "message": "You exceeded your current quota.",
+                    "status": "RESOURCE_EXHAUSTED",
+                    "details": [{
+                        "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                        "retryDelay": "37s"
+                    }]
+                } })),
+            )
+                .into_response()
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({ "query": "halo", "tools": false }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["retry-after"], "37");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"], "quota_exceeded");
+        assert_eq!(body["retry_after_secs"], 37);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("You exceeded your current quota.")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_quota_in_a_stream_is_a_quota_exceeded_event() {
+        let (gemini, _) = spawn_scripted_gemini(|_| async {
+            (
+                axum::http::StatusCode::TOO_MANY_REQUESTS,
+                axum::Json(serde_json::json!({ "error": {
+                    "code": 429,
+                    "message": "You exceeded your current quota.",
+                    "status": "RESOURCE_EXHAUSTED",
+                    "details": [{
+                        "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                        "retryDelay": "37s"
+                    }]
+                } })),
+            )
+                .into_response()
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+
+        let body = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({ "query": "halo", "tools": false, "stream": true }))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        let data = body
+            .split("\n\n")
+            .find(|event| event.starts_with("event: error"))
+            .and_then(|event| event.split_once("data: "))
+            .map(|(_, data)| data)
+            .unwrap();
+        let data: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(data["error"], "quota_exceeded");
+        assert_eq!(data["retry_after_secs"], 37);
+    }
+
+    #[tokio::test]
+    async fn test_chat_records_audit_metadata() {
+        let (gemini, _) =
+            spawn_scripted_gemini(
+                |call: GeminiCall| async move { text_reply(&call, "Ringkasan.") },
+            )
+            .await;
+        let backend = serve_backend(gemini).await;
+        let client = reqwest::Client::new();
+
+        // With tools the page goes to the model; without them only the query does
+        for tools in [true, false] {
+            client
+                .post(format!("{}/agent/run", backend))
+                .json(&serde_json::json!({
+                    "query": "rangkum halaman ini",
+                    "session_id": "s1",
+                    "tools": tools,
+                    "page_url": "https://example.com/artikel",
+                    "page_content": "Isi artikel",
+                    "image": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAA",
+                }))
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+        }
+
+        let audit = |path: String| {
+            let request = client.get(format!("{}{}", backend, path));
+            async move {
+                request
+                    .send()
+                    .await
+                    .unwrap()
+                    .json::<serde_json::Value>()
+                    .await
+                    .unwrap()
+            }
+        };
+        let entries = audit("/session/s1/audit?from=0".to_string()).await;
+        let entries = entries.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        let entry = &entries[0];
+        assert_eq!(entry["session_id"], "s1");
+        assert_eq!(entry["url"], "https://example.com/artikel");
+        assert_eq!(entry["content_length"], 11);
+        assert_eq!(entry["selection_length"], 0);
+        assert_eq!(entry["screenshot_sent"], true);
+        assert!(entry["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(entries[1]["content_length"], 0);
+        assert_eq!(entries[1]["screenshot_sent"], true);
+        // Only metadata is stored
+        assert!(!entry.to_string().contains("Isi artikel"));
+
+        // Other sessions' entries, and entries outside the range, are left out
+        assert_eq!(
+            audit("/session/s2/audit".to_string()).await,
+            serde_json::json!([])
+        );
+        assert_eq!(
+            audit(format!("/session/s1/audit?from={}", u64::MAX)).await,
+            serde_json::json!([])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_use_screenshot_false_leaves_image_out_of_model_call() {
+        let (gemini, calls) =
+            spawn_scripted_gemini(
+                |call: GeminiCall| async move { text_reply(&call, "Ringkasan.") },
+            )
+            .await;
+        let backend = serve_backend(gemini).await;
+        let client = reqwest::Client::new();
+
+        for (use_screenshot, expect_image) in [(true, true), (false, false)] {
+            let response = client
+                .post(format!("{}/agent/run", backend))
+                .json(&serde_json::json!({
+                    "query": "apa isi gambar ini",
+                    "tools": false,
+                    "image": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAA",
+                    "use_screenshot": use_screenshot,
+                }))
+                .send()
+                .await
+                .unwrap();
+            assert!(response.status().is_success());
+
+            let sent = calls.lock().unwrap().last().unwrap().body.to_string();
+            assert_eq!(sent.contains("inlineData"), expect_image);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_instructions_are_sent_as_system_instruction() {
+        let (gemini, calls) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Halo.") })
+                .await;
+        let backend = serve_backend(gemini).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "halo",
+                "tools": false,
+                "custom_instruction": "Jawab singkat",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // Instructions go in systemInstruction; the user turn holds only the message
+        let sent = calls.lock().unwrap()[0].body.clone();
+        let system = sent["systemInstruction"].to_string();
+        assert!(system.contains("Bahasa Indonesia"));
+        assert!(system.contains("Jawab singkat"));
+        let contents = sent["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents[0]["role"], "user");
+        assert_eq!(contents[0]["parts"][0]["text"], "halo");
+    }
+
+    #[tokio::test]
+    async fn test_safe_mode_declares_only_read_only_tools() {
+        // Records the model call, then fails it: only the request matters here
+        let (gemini, calls) = spawn_scripted_gemini(|_| async {
+            axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response()
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "klik tombol masuk",
+                "session_id": "s1",
+                "safe_mode": true,
+            }))
+            .send()
+            .await
+            .unwrap();
+        response.text().await.unwrap();
+
+        let sent = calls.lock().unwrap()[0].body.clone();
+        let declared: Vec<&str> = sent["tools"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|tool| tool["functionDeclarations"].as_array())
+            .flatten()
+            .filter_map(|function| function["name"].as_str())
+            .collect();
+        assert!(declared.contains(&"get_page_content"));
+        for action in [
+            "click_element",
+            "type_text",
+            "navigate_to",
+            "scroll_to",
+            "for_each",
+        ] {
+            assert!(!declared.contains(&action), "{} was declared", action);
+        }
+        assert!(sent["systemInstruction"].to_string().contains("SAFE MODE"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_with_usage_from_gemini() {
+        // A streamed Gemini answer with usage metadata, as Gemini sends it with the last chunk
+        let (gemini, _) = spawn_scripted_gemini(|call: GeminiCall| async move {
+            gemini_response(
+                &call.method,
+                serde_json::json!({
+                    "responseId": "r1",
+                    "candidates": [{
+                        "content": { "role": "model", "parts": [{ "text": "Halo." }] },
+                        "finishReason": "STOP"
+                    }],
+                    "usageMetadata": {
+                        "promptTokenCount": 12,
+                        "candidatesTokenCount": 3,
+                        "totalTokenCount": 15
+                    }
+                }),
+            )
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+        let client = reqwest::Client::new();
+
+        // Without tools, and through the tool-enabled agent
+        for request in [
+            serde_json::json!({ "query": "halo", "tools": false, "stream": true }),
+            serde_json::json!({ "query": "klik tombol masuk", "session_id": "s1" }),
+        ] {
+            let body = client
+                .post(format!("{}/agent/run", backend))
+                .json(&request)
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+
+            let events: Vec<&str> = body.split("\n\n").filter(|e| !e.is_empty()).collect();
+            let usage: Vec<&&str> = events
+                .iter()
+                .filter(|event| event.contains("event: usage"))
+                .collect();
+            assert_eq!(usage.len(), 1, "{}", body);
+            let data = usage[0].split_once("data: ").unwrap().1;
+            let usage: serde_json::Value = serde_json::from_str(data).unwrap();
+            assert_eq!(usage["input_tokens"], 12);
+            assert_eq!(usage["output_tokens"], 3);
+            assert_eq!(usage["total_tokens"], 15);
+
+            // It is the last event before the end marker
+            assert_eq!(events[events.len() - 1], "data: [DONE]");
+            assert!(events[events.len() - 2].contains("event: usage"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follow_up_turn_still_sends_the_page() {
+        let (gemini, calls) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Selesai.") })
+                .await;
+        let backend = serve_backend(gemini).await;
+        let client = reqwest::Client::new();
+
+        // The side panel's history holds the typed messages, not the page sent with them
+        let first = serde_json::json!({
+            "query": "klik tombol masuk",
+            "session_id": "s1",
+            "page_content": "Isi halaman toko",
+        });
+        let second = serde_json::json!({
+            "query": "klik tombol daftar",
+            "session_id": "s1",
+            "page_content": "Isi halaman toko",
+            "history": [
+                { "role": "user", "content": "klik tombol masuk" },
+                { "role": "assistant", "content": "Selesai." }
+            ],
+        });
+        for request in [first, second] {
+            client
+                .post(format!("{}/agent/run", backend))
+                .json(&request)
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+        }
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        let last_message = calls[1].body["contents"]
+            .as_array()
+            .unwrap()
+            .last()
+            .unwrap()
+            .to_string();
+        assert!(last_message.contains("klik tombol daftar"));
+        assert!(last_message.contains("Isi halaman toko"));
+    }
+
+    #[tokio::test]
+    async fn test_page_question_is_asked_without_tools() {
+        let (gemini, calls) = spawn_scripted_gemini(|call: GeminiCall| async move {
+            text_reply(&call, "Halaman ini menjual sepatu.")
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+
+        let body = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "rangkum halaman ini",
+                "session_id": "s1",
+                "page_content": "Toko sepatu lari",
+            }))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("Halaman ini menjual sepatu."));
+
+        // Neither declared nor described, so the model can't call a tool it doesn't have
+        let sent = calls.lock().unwrap()[0].body.clone();
+        assert!(
+            sent.get("tools")
+                .is_none_or(|tools| tools.to_string() == "[]")
+        );
+        let system = sent["systemInstruction"].to_string();
+        assert!(!system.contains("get_page_content"));
+        assert!(!system.contains("Available Tools"));
+        assert!(sent["contents"].to_string().contains("Toko sepatu lari"));
+    }
+
+    #[tokio::test]
+    async fn test_translation_without_tools_sends_the_page() {
+        let (gemini, calls) = spawn_scripted_gemini(|call: GeminiCall| async move {
+            text_reply(&call, "This is our store's official page.")
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+        let page = "Ini adalah halaman resmi dari toko kami dan produk yang dijual tidak mahal.";
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "terjemahkan",
+                "tools": false,
+                "translate_to": "en",
+                "page_content": page,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let sent = calls.lock().unwrap()[0].body.clone();
+        assert!(
+            sent["systemInstruction"]
+                .to_string()
+                .contains("Translate the page content into English")
+        );
+        let user_text = sent["contents"][0]["parts"][0]["text"].as_str().unwrap();
+        assert!(user_text.starts_with("terjemahkan"));
+        assert!(user_text.contains(page));
+    }
+
+    #[tokio::test]
+    async fn test_body_limit_fits_large_pages_but_caps_files() {
+        let (gemini, _) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Oke.") })
+                .await;
+        let backend = serve_backend(gemini).await;
+        let client = reqwest::Client::new();
+
+        // Over axum's default 2 MB body limit
+        let response = client
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "rangkum",
+                "tools": false,
+                "page_content": "a".repeat(3 * 1024 * 1024),
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // Five 1 MB files are over the total for files
+        let file = serde_json::json!({
+            "name": "foto.jpg",
+            "data": format!("data:image/jpeg;base64,{}", "A".repeat(1024 * 1024 / 3 * 4)),
+        });
+        let response = client
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "unggah foto",
+                "session_id": "s1",
+                "files": vec![file; 5],
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 413);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"], "files_too_large");
+    }
+
+    #[tokio::test]
+    async fn test_tools_false_makes_one_call_without_tools() {
+        let (gemini, calls) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Halo!") })
+                .await;
+        let backend = serve_backend(gemini).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "klik tombol masuk",
+                "session_id": "s1",
+                "tools": false,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["response"], "Halo!");
+
+        // Even with a session, no function is declared
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].body.get("tools").is_none());
+        assert!(!calls[0].body.to_string().contains("functionDeclarations"));
+    }
+
+    #[tokio::test]
+    async fn test_incognito_agent_request_leaves_no_trace() {
+        let (gemini, _) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Selesai.") })
+                .await;
+        let (backend, state) = serve_backend_with(gemini, AppConfig::for_tests()).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "klik tombol masuk",
+                "session_id": "s1",
+                "incognito": true,
+                "page_url": "https://example.com/masuk",
+                "page_content": "Halaman masuk",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        response.text().await.unwrap();
+
+        assert_eq!(state.agent_runs.read().await.for_session("s1").count(), 0);
+        assert!(
+            state
+                .audit_log
+                .read()
+                .await
+                .query("s1", None, None)
+                .is_empty()
+        );
+        // A URL seen before would return a diff
+        let diff = state
+            .page_snapshots
+            .write()
+            .await
+            .record("https://example.com/masuk", "Halaman masuk");
+        assert!(diff.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_incognito_answers_are_not_cached() {
+        let (gemini, calls) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Halo!") })
+                .await;
+        let (backend, state) = serve_backend_with(
+            gemini,
+            AppConfig {
+                response_cache_ttl: Duration::from_secs(300),
+                ..AppConfig::for_tests()
+            },
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        for _ in 0..2 {
+            let response = client
+                .post(format!("{}/agent/run", backend))
+                .json(&serde_json::json!({
+                    "query": "apa itu rust?",
+                    "session_id": "s1",
+                    "tools": false,
+                    "incognito": true,
+                }))
+                .send()
+                .await
+                .unwrap();
+            assert!(response.status().is_success());
+            response.text().await.unwrap();
+        }
+
+        // Both requests reached the model, and neither was audited
+        assert_eq!(calls.lock().unwrap().len(), 2);
+        assert!(
+            state
+                .audit_log
+                .read()
+                .await
+                .query("s1", None, None)
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_avif_image_is_rejected_before_the_model_call() {
+        let (gemini, calls) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Halo!") })
+                .await;
+        let backend = serve_backend(gemini).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "apa isi gambar ini?",
+                "tools": false,
+                "image": "data:image/avif;base64,AAAAHGZ0eXBhdmlmAAAAAG1pZjE=",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"], "unsupported_image");
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_when_the_model_loops_on_an_unknown_function() {
+        use crate::agent::guard::MAX_UNKNOWN_CALL_REPEATS;
+
+        // The model asks for the same function the agent doesn't have, every turn
+        let (gemini, calls) = spawn_scripted_gemini(|call: GeminiCall| async move {
+            gemini_response(
+                &call.method,
+                serde_json::json!({
+                    "responseId": "r1",
+                    "candidates": [{
+                        "content": {
+                            "role": "model",
+                            "parts": [{
+                                "functionCall": { "name": "open_admin_panel", "args": {} }
+                            }]
+                        },
+                        "finishReason": "STOP"
+                    }]
+                }),
+            )
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+
+        let body = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "klik tombol masuk",
+                "session_id": "s1",
+            }))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(body.contains(
+            "event: error\ndata: Maaf, model terus memanggil fungsi yang tidak dikenal (open_admin_panel)"
+        ));
+        // Stopped at the repeat limit, long before the depth limit
+        assert!(calls.lock().unwrap().len() <= MAX_UNKNOWN_CALL_REPEATS);
+    }
+
+    #[tokio::test]
+    async fn test_cached_answers_are_not_shared_across_api_keys() {
+        use crate::state::USER_API_KEY_HEADER;
+
+        let (gemini, calls) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Halo!") })
+                .await;
+        let (backend, _) = serve_backend_with(
+            gemini,
+            AppConfig {
+                response_cache_ttl: Duration::from_secs(300),
+                ..AppConfig::for_tests()
+            },
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let mut cached = Vec::new();
+        for key in ["user-key-a", "user-key-b", "user-key-a"] {
+            let body: serde_json::Value = client
+                .post(format!("{}/agent/run", backend))
+                .header(USER_API_KEY_HEADER, key)
+                .json(&serde_json::json!({ "query": "apa itu rust?", "tools": false }))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            cached.push(body["cached"].as_bool().unwrap_or(false));
+        }
+
+        // The second user's request reaches the model; the first user's repeat doesn't
+        assert_eq!(cached, [false, false, true]);
+        assert_eq!(calls.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_user_api_key_stays_out_of_logs_and_errors() {
+        use crate::state::USER_API_KEY_HEADER;
+        use std::io::Write;
+        use std::sync::Mutex;
+
+        /// Collects everything logged while the test runs.
+        #[derive(Clone, Default)]
+        struct Logs(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Logs {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_writer(move || writer.clone())
+                .finish(),
+        );
+
+        // An upstream error that quotes the request URL, key included
+        let (gemini, calls) = spawn_scripted_gemini(|_| async {
+            let body = serde_json::json!({
+                "error": {
+                    "code": 400,
+                    "message": "API key not valid (https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent?key=user-secret-key)",
+                    "status": "INVALID_ARGUMENT"
+                }
+            });
+            (axum::http::StatusCode::BAD_REQUEST, axum::Json(body)).into_response()
+        })
+        .await;
+        let backend = serve_backend(gemini).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .header(USER_API_KEY_HEADER, "user-secret-key")
+            .json(&serde_json::json!({ "query": "apa itu rust?", "tools": false }))
+            .send()
+            .await
+            .unwrap();
+        assert!(!response.status().is_success());
+        let body = response.text().await.unwrap();
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls[0].api_key.as_deref(), Some("user-secret-key"));
+        assert!(!calls[0].query.contains_key("key"));
+        assert!(!body.contains("user-secret-key"));
+        let logs = String::from_utf8_lossy(&logs.0.lock().unwrap()).to_string();
+        assert!(logs.contains("API key not valid"));
+        assert!(!logs.contains("user-secret-key"));
+    }
+
+    #[tokio::test]
+    async fn test_screenshot_reaches_the_agents_next_call_as_an_image() {
+        use crate::models::ws::{ActionCommand, ActionResult, WsMessage};
+        use tokio::sync::mpsc;
+
+        // The model asks for a screenshot, then answers once it has a tool result
+        let (gemini, calls) = spawn_scripted_gemini(|call: GeminiCall| async move {
+            if call.body["contents"]
+                .to_string()
+                .contains("functionResponse")
+            {
+                return text_reply(&call, "Grafiknya naik.");
+            }
+            gemini_response(
+                &call.method,
+                serde_json::json!({
+                    "responseId": "r1",
+                    "candidates": [{
+                        "content": {
+                            "role": "model",
+                            "parts": [{
+                                "functionCall": {
+                                    "name": "full_page_screenshot",
+                                    "args": { "question": "Apa isi grafik?" }
+                                }
+                            }]
+                        },
+                        "finishReason": "STOP"
+                    }]
+                }),
+            )
+        })
+        .await;
+        let (backend, state) = serve_backend_with(gemini, AppConfig::for_tests()).await;
+
+        // The extension: answers the screenshot request with a capture
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        state.register_connection("s1".to_string(), tx).await;
+        let browser_state = state.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let WsMessage::ActionRequest {
+                    request_id,
+                    command: ActionCommand::FullPageScreenshot,
+                } = msg
+                {
+                    let result = ActionResult {
+                        request_id: request_id.clone(),
+                        success: true,
+                        error: None,
+                        data: Some(serde_json::json!({
+                            "image": "data:image/jpeg;base64,/9j/4AAQSkZJRg",
+                            "full_page": true
+                        })),
+                    };
+                    browser_state
+                        .complete_pending_action(&request_id, result)
+                        .await;
+                }
+            }
+        });
+
+        let body = reqwest::Client::new()
+            .post(format!("{}/agent/run", backend))
+            .json(&serde_json::json!({
+                "query": "lihat grafik di halaman ini",
+                "session_id": "s1",
+                "tools": true,
+            }))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("Grafiknya naik."));
+
+        // The agent's own model gets the capture, after the tool result
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert!(!calls[0].body.to_string().contains("inlineData"));
+        let contents = calls[1].body["contents"].as_array().unwrap();
+        let last = contents.last().unwrap();
+        assert_eq!(last["role"], "user");
+        assert!(
+            last.to_string()
+                .contains("Screenshot from full_page_screenshot")
+        );
+        assert_eq!(last["parts"][1]["inlineData"]["mimeType"], "image/jpeg");
+        assert_eq!(last["parts"][1]["inlineData"]["data"], "/9j/4AAQSkZJRg");
+        assert!(
+            contents[..contents.len() - 1]
+                .iter()
+                .any(|content| content.to_string().contains("functionResponse"))
+        );
+    }
+}
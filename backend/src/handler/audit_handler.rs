@@ -0,0 +1,30 @@
+use axum::extract::{Json, Path, Query, State};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::models::audit::AuditEntry;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    /// Unix time in seconds, inclusive
+    pub from: Option<u64>,
+    /// Unix time in seconds, inclusive
+    pub to: Option<u64>,
+}
+
+/// Lists what page data a session's agent requests sent to the model in a
+/// time range. Only metadata is kept; incognito requests aren't recorded.
+pub async fn list_audit(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Query(query): Query<AuditQuery>,
+) -> Json<Vec<AuditEntry>> {
+    Json(
+        state
+            .audit_log
+            .read()
+            .await
+            .query(&session_id, query.from, query.to),
+    )
+}
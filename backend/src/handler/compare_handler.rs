@@ -0,0 +1,271 @@
+use axum::{
+    extract::{Json, State},
+    http::{HeaderMap, StatusCode},
+};
+use futures::future::join_all;
+use std::sync::Arc;
+
+use crate::agent::instruction::validate_instruction;
+use crate::agent::language::{request_translation, translation_source};
+use crate::dtos::compare::{CompareRequest, CompareResponse, MAX_COMPARE_MODELS, ModelResult};
+use crate::error::ApiError;
+use crate::llm::gemini_error::model_call_error;
+use crate::llm::{is_avif, unsupported_image_error};
+use crate::models::audit::{AuditEntry, unix_now};
+use crate::state::AppState;
+use crate::utils::json::ApiJson;
+
+/// Runs the same no-tools request against several models at once and returns
+/// their answers and token usage side by side, for comparing models.
+pub async fn compare_models(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(CompareRequest {
+        request: mut request,
+        models,
+    }): ApiJson<CompareRequest>,
+) -> Result<Json<CompareResponse>, ApiError> {
+    if let Some(instruction) = &request.custom_instruction {
+        validate_instruction(instruction)?;
+    }
+    if request.image.as_deref().is_some_and(is_avif) {
+        return Err(unsupported_image_error());
+    }
+    if models.is_empty() || models.len() > MAX_COMPARE_MODELS {
+        return Err(ApiError::from((
+            StatusCode::BAD_REQUEST,
+            format!("models must list 1 to {} models", MAX_COMPARE_MODELS),
+        )));
+    }
+    if let Some(model) = models
+        .iter()
+        .find(|model| !state.config.routing.is_allowed(model))
+    {
+        return Err(ApiError::from((
+            StatusCode::BAD_REQUEST,
+            format!("Model not allowed: {}", model),
+        )));
+    }
+
+    request.strip_unused_context();
+    tracing::info!("Comparing models {:?}", models);
+
+    let custom_instruction = [
+        request.custom_instruction.clone(),
+        request_translation(&request),
+    ]
+    .into_iter()
+    .flatten()
+    .reduce(|a, b| format!("{}\n{}", a, b));
+    let source = translation_source(&request);
+    let query = match source {
+        Some(source) => source.attach_to(&request.query),
+        None => request.query.clone(),
+    };
+
+    {
+        let mut audit_log = state.audit_log.write().await;
+        for model in &models {
+            if let Some(entry) = AuditEntry::new(&request, model, source, 0, unix_now()) {
+                audit_log.record(entry);
+            }
+        }
+    }
+
+    let (_, llm) = state.gemini_for_request(&headers);
+    let llm = llm.with_seed(request.seed);
+    let calls = models.iter().map(|model| {
+        llm.complete_with_usage(
+            model,
+            &query,
+            custom_instruction.as_deref(),
+            request.image.as_deref(),
+        )
+    });
+
+    let results = join_all(calls)
+        .await
+        .into_iter()
+        .zip(models)
+        .map(|(result, model)| match result {
+            Ok((response, usage)) => ModelResult {
+                model,
+                response: Some(response),
+                error: None,
+                error_code: None,
+                retry_after_secs: None,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                total_tokens: usage.total_tokens,
+            },
+            Err(error) => {
+                tracing::warn!("Model {} failed in comparison: {}", model, error);
+                let error = model_call_error(StatusCode::BAD_GATEWAY, error);
+                ModelResult {
+                    model,
+                    response: None,
+                    retry_after_secs: error.retry_after_secs(),
+                    error_code: error.code,
+                    error: Some(error.message),
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    total_tokens: 0,
+                }
+            }
+        })
+        .collect();
+
+    Ok(Json(CompareResponse { results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::llm::mock::{GeminiCall, GeminiCalls, gemini_response, spawn_scripted_gemini};
+    use crate::routes::app_router;
+    use crate::state::AppState;
+    use axum::{body::Body, extract::Request, http::StatusCode, response::IntoResponse};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// Answers with the name of the model called.
+    async fn mock_gemini() -> (String, GeminiCalls) {
+        spawn_scripted_gemini(|call: GeminiCall| async move {
+            gemini_response(
+                &call.method,
+                serde_json::json!({
+                    "responseId": "r1",
+                    "candidates": [{
+                        "content": { "role": "model", "parts": [{ "text": format!("Jawaban {}", call.model) }] },
+                        "finishReason": "STOP"
+                    }],
+                    "usageMetadata": {
+                        "promptTokenCount": 5,
+                        "candidatesTokenCount": 3,
+                        "totalTokenCount": 8
+                    }
+                }),
+            )
+        })
+        .await
+    }
+
+    async fn post_compare(
+        state: Arc<AppState>,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let response = app_router(state)
+            .oneshot(
+                Request::post("/agent/compare")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_each_model_is_called_and_returned() {
+        let (url, calls) = mock_gemini().await;
+        let state = Arc::new(AppState::new(AppConfig {
+            gemini_base_url: Some(url),
+            ..AppConfig::for_tests()
+        }));
+        let flash = state.config.routing.flash_model.clone();
+        let pro = state.config.routing.pro_model.clone();
+
+        let (status, json) = post_compare(
+            state,
+            serde_json::json!({ "query": "halo", "models": [flash, pro] }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let mut called: Vec<String> = calls
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|call| call.model.clone())
+            .collect();
+        called.sort();
+        let mut expected = vec![flash.clone(), pro.clone()];
+        expected.sort();
+        assert_eq!(called, expected);
+
+        let results = json["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["model"], flash);
+        assert_eq!(results[0]["response"], format!("Jawaban {}", flash));
+        assert_eq!(results[1]["model"], pro);
+        assert_eq!(results[1]["response"], format!("Jawaban {}", pro));
+        assert_eq!(results[1]["total_tokens"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_disallowed_or_too_many_models() {
+        let state = Arc::new(AppState::new(AppConfig::for_tests()));
+
+        let (status, _) = post_compare(
+            state.clone(),
+            serde_json::json!({ "query": "halo", "models": ["gpt-unknown"] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let flash = state.config.routing.flash_model.clone();
+        let (status, _) = post_compare(
+            state,
+            serde_json::json!({ "query": "halo", "models": vec![flash; 5] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_quota_is_reported_per_model() {
+        let (url, _) = spawn_scripted_gemini(|_| async {
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                axum::Json(serde_json::json!({ "error": {
+                    "code": 429,
+                    "message": "You exceeded your current quota.",
+                    "status": "RESOURCE_EXHAUSTED",
+                    "details": [{
+                        "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                        "retryDelay": "37s"
+                    }]
+                } })),
+            )
+                .into_response()
+        })
+        .await;
+        let state = Arc::new(AppState::new(AppConfig {
+            gemini_base_url: Some(url),
+            ..AppConfig::for_tests()
+        }));
+        let flash = state.config.routing.flash_model.clone();
+
+        let (status, json) = post_compare(
+            state,
+            serde_json::json!({ "query": "halo", "models": [flash] }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let result = &json["results"][0];
+        assert_eq!(result["error_code"], "quota_exceeded");
+        assert_eq!(result["retry_after_secs"], 37);
+        assert!(
+            result["error"]
+                .as_str()
+                .unwrap()
+                .contains("You exceeded your current quota.")
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use axum::extract::{Json, State};
+use axum::http::HeaderMap;
+use std::sync::Arc;
+
+use crate::llm::key_check::{KeyStatus, check_api_key};
+use crate::state::AppState;
+
+/// Reports whether the Gemini API key works for the flash model: the user's
+/// own key when sent in `X-User-Api-Key`, otherwise the server's.
+pub async fn key_status(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Json<KeyStatus> {
+    let status = check_api_key(
+        &state.http,
+        state.config.gemini_base_url.as_deref(),
+        state.api_key_for_request(&headers),
+        &state.config.routing.flash_model,
+    )
+    .await;
+    tracing::info!("API key check: {}", status.message);
+    Json(status)
+}
@@ -1 +1,8 @@
 pub mod agent_handler;
+pub mod audit_handler;
+pub mod compare_handler;
+pub mod instruction_handler;
+pub mod key_handler;
+pub mod screenshot_handler;
+pub mod transcript_handler;
+pub mod vision_handler;
@@ -0,0 +1,126 @@
+use async_stream::stream;
+use axum::extract::{Path, State};
+use axum::response::{Response, sse::Event};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::models::screenshots::ScreenshotEvent;
+use crate::state::AppState;
+use crate::utils::streaming::sse_response;
+
+/// A client's subscription to a session's screenshots. Dropped with the SSE
+/// stream when the client disconnects, releasing the feed if it was the last
+/// watcher.
+struct Watcher {
+    state: Arc<AppState>,
+    session_id: String,
+    screenshots: Option<broadcast::Receiver<ScreenshotEvent>>,
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        // Unsubscribe first so the feed no longer counts this watcher
+        drop(self.screenshots.take());
+        let state = self.state.clone();
+        let session_id = std::mem::take(&mut self.session_id);
+        tokio::spawn(async move {
+            state.screenshot_feeds.write().await.release(&session_id);
+        });
+    }
+}
+
+/// Streams each screenshot the agent captures on a session as a `screenshot`
+/// event, separately from the run's text stream. Screenshots taken before the
+/// client connected are not replayed.
+pub async fn screenshot_stream(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+) -> Response {
+    let screenshots = state.screenshot_feeds.write().await.subscribe(&session_id);
+    let mut watcher = Watcher {
+        state: state.clone(),
+        session_id,
+        screenshots: Some(screenshots),
+    };
+
+    let stream = stream! {
+        let Some(screenshots) = watcher.screenshots.as_mut() else {
+            return;
+        };
+        loop {
+            match screenshots.recv().await {
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    yield Ok::<_, String>(Event::default().event("screenshot").data(data));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!("Screenshot watcher skipped {} screenshots", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+
+    sse_response(stream, state.config.sse_keepalive)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::llm::mock::serve_local;
+    use crate::models::screenshots::ScreenshotEvent;
+    use crate::routes::app_router;
+    use crate::state::AppState;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_captured_screenshots_are_streamed() {
+        let state = Arc::new(AppState::new(AppConfig::for_tests()));
+        let url = serve_local(app_router(state.clone())).await.unwrap();
+
+        let mut response = reqwest::get(format!("{}/session/s1/screenshots", url))
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // The watcher is subscribed once the response has started
+        state.screenshot_feeds.write().await.publish(
+            "s1",
+            ScreenshotEvent {
+                tool: "full_page_screenshot".to_string(),
+                image: "data:image/png;base64,AAAA".to_string(),
+                timestamp: 1,
+            },
+        );
+
+        let mut body = String::new();
+        while !body.contains("\n\n") {
+            let chunk = response.chunk().await.unwrap().unwrap();
+            body.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        assert!(body.starts_with("event: screenshot\n"));
+        assert!(body.contains(r#""tool":"full_page_screenshot""#));
+        assert!(body.contains("data:image/png;base64,AAAA"));
+    }
+
+    #[tokio::test]
+    async fn test_feed_is_released_when_the_watcher_disconnects() {
+        let state = Arc::new(AppState::new(AppConfig::for_tests()));
+        let url = serve_local(app_router(state.clone())).await.unwrap();
+
+        let response = reqwest::get(format!("{}/session/s1/screenshots", url))
+            .await
+            .unwrap();
+        assert!(state.screenshot_feeds.read().await.is_watched("s1"));
+
+        drop(response);
+        for _ in 0..50 {
+            if !state.screenshot_feeds.read().await.is_watched("s1") {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("screenshot feed was not released after the watcher disconnected");
+    }
+}
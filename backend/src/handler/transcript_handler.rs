@@ -0,0 +1,50 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Json, Response};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::models::transcript::Transcript;
+use crate::state::AppState;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    #[default]
+    Json,
+    Md,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranscriptQuery {
+    #[serde(default)]
+    pub format: TranscriptFormat,
+}
+
+/// Exports a session's agent runs as JSON or Markdown. Incognito runs are
+/// never stored, so they don't appear.
+pub async fn session_transcript(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let runs = state.agent_runs.read().await;
+    let transcript = Transcript::from_runs(&session_id, runs.for_session(&session_id));
+    drop(runs);
+
+    if transcript.entries.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("No transcript for session: {}", session_id),
+        ));
+    }
+
+    Ok(match query.format {
+        TranscriptFormat::Json => Json(transcript).into_response(),
+        TranscriptFormat::Md => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            transcript.to_markdown(),
+        )
+            .into_response(),
+    })
+}
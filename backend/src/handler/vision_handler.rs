@@ -0,0 +1,148 @@
+use axum::{
+    extract::{Json, State},
+    http::{HeaderMap, StatusCode},
+};
+use std::sync::Arc;
+
+use crate::dtos::vision::{VisionRequest, VisionResponse};
+use crate::error::ApiError;
+use crate::llm::gemini_error::model_call_error;
+use crate::llm::{is_avif, unsupported_image_error};
+use crate::state::AppState;
+use crate::utils::json::ApiJson;
+
+/// Asked when a vision request has no question.
+const DEFAULT_VISION_QUESTION: &str = "Jelaskan isi gambar ini.";
+
+/// Describes an image, or answers a question about it, with a single
+/// no-tools model call: no page context, history or stored state.
+pub async fn analyze_image(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<VisionRequest>,
+) -> Result<Json<VisionResponse>, ApiError> {
+    if request.image.trim().is_empty() {
+        return Err(ApiError::from((
+            StatusCode::BAD_REQUEST,
+            "image must not be empty".to_string(),
+        )));
+    }
+    if is_avif(&request.image) {
+        return Err(unsupported_image_error());
+    }
+
+    let question = request
+        .question
+        .as_deref()
+        .unwrap_or(DEFAULT_VISION_QUESTION);
+    tracing::info!("Vision request: {}", question);
+
+    let (_, llm) = state.gemini_for_request(&headers);
+    let description = llm
+        .complete(
+            &state.config.routing.flash_model,
+            question,
+            None,
+            Some(&request.image),
+        )
+        .await
+        .map_err(|e| model_call_error(StatusCode::BAD_GATEWAY, e))?;
+
+    Ok(Json(VisionResponse { description }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::llm::mock::{GeminiCall, GeminiCalls, spawn_scripted_gemini, text_reply};
+    use crate::routes::app_router;
+    use crate::state::{AppState, USER_API_KEY_HEADER};
+    use axum::{body::Body, extract::Request, http::StatusCode, response::Response};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// State against a Gemini API that describes every image the same way.
+    async fn mock_state() -> (Arc<AppState>, GeminiCalls) {
+        let (url, calls) = spawn_scripted_gemini(|call: GeminiCall| async move {
+            text_reply(&call, "Sebuah logo biru.")
+        })
+        .await;
+        let state = Arc::new(AppState::new(AppConfig {
+            gemini_base_url: Some(url),
+            ..AppConfig::for_tests()
+        }));
+        (state, calls)
+    }
+
+    const PNG_IMAGE: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAA";
+
+    async fn post_vision(state: &Arc<AppState>, api_key: Option<&str>, image: &str) -> Response {
+        let body = serde_json::json!({ "image": image });
+        let mut request = Request::post("/vision").header("content-type", "application/json");
+        if let Some(key) = api_key {
+            request = request.header(USER_API_KEY_HEADER, key);
+        }
+        app_router(state.clone())
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_vision_returns_description() {
+        let (state, calls) = mock_state().await;
+
+        let response = post_vision(&state, None, PNG_IMAGE).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["description"], "Sebuah logo biru.");
+
+        // One plain call, without tools
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].body.get("tools").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_user_api_key_is_used_for_the_call() {
+        let (state, calls) = mock_state().await;
+
+        post_vision(&state, Some("user-key"), PNG_IMAGE).await;
+        post_vision(&state, None, PNG_IMAGE).await;
+
+        // The user's key is used for its request only, never kept in the state,
+        // and goes in the header rather than the URL
+        let calls = calls.lock().unwrap();
+        let keys: Vec<_> = calls.iter().map(|call| call.api_key.clone()).collect();
+        assert_eq!(
+            keys,
+            vec![Some("user-key".to_string()), Some("test-key".to_string())]
+        );
+        assert!(calls.iter().all(|call| !call.query.contains_key("key")));
+        assert_eq!(state.config.gemini_api_key, "test-key");
+    }
+
+    #[tokio::test]
+    async fn test_avif_image_is_rejected_before_the_model_call() {
+        let (state, calls) = mock_state().await;
+
+        let response = post_vision(
+            &state,
+            None,
+            "data:image/avif;base64,AAAAHGZ0eXBhdmlmAAAAAG1pZjE=",
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["error"], "unsupported_image");
+        assert!(calls.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! Cache of complete model answers for repeated identical requests, e.g. the
+//! same question about the same unchanged page after reopening the side panel.
+//!
+//! Entries are keyed by a hash of everything that shapes the answer, so any
+//! new context (page content, history, image) misses the cache.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+pub struct ResponseCache {
+    /// How long an answer is reused; zero disables the cache
+    ttl: Duration,
+    entries: Mutex<HashMap<u64, (Instant, String)>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero()
+    }
+
+    /// Hashes the parts of a request that determine its answer.
+    pub fn key(parts: &[Option<&str>]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        parts.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub async fn get(&self, key: u64) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let entries = self.entries.lock().await;
+        entries
+            .get(&key)
+            .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+            .map(|(_, answer)| answer.clone())
+    }
+
+    pub async fn insert(&self, key: u64, answer: String) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut entries = self.entries.lock().await;
+        entries.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), answer));
+    }
+
+    /// Returns the cached answer for `key`, or calls `answer` and caches its
+    /// result. The flag is true when the answer came from the cache.
+    pub async fn get_or_try_insert_with<F, Fut, E>(
+        &self,
+        key: u64,
+        answer: F,
+    ) -> Result<(String, bool), E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String, E>>,
+    {
+        if let Some(cached) = self.get(key).await {
+            return Ok((cached, true));
+        }
+        let fresh = answer().await?;
+        self.insert(key, fresh.clone()).await;
+        Ok((fresh, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn ask(cache: &ResponseCache, key: u64, calls: &AtomicUsize) -> (String, bool) {
+        cache
+            .get_or_try_insert_with(key, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, String>("Jawaban".to_string())
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_identical_requests_call_provider_once() {
+        let cache = ResponseCache::new(Duration::from_millis(200));
+        let calls = AtomicUsize::new(0);
+        let key = ResponseCache::key(&[Some("gemini-2.5-flash"), Some("apa ini?"), None]);
+
+        assert_eq!(
+            ask(&cache, key, &calls).await,
+            ("Jawaban".to_string(), false)
+        );
+        assert_eq!(
+            ask(&cache, key, &calls).await,
+            ("Jawaban".to_string(), true)
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // New context is a different key; expired answers are fetched again
+        let other = ResponseCache::key(&[Some("gemini-2.5-flash"), Some("apa ini?"), Some("page")]);
+        assert!(!ask(&cache, other, &calls).await.1);
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(!ask(&cache, key, &calls).await.1);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_disables_cache() {
+        let cache = ResponseCache::new(Duration::ZERO);
+        let calls = AtomicUsize::new(0);
+        ask(&cache, 1, &calls).await;
+        assert!(!ask(&cache, 1, &calls).await.1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
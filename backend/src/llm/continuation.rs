@@ -0,0 +1,123 @@
+//! Continues responses cut off at the output token limit.
+
+use rig::completion::Usage;
+use rig::message::Message;
+
+/// Maximum number of follow-up calls made after a truncated response.
+pub const MAX_CONTINUATIONS: usize = 2;
+
+/// Prompt sent as a new user turn to get the rest of a truncated response.
+pub const CONTINUE_PROMPT: &str =
+    "Continue exactly where you left off. Do not repeat anything you already wrote.";
+
+/// The text of one model call, whether it stopped at the token limit and the
+/// tokens it used.
+pub struct Turn {
+    pub text: String,
+    pub truncated: bool,
+    pub usage: Usage,
+}
+
+/// Calls the model, and while the response is truncated, appends the partial
+/// text as a model turn and asks it to continue, up to `MAX_CONTINUATIONS` times.
+/// Returns the joined text and the tokens used by all calls.
+pub async fn complete_with_continuations<F, Fut>(
+    prompt: Message,
+    mut call: F,
+) -> Result<(String, Usage), String>
+where
+    F: FnMut(Message, Vec<Message>) -> Fut,
+    Fut: Future<Output = Result<Turn, String>>,
+{
+    let mut prompt = prompt;
+    let mut history = Vec::new();
+    let mut output = String::new();
+    let mut usage = Usage::new();
+
+    for continuation in 0..=MAX_CONTINUATIONS {
+        let turn = call(prompt.clone(), history.clone()).await?;
+        output.push_str(&turn.text);
+        usage += turn.usage;
+
+        if !turn.truncated {
+            break;
+        }
+        if continuation == MAX_CONTINUATIONS {
+            tracing::warn!(
+                "Response still truncated after {} continuations",
+                MAX_CONTINUATIONS
+            );
+            break;
+        }
+
+        history.push(prompt);
+        history.push(Message::assistant(turn.text));
+        prompt = Message::user(CONTINUE_PROMPT);
+    }
+
+    Ok((output, usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::OneOrMany;
+    use rig::message::UserContent;
+
+    fn is_continue_prompt(message: &Message) -> bool {
+        matches!(message, Message::User { content } if *content == OneOrMany::one(UserContent::text(CONTINUE_PROMPT)))
+    }
+
+    #[tokio::test]
+    async fn test_truncated_response_is_continued() {
+        let mut calls = Vec::new();
+        let (output, _) =
+            complete_with_continuations(Message::user("tulis esai"), |prompt, history| {
+                calls.push((is_continue_prompt(&prompt), history.len()));
+                let turn = if history.is_empty() {
+                    Turn {
+                        text: "Bagian pertama, ".to_string(),
+                        truncated: true,
+                        usage: Usage::new(),
+                    }
+                } else {
+                    Turn {
+                        text: "bagian kedua.".to_string(),
+                        truncated: false,
+                        usage: Usage::new(),
+                    }
+                };
+                async move { Ok(turn) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(output, "Bagian pertama, bagian kedua.");
+        // The second call carries the original prompt and the partial answer
+        assert_eq!(calls, vec![(false, 0), (true, 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_continuations_are_bounded() {
+        let mut count = 0;
+        let (output, usage) = complete_with_continuations(Message::user("tulis esai"), |_, _| {
+            count += 1;
+            async move {
+                let mut usage = Usage::new();
+                usage.output_tokens = 10;
+                Ok(Turn {
+                    text: "x".to_string(),
+                    truncated: true,
+                    usage,
+                })
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(count, MAX_CONTINUATIONS + 1);
+        assert_eq!(output, "x".repeat(MAX_CONTINUATIONS + 1));
+        // Tokens of every call are counted
+        assert_eq!(usage.output_tokens, 10 * (MAX_CONTINUATIONS as u64 + 1));
+    }
+}
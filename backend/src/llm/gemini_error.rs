@@ -0,0 +1,206 @@
+//! Structured errors from the Gemini API.
+//!
+//! rig passes a failed call on as text that embeds Gemini's JSON error body,
+//! e.g. `ProviderError: {"error": {"code": 429, "status": "RESOURCE_EXHAUSTED", ...}}`.
+//! Parsing it back lets clients tell exhausted quota apart from other failures.
+
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+use crate::error::ApiError;
+
+/// The `error` object of a Gemini error response.
+#[derive(Debug, PartialEq)]
+pub struct GeminiError {
+    pub code: u16,
+    /// gRPC status, e.g. `RESOURCE_EXHAUSTED` or `UNAVAILABLE`
+    pub status: String,
+    pub message: String,
+    /// How long Gemini asks to wait before retrying (its `RetryInfo` detail)
+    pub retry_delay: Option<Duration>,
+}
+
+impl GeminiError {
+    /// Finds and parses the Gemini error body in an error message.
+    pub fn parse(error: &str) -> Option<Self> {
+        let start = error.find('{')?;
+        let body: Value = serde_json::Deserializer::from_str(&error[start..])
+            .into_iter()
+            .next()?
+            .ok()?;
+        let error = body.get("error")?;
+
+        let retry_delay = error["details"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|detail| {
+                detail["@type"]
+                    .as_str()
+                    .is_some_and(|kind| kind.ends_with("google.rpc.RetryInfo"))
+            })
+            .and_then(|detail| detail["retryDelay"].as_str())
+            .and_then(parse_delay);
+
+        Some(Self {
+            code: error["code"].as_u64().unwrap_or_default() as u16,
+            status: error["status"].as_str().unwrap_or_default().to_string(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+            retry_delay,
+        })
+    }
+
+    /// The key's quota or billing limit is reached, as opposed to a
+    /// temporary overload (`UNAVAILABLE`).
+    pub fn is_quota_exceeded(&self) -> bool {
+        self.status == "RESOURCE_EXHAUSTED"
+    }
+}
+
+/// Masks `key=` query parameters in text that may quote a request URL, so an
+/// upstream error can be logged and returned without leaking the API key.
+pub fn redact_api_key(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("key=") {
+        let (before, after) = rest.split_at(pos + "key=".len());
+        redacted.push_str(before);
+        let end = after
+            .find(|c: char| matches!(c, '&' | '"' | '\'' | ')') || c.is_whitespace())
+            .unwrap_or(after.len());
+        if before[..pos].ends_with(['?', '&']) && end > 0 {
+            redacted.push_str("[REDACTED]");
+            rest = &after[end..];
+        } else {
+            rest = after;
+        }
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Parses a protobuf duration such as `37s` or `1.5s`.
+fn parse_delay(delay: &str) -> Option<Duration> {
+    let secs: f64 = delay.strip_suffix('s')?.parse().ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Maps a failed model call to an `ApiError` with `status`, except exhausted
+/// quota, which becomes a 429 `quota_exceeded` with Gemini's retry delay.
+pub fn model_call_error(status: StatusCode, error: String) -> ApiError {
+    match GeminiError::parse(&error).filter(GeminiError::is_quota_exceeded) {
+        Some(gemini) => ApiError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            message: format!("Gemini API quota exceeded: {}", gemini.message),
+            code: Some("quota_exceeded"),
+            retry_after: gemini.retry_delay,
+        },
+        None => ApiError::from((status, redact_api_key(&error))),
+    }
+}
+
+/// Data of an SSE `error` event for a model call that failed on exhausted
+/// quota, shaped like the JSON error body: `{"__type":"error","error":
+/// "quota_exceeded","message":...,"retry_after_secs":...}`. `None` for other
+/// failures, which streams report as text.
+pub fn quota_exceeded_event(error: &str) -> Option<String> {
+    let error = model_call_error(StatusCode::BAD_GATEWAY, error.to_string());
+    let code = error.code?;
+    let mut data = json!({
+        "__type": "error",
+        "error": code,
+        "message": error.message,
+    });
+    if let Some(secs) = error.retry_after_secs() {
+        data["retry_after_secs"] = json!(secs);
+    }
+    Some(data.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_api_key_in_urls() {
+        let error = "error sending request for url (https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse&key=AIzaSecret123)";
+        let redacted = redact_api_key(error);
+        assert!(!redacted.contains("AIzaSecret123"));
+        assert!(redacted.ends_with("?alt=sse&key=[REDACTED])"));
+
+        assert_eq!(
+            redact_api_key(r#"{"url": "https://x.test/v1?key=abc&b=1"}"#),
+            r#"{"url": "https://x.test/v1?key=[REDACTED]&b=1"}"#
+        );
+        // Words that merely end in "key" are left alone
+        assert_eq!(redact_api_key("monkey=banana"), "monkey=banana");
+        assert_eq!(redact_api_key("no key here"), "no key here");
+    }
+
+    const RESOURCE_EXHAUSTED: &str = r#"CompletionError: ProviderError: {
+  "error": {
+    "code": 429,
+    "message": "You exceeded your current quota, please check your plan and billing details.",
+    "status": "RESOURCE_EXHAUSTED",
+    "details": [
+      {
+        "@type": "type.googleapis.com/google.rpc.QuotaFailure",
+        "violations": [{ "quotaMetric": "generativelanguage.googleapis.com/generate_content_free_tier_requests" }]
+      },
+      { "@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "37s" }
+    ]
+  }
+}
+"#;
+
+    #[test]
+    fn test_parses_resource_exhausted() {
+        let error = GeminiError::parse(RESOURCE_EXHAUSTED).unwrap();
+        assert_eq!(error.code, 429);
+        assert_eq!(error.status, "RESOURCE_EXHAUSTED");
+        assert!(error.message.starts_with("You exceeded your current quota"));
+        assert_eq!(error.retry_delay, Some(Duration::from_secs(37)));
+        assert!(error.is_quota_exceeded());
+
+        let api_error = model_call_error(StatusCode::BAD_GATEWAY, RESOURCE_EXHAUSTED.to_string());
+        assert_eq!(api_error.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(api_error.code, Some("quota_exceeded"));
+        assert_eq!(api_error.retry_after, Some(Duration::from_secs(37)));
+    }
+
+    #[test]
+    fn test_other_errors_keep_their_status() {
+        let overloaded = r#"ProviderError: {"error": {"code": 503, "message": "The model is overloaded.", "status": "UNAVAILABLE"}}"#;
+        let error = GeminiError::parse(overloaded).unwrap();
+        assert!(!error.is_quota_exceeded());
+        assert_eq!(error.retry_delay, None);
+
+        for message in [overloaded, "error sending request: connection refused"] {
+            let api_error = model_call_error(StatusCode::BAD_GATEWAY, message.to_string());
+            assert_eq!(api_error.status, StatusCode::BAD_GATEWAY);
+            assert_eq!(api_error.code, None);
+            assert_eq!(api_error.message, message);
+        }
+    }
+
+    #[test]
+    fn test_quota_exceeded_event() {
+        let data: Value =
+            serde_json::from_str(&quota_exceeded_event(RESOURCE_EXHAUSTED).unwrap()).unwrap();
+        assert_eq!(data["__type"], "error");
+        assert_eq!(data["error"], "quota_exceeded");
+        assert_eq!(data["retry_after_secs"], 37);
+        assert!(data["message"].as_str().unwrap().contains("exceeded"));
+
+        let overloaded = r#"ProviderError: {"error": {"code": 503, "message": "The model is overloaded.", "status": "UNAVAILABLE"}}"#;
+        assert_eq!(quota_exceeded_event(overloaded), None);
+    }
+
+    #[test]
+    fn test_parse_delay() {
+        assert_eq!(parse_delay("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_delay("37"), None);
+        assert_eq!(parse_delay("-1s"), None);
+    }
+}
@@ -0,0 +1,119 @@
+//! Passes screenshots captured by tools to the agent's own model.
+//!
+//! Gemini only takes text tool results, so a screenshot tool can't return its
+//! capture. The tool queues it in `PendingImages` instead, and `ImageRelay`,
+//! wrapped around the agent's completion model, appends the queued images as
+//! image parts to the next request of the run.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rig::OneOrMany;
+use rig::completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+use rig::message::{ImageMediaType, Message, UserContent};
+use rig::streaming::StreamingCompletionResponse;
+
+/// A captured image waiting for the agent's next model call.
+#[derive(Debug, Clone)]
+pub struct PendingImage {
+    /// What the image shows, e.g. the tool and question it was captured for
+    pub caption: String,
+    pub media_type: ImageMediaType,
+    /// Base64 payload, without a data-URL prefix
+    pub data: String,
+}
+
+/// Images queued by a run's tools, shared with its `ImageRelay`.
+#[derive(Clone, Default)]
+pub struct PendingImages(Arc<Mutex<Vec<PendingImage>>>);
+
+impl PendingImages {
+    pub fn push(&self, image: PendingImage) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(image);
+    }
+
+    /// Removes and returns the queued images, oldest first.
+    pub fn take(&self) -> Vec<PendingImage> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Appends the queued images to `request` as a user message, after the
+    /// tool results they belong to.
+    pub fn attach_to(&self, request: &mut CompletionRequest) {
+        let images = self.take();
+        if images.is_empty() {
+            return;
+        }
+
+        let parts = images.into_iter().flat_map(|image| {
+            [
+                UserContent::text(image.caption),
+                UserContent::image_base64(image.data, Some(image.media_type), None),
+            ]
+        });
+        if let Ok(content) = OneOrMany::many(parts) {
+            request.chat_history.push(Message::User { content });
+        }
+    }
+}
+
+/// A completion model that sends `PendingImages` with its next request.
+#[derive(Clone)]
+pub struct ImageRelay<M> {
+    model: M,
+    images: PendingImages,
+}
+
+impl<M> ImageRelay<M> {
+    pub fn new(model: M, images: PendingImages) -> Self {
+        Self { model, images }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for ImageRelay<M> {
+    type Response = M::Response;
+    type StreamingResponse = M::StreamingResponse;
+    type Client = M::Client;
+
+    fn make(client: &Self::Client, model: impl Into<String>) -> Self {
+        Self::new(M::make(client, model), PendingImages::default())
+    }
+
+    async fn completion(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        self.images.attach_to(&mut request);
+        self.model.completion(request).await
+    }
+
+    async fn stream(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        self.images.attach_to(&mut request);
+        self.model.stream(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queued_images_are_taken_once() {
+        let images = PendingImages::default();
+        images.push(PendingImage {
+            caption: "Screenshot from capture_region".to_string(),
+            media_type: ImageMediaType::JPEG,
+            data: "/9j/4AAQSkZJRg".to_string(),
+        });
+
+        let taken = images.take();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].caption, "Screenshot from capture_region");
+        assert!(images.take().is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+//! Checks whether the configured Gemini API key works, so setup problems show
+//! up before the first chat fails.
+
+use serde::Serialize;
+
+const GEMINI_API_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct KeyStatus {
+    /// Whether Gemini accepted the key
+    pub valid: bool,
+    /// Whether the key can currently use the model
+    pub model_access: bool,
+    pub message: String,
+}
+
+/// Makes a minimal `countTokens` call for `model` with the key.
+pub async fn check_api_key(
+    http: &reqwest::Client,
+    base_url: Option<&str>,
+    api_key: &str,
+    model: &str,
+) -> KeyStatus {
+    let base_url = base_url.unwrap_or(GEMINI_API_BASE_URL);
+    let url = format!("{}/v1beta/models/{}:countTokens", base_url, model);
+    let body = serde_json::json!({ "contents": [{ "parts": [{ "text": "ping" }] }] });
+
+    let response = match http
+        .post(url)
+        .header("x-goog-api-key", api_key)
+        .json(&body)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            return KeyStatus {
+                valid: false,
+                model_access: false,
+                message: format!("Could not reach Gemini: {}", e),
+            };
+        }
+    };
+
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    key_status_from_response(status, &body, model)
+}
+
+/// Maps a Gemini response to a `KeyStatus`, telling an invalid key apart
+/// from exhausted quota and missing model access.
+pub fn key_status_from_response(status: u16, body: &str, model: &str) -> KeyStatus {
+    let error: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let error_status = error["error"]["status"].as_str().unwrap_or_default();
+    let error_message = error["error"]["message"].as_str().unwrap_or(body).trim();
+
+    let (valid, model_access, message) = match status {
+        200..=299 => (true, true, format!("API key is valid for {}", model)),
+        429 => (
+            true,
+            false,
+            "API key is valid but its quota is exceeded".into(),
+        ),
+        _ if error_status == "RESOURCE_EXHAUSTED" => (
+            true,
+            false,
+            "API key is valid but its quota is exceeded".into(),
+        ),
+        400 if body.contains("API_KEY_INVALID") => (false, false, "API key is invalid".into()),
+        401 | 403 => (
+            false,
+            false,
+            format!("API key was rejected: {}", error_message),
+        ),
+        404 => (true, false, format!("API key has no access to {}", model)),
+        _ => (
+            false,
+            false,
+            format!("Unexpected response ({}): {}", status, error_message),
+        ),
+    };
+
+    KeyStatus {
+        valid,
+        model_access,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "gemini-2.5-flash";
+
+    #[test]
+    fn test_key_ok() {
+        let status = key_status_from_response(200, r#"{"totalTokens": 1}"#, MODEL);
+        assert!(status.valid);
+        assert!(status.model_access);
+    }
+
+    #[test]
+    fn test_key_invalid() {
+        let body = r#"{"error": {"code": 400, "message": "API key not valid. Please pass a valid API key.", "status": "INVALID_ARGUMENT", "details": [{"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "API_KEY_INVALID"}]}}"#;
+        let status = key_status_from_response(400, body, MODEL);
+        assert!(!status.valid);
+        assert!(!status.model_access);
+        assert_eq!(status.message, "API key is invalid");
+    }
+
+    #[test]
+    fn test_key_quota_exceeded() {
+        let body = r#"{"error": {"code": 429, "message": "You exceeded your current quota.", "status": "RESOURCE_EXHAUSTED"}}"#;
+        let status = key_status_from_response(429, body, MODEL);
+        assert!(status.valid);
+        assert!(!status.model_access);
+        assert!(status.message.contains("quota"));
+    }
+}
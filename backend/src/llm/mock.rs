@@ -0,0 +1,480 @@
+//! A stand-in for the Gemini API, used with `LLM_PROVIDER=mock` so the
+//! extension can be developed without an API key or network.
+//!
+//! It answers over the same HTTP API as Gemini, so every code path (rig's
+//! client, streaming and the tool loop) runs unchanged. Replies are
+//! deterministic: the question is echoed with a summary of the context sent
+//! with it, and when tools are declared the first tool without required
+//! arguments is called once before answering.
+
+use axum::{
+    Json, Router,
+    extract::Path,
+    response::{IntoResponse, Response, sse::Event},
+    routing::post,
+};
+use serde_json::{Value, json};
+use std::convert::Infallible;
+#[cfg(test)]
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use crate::utils::streaming::sse_response;
+
+/// What the mock model does in reply to a request.
+#[derive(Debug, PartialEq)]
+pub enum MockReply {
+    Text(String),
+    ToolCall { name: String, args: Value },
+}
+
+/// Routes of the mock API for any API version.
+pub fn mock_gemini_router() -> Router {
+    Router::new().route("/{version}/models/{*rest}", post(handle))
+}
+
+/// Serves the mock API on a free local port and returns its base URL.
+pub async fn spawn_mock_gemini() -> std::io::Result<String> {
+    serve_local(mock_gemini_router()).await
+}
+
+/// Serves `app` on a free local port and returns its base URL.
+pub async fn serve_local(app: Router) -> std::io::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!("Local server stopped: {}", e);
+        }
+    });
+    Ok(url)
+}
+
+/// Sends a `generateContent` response body, as a single SSE event when the
+/// call was `streamGenerateContent`.
+pub fn gemini_response(method: &str, response: Value) -> Response {
+    if method == "streamGenerateContent" {
+        let event = Ok::<_, Infallible>(Event::default().data(response.to_string()));
+        sse_response(futures::stream::iter([event]), std::time::Duration::ZERO)
+    } else {
+        Json(response).into_response()
+    }
+}
+
+async fn handle(Path((_, rest)): Path<(String, String)>, Json(body): Json<Value>) -> Response {
+    let (model, method) = rest.split_once(':').unwrap_or((&rest, "generateContent"));
+    let prompt_tokens = estimate_tokens(&body.to_string());
+
+    if method == "countTokens" {
+        return Json(json!({ "totalTokens": prompt_tokens })).into_response();
+    }
+
+    let reply = mock_reply(model, &body);
+    let part = match &reply {
+        MockReply::Text(text) => json!({ "text": text }),
+        MockReply::ToolCall { name, args } => {
+            json!({ "functionCall": { "name": name, "args": args } })
+        }
+    };
+    let output_tokens = estimate_tokens(&part.to_string());
+    gemini_response(
+        method,
+        json!({
+            "responseId": "mock",
+            "modelVersion": model,
+            "candidates": [{
+                "content": { "role": "model", "parts": [part] },
+                "finishReason": "STOP",
+                "index": 0
+            }],
+            "usageMetadata": {
+                "promptTokenCount": prompt_tokens,
+                "candidatesTokenCount": output_tokens,
+                "totalTokenCount": prompt_tokens + output_tokens
+            }
+        }),
+    )
+}
+
+/// The mock model's reply to a `generateContent` request body.
+pub fn mock_reply(model: &str, body: &Value) -> MockReply {
+    let last = body["contents"]
+        .as_array()
+        .and_then(|contents| contents.last())
+        .cloned()
+        .unwrap_or(Value::Null);
+    let parts = last["parts"].as_array().cloned().unwrap_or_default();
+
+    // After tools ran, report their results (all calls of a turn come back together)
+    let responses: Vec<&Value> = parts
+        .iter()
+        .filter_map(|part| part.get("functionResponse"))
+        .collect();
+    if !responses.is_empty() {
+        let names: Vec<&str> = responses
+            .iter()
+            .map(|response| response["name"].as_str().unwrap_or("tool"))
+            .collect();
+        let results: Vec<String> = responses
+            .iter()
+            .map(|response| response["response"].to_string())
+            .collect();
+        return MockReply::Text(format!(
+            "[mock {}] Tool {} selesai: {}",
+            model,
+            names.join(", "),
+            truncate(&results.join("; "), 200)
+        ));
+    }
+
+    if let Some(name) = first_tool_without_arguments(body) {
+        return MockReply::ToolCall {
+            name,
+            args: json!({}),
+        };
+    }
+
+    let text: String = parts
+        .iter()
+        .filter_map(|part| part["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let image_count = parts
+        .iter()
+        .filter(|part| part.get("inlineData").is_some())
+        .count();
+    MockReply::Text(describe_prompt(model, &text, image_count))
+}
+
+/// Echoes the question and lists the context sections (`## Heading`) with their size.
+fn describe_prompt(model: &str, text: &str, image_count: usize) -> String {
+    let mut sections = text.split("\n\n## ");
+    let question = sections.next().unwrap_or_default().trim();
+
+    let mut reply = format!("[mock {}] Pertanyaan: {}", model, question);
+    for section in sections {
+        let (heading, content) = section.split_once('\n').unwrap_or((section, ""));
+        reply.push_str(&format!(
+            "\n- {}: {} karakter",
+            heading.trim(),
+            content.trim().chars().count()
+        ));
+    }
+    if image_count > 0 {
+        reply.push_str(&format!("\n- Gambar: {}", image_count));
+    }
+    reply
+}
+
+/// The first declared function with no required parameters, so it can be
+/// called with empty arguments.
+fn first_tool_without_arguments(body: &Value) -> Option<String> {
+    let tools = match &body["tools"] {
+        Value::Array(tools) => tools.clone(),
+        Value::Null => Vec::new(),
+        tool => vec![tool.clone()],
+    };
+    tools
+        .iter()
+        .filter_map(|tool| tool["functionDeclarations"].as_array())
+        .flatten()
+        .find(|function| {
+            function["parameters"]["required"]
+                .as_array()
+                .is_none_or(|required| required.is_empty())
+        })
+        .and_then(|function| function["name"].as_str())
+        .map(str::to_string)
+}
+
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// A call received by a scripted Gemini API in tests.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct GeminiCall {
+    pub model: String,
+    /// `generateContent`, `streamGenerateContent` or `countTokens`
+    pub method: String,
+    /// The `x-goog-api-key` header
+    pub api_key: Option<String>,
+    pub query: HashMap<String, String>,
+    pub body: Value,
+}
+
+/// Calls received by a scripted Gemini API, in order.
+#[cfg(test)]
+pub type GeminiCalls = Arc<Mutex<Vec<GeminiCall>>>;
+
+/// Serves a Gemini API for tests that records every call and answers it
+/// with `respond`. Returns its base URL and the recorded calls.
+#[cfg(test)]
+pub async fn spawn_scripted_gemini<F, Fut>(respond: F) -> (String, GeminiCalls)
+where
+    F: Fn(GeminiCall) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    use axum::extract::Query;
+    use axum::http::HeaderMap;
+
+    let calls = GeminiCalls::default();
+    let recorded = calls.clone();
+    let app = Router::new().route(
+        "/{version}/models/{*rest}",
+        post(
+            move |Path((_, rest)): Path<(String, String)>,
+                  Query(query): Query<HashMap<String, String>>,
+                  headers: HeaderMap,
+                  Json(body): Json<Value>| {
+                let (respond, recorded) = (respond.clone(), recorded.clone());
+                async move {
+                    let (model, method) =
+                        rest.split_once(':').unwrap_or((&rest, "generateContent"));
+                    let call = GeminiCall {
+                        model: model.to_string(),
+                        method: method.to_string(),
+                        api_key: headers
+                            .get("x-goog-api-key")
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string),
+                        query,
+                        body,
+                    };
+                    recorded.lock().unwrap().push(call.clone());
+                    respond(call).await
+                }
+            },
+        ),
+    );
+    let url = serve_local(app)
+        .await
+        .expect("failed to serve scripted Gemini API");
+    (url, calls)
+}
+
+/// Answers `call` with a single text part and no usage metadata.
+#[cfg(test)]
+pub fn text_reply(call: &GeminiCall, text: &str) -> Response {
+    gemini_response(
+        &call.method,
+        json!({
+            "responseId": "r1",
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": text }] },
+                "finishReason": "STOP"
+            }]
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::state::AppState;
+    use axum::http::HeaderMap;
+    use rig::client::CompletionClient;
+    use rig::completion::{Prompt, ToolDefinition};
+    use rig::tool::{ToolDyn, ToolError};
+    use rig::wasm_compat::WasmBoxedFuture;
+
+    fn user_request(text: &str) -> Value {
+        json!({ "contents": [{ "role": "user", "parts": [{ "text": text }] }] })
+    }
+
+    #[test]
+    fn test_reply_echoes_question_and_context() {
+        let body = user_request("rangkum halaman ini\n\n## Page Content\nHalo dunia");
+        let reply = mock_reply("gemini-2.5-flash", &body);
+
+        assert_eq!(
+            reply,
+            MockReply::Text(
+                "[mock gemini-2.5-flash] Pertanyaan: rangkum halaman ini\n- Page Content: 10 karakter"
+                    .to_string()
+            )
+        );
+        // The same request always gets the same reply
+        assert_eq!(mock_reply("gemini-2.5-flash", &body), reply);
+    }
+
+    #[test]
+    fn test_declared_tool_is_called_then_reported() {
+        let mut body = user_request("klik tombol masuk");
+        body["tools"] = json!([{ "functionDeclarations": [
+            { "name": "click_element", "parameters": { "type": "object", "required": ["ref"] } },
+            { "name": "get_interactive_elements", "parameters": { "type": "object" } }
+        ] }]);
+        assert_eq!(
+            mock_reply("m", &body),
+            MockReply::ToolCall {
+                name: "get_interactive_elements".to_string(),
+                args: json!({}),
+            }
+        );
+
+        body["contents"].as_array_mut().unwrap().push(json!({
+            "role": "user",
+            "parts": [{ "functionResponse": {
+                "name": "get_interactive_elements",
+                "response": { "result": "[ref=1] button \"Masuk\"" }
+            } }]
+        }));
+        let MockReply::Text(text) = mock_reply("m", &body) else {
+            panic!("Expected a text reply after the tool result");
+        };
+        assert!(text.contains("Tool get_interactive_elements selesai"));
+        assert!(text.contains("Masuk"));
+    }
+
+    /// Counts how often it runs.
+    struct CountingTool(Arc<Mutex<usize>>);
+
+    impl ToolDyn for CountingTool {
+        fn name(&self) -> String {
+            "get_interactive_elements".to_string()
+        }
+
+        fn definition<'a>(&'a self, _prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+            Box::pin(async {
+                ToolDefinition {
+                    name: "get_interactive_elements".to_string(),
+                    description: "List the interactive elements".to_string(),
+                    parameters: json!({ "type": "object", "properties": {} }),
+                }
+            })
+        }
+
+        fn call<'a>(&'a self, _args: String) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+            *self.0.lock().unwrap() += 1;
+            Box::pin(async { Ok("\"[ref=1] button Masuk\"".to_string()) })
+        }
+    }
+
+    /// Answers with its name, like a browser tool.
+    struct NamedTool(&'static str);
+
+    impl ToolDyn for NamedTool {
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn definition<'a>(&'a self, _prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+            Box::pin(async {
+                ToolDefinition {
+                    name: self.0.to_string(),
+                    description: String::new(),
+                    parameters: json!({ "type": "object", "properties": {} }),
+                }
+            })
+        }
+
+        fn call<'a>(&'a self, _args: String) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+            Box::pin(async { Ok(format!("\"{} ok\"", self.0)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_tool_results_are_sent_in_one_content() {
+        // A model that calls two tools in one turn, then reports their results
+        // the way the mock does, recording every request
+        let (url, calls) = spawn_scripted_gemini(|call: GeminiCall| async move {
+            let parts = match mock_reply("m", &call.body) {
+                MockReply::Text(text) => json!([{ "text": text }]),
+                MockReply::ToolCall { .. } => json!([
+                    { "functionCall": { "name": "get_selection", "args": {} } },
+                    { "functionCall": { "name": "get_performance", "args": {} } }
+                ]),
+            };
+            gemini_response(
+                &call.method,
+                json!({
+                    "responseId": "r1",
+                    "candidates": [{ "content": { "role": "model", "parts": parts }, "finishReason": "STOP" }]
+                }),
+            )
+        })
+        .await;
+
+        let state = AppState::new(AppConfig {
+            gemini_base_url: Some(url),
+            ..AppConfig::for_tests()
+        });
+        let (gemini, _) = state.gemini_for_request(&HeaderMap::new());
+        let agent = gemini
+            .agent("m")
+            .tools(vec![
+                Box::new(NamedTool("get_selection")) as Box<dyn ToolDyn>,
+                Box::new(NamedTool("get_performance")),
+            ])
+            .default_max_depth(5)
+            .build();
+        let answer = agent.prompt("cek halaman").await.unwrap();
+        assert!(answer.contains("Tool get_selection, get_performance selesai"));
+
+        // Both results of the turn go back as parts of a single content
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        let with_results: Vec<&Value> = calls[1].body["contents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|content| {
+                content["parts"].as_array().is_some_and(|parts| {
+                    parts
+                        .iter()
+                        .any(|part| part.get("functionResponse").is_some())
+                })
+            })
+            .collect();
+        assert_eq!(with_results.len(), 1);
+        let names: Vec<&str> = with_results[0]["parts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|part| part["functionResponse"]["name"].as_str())
+            .collect();
+        assert_eq!(names, ["get_selection", "get_performance"]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_api_runs_the_tool_loop() {
+        let state = AppState::new(AppConfig {
+            gemini_base_url: Some(spawn_mock_gemini().await.unwrap()),
+            ..AppConfig::for_tests()
+        });
+        let (gemini, llm) = state.gemini_for_request(&HeaderMap::new());
+
+        // A plain completion echoes the question
+        let answer = llm
+            .complete("gemini-2.5-flash", "apa ini?", None, None)
+            .await
+            .unwrap();
+        assert_eq!(answer, "[mock gemini-2.5-flash] Pertanyaan: apa ini?");
+
+        // With tools, the tool runs once and its result is reported
+        let runs = Arc::new(Mutex::new(0));
+        let agent = gemini
+            .agent("gemini-2.5-flash")
+            .tools(vec![
+                Box::new(CountingTool(runs.clone())) as Box<dyn ToolDyn>
+            ])
+            .default_max_depth(5)
+            .build();
+        let answer = agent.prompt("klik tombol masuk").await.unwrap();
+        assert_eq!(*runs.lock().unwrap(), 1);
+        assert!(answer.contains("Tool get_interactive_elements selesai"));
+    }
+}
@@ -1,2 +1,9 @@
+pub mod cache;
+pub mod continuation;
+pub mod gemini_error;
+pub mod image_relay;
+pub mod key_check;
+pub mod mock;
 pub mod provider;
+pub mod router;
 pub use provider::*;
@@ -1,58 +1,130 @@
 use rig::OneOrMany;
-use rig::agent::MultiTurnStreamItem;
-use rig::completion::{GetTokenUsage, Prompt};
-use rig::message::{ImageMediaType, Message, UserContent};
+use rig::agent::{Agent, MultiTurnStreamItem};
+use rig::completion::{Completion, GetTokenUsage, Usage};
+use rig::message::{AssistantContent, ImageMediaType, Message, UserContent};
 use rig::prelude::*;
 use rig::providers::gemini;
+use rig::providers::gemini::completion::gemini_api_types::FinishReason;
 use rig::streaming::{StreamedAssistantContent, StreamingPrompt};
 
 use async_stream::stream;
+use axum::http::StatusCode;
+use base64::prelude::*;
 use futures::stream::{Stream, StreamExt};
+use image::ImageFormat;
+use reqwest::header::HeaderMap;
+use std::io::Cursor;
 use std::pin::Pin;
+use std::time::Duration;
 
+use super::continuation::{Turn, complete_with_continuations};
+use super::gemini_error::redact_api_key;
+use crate::error::ApiError;
+
+#[derive(Clone)]
 pub struct GeminiProvider {
     client: gemini::Client,
+    default_image_type: ImageMediaType,
+    seed: Option<i64>,
 }
 
 impl GeminiProvider {
-    pub fn new(client: gemini::Client) -> Self {
-        Self { client }
+    pub fn new(client: gemini::Client, default_image_type: ImageMediaType) -> Self {
+        Self {
+            client,
+            default_image_type,
+            seed: None,
+        }
+    }
+
+    /// Sends `seed` with every call made through this provider.
+    pub fn with_seed(mut self, seed: Option<i64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn agent(&self, model: &str, preamble: &str) -> Agent<gemini::completion::CompletionModel> {
+        let builder = self.client.agent(model).preamble(preamble);
+        match generation_params(self.seed) {
+            Some(params) => builder.additional_params(params).build(),
+            None => builder.build(),
+        }
     }
 
     pub async fn complete(
         &self,
+        model: &str,
         message: &str,
         custom_instruction: Option<&str>,
         image: Option<&str>,
     ) -> Result<String, String> {
+        self.complete_with_usage(model, message, custom_instruction, image)
+            .await
+            .map(|(text, _)| text)
+    }
+
+    /// Like `complete`, also returning the tokens used.
+    pub async fn complete_with_usage(
+        &self,
+        model: &str,
+        message: &str,
+        custom_instruction: Option<&str>,
+        image: Option<&str>,
+    ) -> Result<(String, Usage), String> {
+        let image = image.map(|img| prepare_image(img, self.default_image_type.clone()));
+        self.complete_prompt_with_usage(model, user_prompt(message, image), custom_instruction)
+            .await
+    }
+
+    async fn complete_prompt_with_usage(
+        &self,
+        model: &str,
+        prompt: Message,
+        custom_instruction: Option<&str>,
+    ) -> Result<(String, Usage), String> {
         let mut preamble =
             "WAJIB: Selalu jawab dalam Bahasa Indonesia kecuali diminta lain.".to_string();
         if let Some(instruction) = custom_instruction {
             preamble.push_str(&format!("\n\nINSTRUKSI TAMBAHAN: {}", instruction));
         }
 
-        let agent = self
-            .client
-            .agent(gemini::completion::GEMINI_2_5_FLASH)
-            .preamble(&preamble)
-            .build();
+        let agent = self.agent(model, &preamble);
+        let agent = &agent;
+        complete_with_continuations(prompt, |prompt, history| async move {
+            let response = agent
+                .completion(prompt, history)
+                .await
+                .map_err(|e| redact_api_key(&e.to_string()))?
+                .send()
+                .await
+                .map_err(|e| redact_api_key(&e.to_string()))?;
 
-        let mut parts = vec![UserContent::text(message.to_string())];
+            let text = response
+                .choice
+                .iter()
+                .filter_map(|content| match content {
+                    AssistantContent::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<String>();
+            let truncated = response
+                .raw_response
+                .candidates
+                .first()
+                .is_some_and(|c| matches!(c.finish_reason, Some(FinishReason::MaxTokens)));
 
-        if let Some(img_data) = image {
-            let (media_type, data) = parse_image_data(img_data);
-            parts.push(UserContent::image_base64(data, Some(media_type), None));
-        }
-
-        let prompt = Message::User {
-            content: OneOrMany::many(parts).expect("Parts list is not empty"),
-        };
-
-        agent.prompt(prompt).await.map_err(|e| e.to_string())
+            Ok(Turn {
+                text,
+                truncated,
+                usage: response.usage,
+            })
+        })
+        .await
     }
 
     pub fn stream(
         &self,
+        model: &str,
         message: &str,
         custom_instruction: Option<&str>,
         image: Option<&str>,
@@ -63,21 +135,17 @@ impl GeminiProvider {
             preamble.push_str(&format!("\n\nINSTRUKSI TAMBAHAN: {}", instruction));
         }
 
-        let client = self.client.clone();
+        let agent = self.agent(model, &preamble);
+        let default_image_type = self.default_image_type.clone();
         let message = message.to_string();
         let image = image.map(|s| s.to_string());
 
         Box::pin(stream! {
-            let agent = client
-                .agent(gemini::completion::GEMINI_2_5_FLASH)
-                .preamble(&preamble)
-                .build();
-
             let mut parts = vec![UserContent::text(message)];
 
             if let Some(img_data) = image {
-                let (media_type, data) = parse_image_data(&img_data);
-                parts.push(UserContent::image_base64(data.to_string(), Some(media_type), None));
+                let (media_type, data) = prepare_image(&img_data, default_image_type);
+                parts.push(UserContent::image_base64(data, Some(media_type), None));
             }
 
             let prompt = Message::User {
@@ -86,6 +154,9 @@ impl GeminiProvider {
 
             let mut rig_stream = agent.stream_prompt(prompt).await;
 
+            // Gemini reports usage with the stream's chunks; only the last
+            // figures are sent, once, after the answer
+            let mut usage = None;
             let mut chunk_count = 0;
             while let Some(chunk) = rig_stream.next().await {
                 chunk_count += 1;
@@ -95,51 +166,239 @@ impl GeminiProvider {
                         yield Ok::<String, String>(text.text);
                     }
                     Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Final(final_resp))) => {
-                        // Send token usage as special JSON marker at end of stream
                         // Final response from stream_prompt() contains token usage
                         tracing::info!("Got StreamedAssistantContent::Final");
-                        if let Some(usage) = final_resp.token_usage() {
-                            tracing::info!("Token usage: in={}, out={}, total={}", usage.input_tokens, usage.output_tokens, usage.total_tokens);
-                            let usage_json = format!(
-                                r#"{{"__type":"usage","input_tokens":{},"output_tokens":{},"total_tokens":{}}}"#,
-                                usage.input_tokens, usage.output_tokens, usage.total_tokens
-                            );
-                            yield Ok::<String, String>(usage_json);
-                        } else {
-                            tracing::warn!("Final response has no token usage");
+                        match final_resp.token_usage() {
+                            Some(final_usage) => usage = Some(final_usage),
+                            None => tracing::warn!("Final response has no token usage"),
                         }
                     }
                     Ok(MultiTurnStreamItem::FinalResponse(final_resp)) => {
                         // This is from multi-turn agent with tools - also has usage
                         tracing::info!("Got MultiTurnStreamItem::FinalResponse");
-                        let usage = final_resp.usage();
-                        let usage_json = format!(
-                            r#"{{"__type":"usage","input_tokens":{},"output_tokens":{},"total_tokens":{}}}"#,
-                            usage.input_tokens, usage.output_tokens, usage.total_tokens
-                        );
-                        yield Ok::<String, String>(usage_json);
+                        usage = Some(final_resp.usage());
                     }
                     Ok(other) => {
                         tracing::debug!("Got other stream item: {:?}", std::any::type_name_of_val(&other));
                     }
-                    Err(e) => yield Err::<String, String>(e.to_string()),
+                    Err(e) => yield Err::<String, String>(redact_api_key(&e.to_string())),
                 }
             }
+            // Send token usage as special JSON marker at end of stream
+            if let Some(usage) = usage {
+                tracing::info!("Token usage: in={}, out={}, total={}", usage.input_tokens, usage.output_tokens, usage.total_tokens);
+                yield Ok::<String, String>(format!(
+                    r#"{{"__type":"usage","input_tokens":{},"output_tokens":{},"total_tokens":{}}}"#,
+                    usage.input_tokens, usage.output_tokens, usage.total_tokens
+                ));
+            }
             tracing::info!("Stream ended after {} chunks", chunk_count);
         })
     }
 }
 
-pub fn parse_image_data(img_data: &str) -> (ImageMediaType, &str) {
+/// User-Agent sent on model API calls unless configured otherwise.
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Settings for the HTTP client used for model API calls.
+pub struct HttpClientConfig {
+    /// Timeout for establishing a connection to the model API
+    pub connect_timeout: Duration,
+    /// Timeout for a whole model API request, including a streamed body
+    pub request_timeout: Duration,
+    pub user_agent: String,
+    /// Extra headers sent on every request (e.g. a project header)
+    pub headers: HeaderMap,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(120),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: HeaderMap::new(),
+        }
+    }
+}
+
+/// Builds the HTTP client shared by all model calls so connections are pooled
+/// across requests instead of being set up per call.
+pub fn build_http_client(config: &HttpClientConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.request_timeout)
+        .user_agent(&config.user_agent)
+        .default_headers(config.headers.clone())
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+/// Builds the Gemini client, against `base_url` when set (e.g. a proxy).
+/// rig sends the key in the `x-goog-api-key` header, never in the URL.
+pub fn gemini_client(
+    api_key: &str,
+    base_url: Option<&str>,
+    http_client: reqwest::Client,
+) -> gemini::Client {
+    let builder = gemini::Client::<reqwest::Client>::builder()
+        .api_key(api_key)
+        .http_client(http_client);
+    let builder = match base_url {
+        Some(base_url) => builder.base_url(base_url),
+        None => builder,
+    };
+    builder.build().expect("Failed to build Gemini client")
+}
+
+/// Extra request parameters for a generation seed.
+///
+/// rig's `GenerationConfig` has no seed field and drops unknown ones, so the
+/// config is sent under its proto field name, which Gemini also accepts.
+/// Same seed and input make answers more repeatable, but Gemini doesn't
+/// guarantee identical output.
+pub fn generation_params(seed: Option<i64>) -> Option<serde_json::Value> {
+    seed.map(|seed| serde_json::json!({ "generation_config": { "seed": seed } }))
+}
+
+/// Builds a user message from text and an optional, already prepared image.
+pub fn user_prompt(message: &str, image: Option<(ImageMediaType, String)>) -> Message {
+    let mut parts = vec![UserContent::text(message.to_string())];
+
+    if let Some((media_type, data)) = image {
+        parts.push(UserContent::image_base64(data, Some(media_type), None));
+    }
+
+    Message::User {
+        content: OneOrMany::many(parts).expect("Parts list is not empty"),
+    }
+}
+
+/// Splits image data into its media type and base64 payload.
+///
+/// Data-URL prefixes win; otherwise the type is detected from the payload's
+/// signature, falling back to `default_type`.
+pub fn parse_image_data(img_data: &str, default_type: ImageMediaType) -> (ImageMediaType, &str) {
     if let Some(stripped) = img_data.strip_prefix("data:image/png;base64,") {
         (ImageMediaType::PNG, stripped)
     } else if let Some(stripped) = img_data.strip_prefix("data:image/jpeg;base64,") {
         (ImageMediaType::JPEG, stripped)
     } else if let Some(stripped) = img_data.strip_prefix("data:image/webp;base64,") {
         (ImageMediaType::WEBP, stripped)
-    } else if let Some(comma_pos) = img_data.find(',') {
-        (ImageMediaType::JPEG, &img_data[comma_pos + 1..])
     } else {
-        (ImageMediaType::JPEG, img_data)
+        let data = match img_data.find(',') {
+            Some(comma_pos) => &img_data[comma_pos + 1..],
+            None => img_data,
+        };
+        (detect_image_type(data).unwrap_or(default_type), data)
+    }
+}
+
+/// Like `parse_image_data`, but transcodes images Gemini doesn't accept
+/// (GIF, BMP, TIFF, ...) to PNG instead of sending them as-is.
+pub fn prepare_image(img_data: &str, default_type: ImageMediaType) -> (ImageMediaType, String) {
+    let (media_type, data) = parse_image_data(img_data, default_type);
+
+    let accepted = matches!(
+        detect_image_type(data),
+        Some(ImageMediaType::PNG | ImageMediaType::JPEG | ImageMediaType::WEBP)
+    );
+    if !accepted && let Some(png) = transcode_to_png(data) {
+        tracing::info!("Converted unsupported image format to PNG");
+        return (ImageMediaType::PNG, png);
+    }
+
+    (media_type, data.to_string())
+}
+
+/// Error for an image Gemini can't take, for handlers to return before any
+/// model call.
+pub fn unsupported_image_error() -> ApiError {
+    ApiError {
+        status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        message: "AVIF images aren't supported; send PNG, JPEG or WebP".to_string(),
+        code: Some("unsupported_image"),
+        retry_after: None,
+    }
+}
+
+/// Whether image data is AVIF, which Gemini doesn't accept and the `image`
+/// crate can't decode to convert. Recognized by a data-URL prefix or by the
+/// `ftyp` box's `avif`/`avis` brand.
+pub fn is_avif(img_data: &str) -> bool {
+    if img_data.starts_with("data:image/avif") {
+        return true;
+    }
+    let (_, data) = parse_image_data(img_data, ImageMediaType::JPEG);
+    // 16 base64 characters decode to the 12 bytes holding the brand
+    let Some(header) = data.trim().get(..16) else {
+        return false;
+    };
+    BASE64_STANDARD
+        .decode(header)
+        .is_ok_and(|bytes| &bytes[4..8] == b"ftyp" && matches!(&bytes[8..12], b"avif" | b"avis"))
+}
+
+/// Longest side, in pixels, of screenshots sent to the model.
+pub const MAX_SCREENSHOT_DIMENSION: u32 = 2048;
+
+/// Scales an image down so neither side exceeds `max_dimension`, returning
+/// a JPEG data URL. Images already within bounds, or that can't be decoded,
+/// are returned unchanged.
+pub fn downscale_image(img_data: &str, max_dimension: u32) -> String {
+    let (_, data) = parse_image_data(img_data, ImageMediaType::JPEG);
+    let Some(image) = BASE64_STANDARD
+        .decode(data.trim())
+        .ok()
+        .and_then(|bytes| image::load_from_memory(&bytes).ok())
+    else {
+        return img_data.to_string();
+    };
+    if image.width() <= max_dimension && image.height() <= max_dimension {
+        return img_data.to_string();
+    }
+
+    let resized = image.thumbnail(max_dimension, max_dimension).into_rgb8();
+    let mut jpeg = Cursor::new(Vec::new());
+    if resized.write_to(&mut jpeg, ImageFormat::Jpeg).is_err() {
+        return img_data.to_string();
+    }
+    tracing::info!(
+        "Downscaled {}x{} screenshot to {}x{}",
+        image.width(),
+        image.height(),
+        resized.width(),
+        resized.height()
+    );
+    format!(
+        "data:image/jpeg;base64,{}",
+        BASE64_STANDARD.encode(jpeg.into_inner())
+    )
+}
+
+/// Decodes base64 image data in any format the `image` crate reads and
+/// re-encodes it as base64 PNG. Returns `None` if the data can't be decoded.
+fn transcode_to_png(base64_data: &str) -> Option<String> {
+    let bytes = BASE64_STANDARD.decode(base64_data.trim()).ok()?;
+    let image = image::load_from_memory(&bytes).ok()?;
+
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageFormat::Png).ok()?;
+    Some(BASE64_STANDARD.encode(png.into_inner()))
+}
+
+/// Detects the image type from the base64-encoded magic bytes.
+fn detect_image_type(base64_data: &str) -> Option<ImageMediaType> {
+    if base64_data.starts_with("iVBORw0KGgo") {
+        Some(ImageMediaType::PNG)
+    } else if base64_data.starts_with("/9j/") {
+        Some(ImageMediaType::JPEG)
+    } else if base64_data.starts_with("R0lGOD") {
+        Some(ImageMediaType::GIF)
+    } else if base64_data.starts_with("UklGR") {
+        Some(ImageMediaType::WEBP)
+    } else {
+        None
     }
 }
@@ -0,0 +1,180 @@
+//! Picks the model for a request: the cheap flash model for simple queries,
+//! the pro model for long, image-heavy or explicitly hard ones.
+
+use rig::providers::gemini;
+
+use crate::dtos::AgentRequest;
+use crate::dtos::agent::Difficulty;
+
+pub const DEFAULT_PRO_MODEL: &str = "gemini-2.5-pro";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Flash,
+    Pro,
+}
+
+/// Rules for choosing between the flash and pro models.
+pub struct RoutingRules {
+    pub flash_model: String,
+    pub pro_model: String,
+    /// Queries with at least this many characters go to the pro model
+    pub pro_min_chars: usize,
+    /// Whether requests with an image go to the pro model
+    pub pro_on_image: bool,
+    /// Models a request may ask for explicitly; when empty, only the flash
+    /// and pro models are allowed
+    pub allowed_models: Vec<String>,
+}
+
+impl Default for RoutingRules {
+    fn default() -> Self {
+        Self {
+            flash_model: gemini::completion::GEMINI_2_5_FLASH.to_string(),
+            pro_model: DEFAULT_PRO_MODEL.to_string(),
+            pro_min_chars: 500,
+            pro_on_image: false,
+            allowed_models: Vec::new(),
+        }
+    }
+}
+
+/// The parts of a request the routing rules look at.
+pub struct RouteFeatures {
+    pub query_chars: usize,
+    pub has_image: bool,
+    pub difficulty: Option<Difficulty>,
+}
+
+impl RouteFeatures {
+    pub fn from_request(request: &AgentRequest) -> Self {
+        Self {
+            query_chars: request.query.chars().count(),
+            has_image: request.image.is_some(),
+            difficulty: request.difficulty,
+        }
+    }
+}
+
+impl RoutingRules {
+    /// An explicit difficulty hint wins; otherwise long queries, and images
+    /// when enabled, go to the pro model.
+    pub fn route(&self, features: &RouteFeatures) -> Route {
+        match features.difficulty {
+            Some(Difficulty::Easy) => Route::Flash,
+            Some(Difficulty::Hard) => Route::Pro,
+            None if features.query_chars >= self.pro_min_chars => Route::Pro,
+            None if features.has_image && self.pro_on_image => Route::Pro,
+            None => Route::Flash,
+        }
+    }
+
+    pub fn model(&self, route: Route) -> &str {
+        match route {
+            Route::Flash => &self.flash_model,
+            Route::Pro => &self.pro_model,
+        }
+    }
+
+    pub fn is_allowed(&self, model: &str) -> bool {
+        if self.allowed_models.is_empty() {
+            model == self.flash_model || model == self.pro_model
+        } else {
+            self.allowed_models.iter().any(|allowed| allowed == model)
+        }
+    }
+
+    /// The model for a request: the one it asked for, if allowed, or else the
+    /// routed one.
+    pub fn select_model(&self, request: &AgentRequest) -> Result<String, String> {
+        if let Some(requested) = &request.model {
+            if !self.is_allowed(requested) {
+                return Err(format!("Model not allowed: {}", requested));
+            }
+            tracing::info!("Using requested model {}", requested);
+            return Ok(requested.clone());
+        }
+
+        let route = self.route(&RouteFeatures::from_request(request));
+        let model = self.model(route).to_string();
+        tracing::info!("Routing request to {:?} model {}", route, model);
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(query_chars: usize, has_image: bool) -> RouteFeatures {
+        RouteFeatures {
+            query_chars,
+            has_image,
+            difficulty: None,
+        }
+    }
+
+    #[test]
+    fn test_long_request_routes_to_pro() {
+        let rules = RoutingRules::default();
+        assert_eq!(rules.route(&features(10, false)), Route::Flash);
+        assert_eq!(rules.route(&features(2000, false)), Route::Pro);
+        assert_eq!(rules.model(Route::Pro), DEFAULT_PRO_MODEL);
+        assert_eq!(
+            rules.model(Route::Flash),
+            gemini::completion::GEMINI_2_5_FLASH
+        );
+    }
+
+    #[test]
+    fn test_difficulty_hint_overrides_length() {
+        let rules = RoutingRules::default();
+        let hard_short = RouteFeatures {
+            difficulty: Some(Difficulty::Hard),
+            ..features(10, false)
+        };
+        assert_eq!(rules.route(&hard_short), Route::Pro);
+
+        let easy_long = RouteFeatures {
+            difficulty: Some(Difficulty::Easy),
+            ..features(2000, false)
+        };
+        assert_eq!(rules.route(&easy_long), Route::Flash);
+    }
+
+    #[test]
+    fn test_image_routing_is_configurable() {
+        let rules = RoutingRules::default();
+        assert_eq!(rules.route(&features(10, true)), Route::Flash);
+
+        let rules = RoutingRules {
+            pro_on_image: true,
+            ..RoutingRules::default()
+        };
+        assert_eq!(rules.route(&features(10, true)), Route::Pro);
+    }
+
+    fn request_for(model: &str) -> AgentRequest {
+        serde_json::from_value(serde_json::json!({ "query": "halo", "model": model })).unwrap()
+    }
+
+    #[test]
+    fn test_requested_model_must_be_allowed() {
+        let rules = RoutingRules::default();
+        assert_eq!(
+            rules.select_model(&request_for(DEFAULT_PRO_MODEL)),
+            Ok(DEFAULT_PRO_MODEL.to_string())
+        );
+        assert!(rules.select_model(&request_for("gemini-ultra")).is_err());
+
+        let rules = RoutingRules {
+            allowed_models: vec!["gemini-2.0-flash-lite".to_string()],
+            ..RoutingRules::default()
+        };
+        assert_eq!(
+            rules.select_model(&request_for("gemini-2.0-flash-lite")),
+            Ok("gemini-2.0-flash-lite".to_string())
+        );
+        assert!(rules.select_model(&request_for(DEFAULT_PRO_MODEL)).is_err());
+    }
+}
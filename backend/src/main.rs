@@ -18,28 +18,78 @@ use crate::state::AppState;
 #[tokio::main]
 async fn main() {
     // Load config
-    let config = config::AppConfig::from_env();
+    let mut config = config::AppConfig::from_env();
 
     // Initialize tracing
     tracing_subscriber::fmt::init();
 
+    // The mock provider is a local stand-in for the Gemini API
+    if config.llm_provider == config::LlmProvider::Mock {
+        let url = llm::mock::spawn_mock_gemini()
+            .await
+            .expect("failed to start the mock Gemini API");
+        tracing::warn!(
+            "LLM_PROVIDER=mock: model calls get canned replies from {}",
+            url
+        );
+        config.gemini_base_url = Some(url);
+    }
+
+    // Bind to port
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+
     // Create shared state
-    let state = Arc::new(AppState::new());
+    let state = Arc::new(AppState::new(config));
 
     // Build the router
-    let app = routes::app_router(state);
+    let app = routes::app_router(state.clone());
 
-    // Bind to port
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
     tracing::info!("Server running on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(state))
+        .await
+        .unwrap();
+    tracing::info!("Server shut down");
+}
+
+/// Resolves on Ctrl+C or SIGTERM. In-flight requests are allowed to finish
+/// before the server exits, so their writes aren't cut off mid-way, and
+/// connected clients are told to stagger their reconnects.
+async fn shutdown_signal(state: Arc<AppState>) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received, waiting for in-flight requests");
+    state.request_reconnect(state::RECONNECT_SPREAD).await;
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::llm::parse_image_data;
+    use crate::llm::{
+        GeminiProvider, HttpClientConfig, build_http_client, downscale_image, gemini_client,
+        is_avif, parse_image_data, prepare_image,
+    };
     use crate::models::{ChatRequest, ChatResponse, HealthResponse};
     use rig::message::ImageMediaType;
 
@@ -69,9 +119,11 @@ mod tests {
     fn test_chat_response_serialize() {
         let resp = ChatResponse {
             response: "Hi".to_string(),
+            reasoning: None,
             prompt_tokens: None,
             response_tokens: None,
             total_tokens: None,
+            cached: false,
         };
         let json = serde_json::to_string(&resp).unwrap();
         // Should not contain tokens since they are None and marked with skip_serializing_if
@@ -79,9 +131,11 @@ mod tests {
 
         let resp_with_tokens = ChatResponse {
             response: "Hi".to_string(),
+            reasoning: None,
             prompt_tokens: Some(10),
             response_tokens: Some(20),
             total_tokens: Some(30),
+            cached: false,
         };
         let json_with_tokens = serde_json::to_string(&resp_with_tokens).unwrap();
         assert!(json_with_tokens.contains(r#""prompt_tokens":10"#));
@@ -92,28 +146,227 @@ mod tests {
     #[test]
     fn test_base64_prefix_stripping() {
         let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAA...";
-        let (media_type, data) = parse_image_data(png);
+        let (media_type, data) = parse_image_data(png, ImageMediaType::JPEG);
         assert!(matches!(media_type, ImageMediaType::PNG));
         assert_eq!(data, "iVBORw0KGgoAAAANSUhEUgAA...");
 
         let jpeg = "data:image/jpeg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD...";
-        let (media_type, data) = parse_image_data(jpeg);
+        let (media_type, data) = parse_image_data(jpeg, ImageMediaType::JPEG);
         assert!(matches!(media_type, ImageMediaType::JPEG));
         assert_eq!(data, "/9j/4AAQSkZJRgABAQAAAQABAAD...");
 
         let webp = "data:image/webp;base64,UklGRtAAAABXRUJQVlA4...";
-        let (media_type, data) = parse_image_data(webp);
+        let (media_type, data) = parse_image_data(webp, ImageMediaType::JPEG);
         assert!(matches!(media_type, ImageMediaType::WEBP));
         assert_eq!(data, "UklGRtAAAABXRUJQVlA4...");
 
         let unknown_with_comma = "image/tiff,somebase64data";
-        let (media_type, data) = parse_image_data(unknown_with_comma);
+        let (media_type, data) = parse_image_data(unknown_with_comma, ImageMediaType::JPEG);
         assert!(matches!(media_type, ImageMediaType::JPEG));
         assert_eq!(data, "somebase64data");
 
         let raw_data = "somebase64datawithoutcomma";
-        let (media_type, data) = parse_image_data(raw_data);
+        let (media_type, data) = parse_image_data(raw_data, ImageMediaType::JPEG);
         assert!(matches!(media_type, ImageMediaType::JPEG));
         assert_eq!(data, "somebase64datawithoutcomma");
     }
+
+    #[test]
+    fn test_configured_default_image_type() {
+        // Prefix-less data without a recognizable signature uses the configured default
+        let raw_data = "somebase64datawithoutcomma";
+        let (media_type, data) = parse_image_data(raw_data, ImageMediaType::PNG);
+        assert!(matches!(media_type, ImageMediaType::PNG));
+        assert_eq!(data, raw_data);
+
+        // A recognizable signature wins over the configured default
+        let raw_png = "iVBORw0KGgoAAAANSUhEUgAA...";
+        let (media_type, _) = parse_image_data(raw_png, ImageMediaType::JPEG);
+        assert!(matches!(media_type, ImageMediaType::PNG));
+
+        let raw_jpeg = "/9j/4AAQSkZJRgABAQAAAQABAAD...";
+        let (media_type, _) = parse_image_data(raw_jpeg, ImageMediaType::PNG);
+        assert!(matches!(media_type, ImageMediaType::JPEG));
+    }
+
+    #[tokio::test]
+    async fn test_shared_http_client_reuses_connections() {
+        use axum::{Router, extract::ConnectInfo, routing::get};
+        use std::collections::HashSet;
+        use std::net::SocketAddr;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        // Record the client address of every request the server sees
+        let peers = Arc::new(Mutex::new(HashSet::new()));
+        let seen = peers.clone();
+        let app = Router::new().route(
+            "/",
+            get(
+                move |ConnectInfo(addr): ConnectInfo<SocketAddr>| async move {
+                    seen.lock().unwrap().insert(addr);
+                    "ok"
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        let client = build_http_client(&HttpClientConfig {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            ..HttpClientConfig::default()
+        });
+        for _ in 0..3 {
+            // Clones share the pool, as with the client held in AppState
+            let response = client.clone().get(&url).send().await.unwrap();
+            assert_eq!(response.text().await.unwrap(), "ok");
+        }
+
+        assert_eq!(peers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_unsupported_image_format_is_converted() {
+        use base64::prelude::*;
+        use image::{ImageFormat, RgbImage};
+        use std::io::Cursor;
+
+        // BMP isn't accepted by Gemini
+        let mut bmp = Cursor::new(Vec::new());
+        RgbImage::new(2, 2)
+            .write_to(&mut bmp, ImageFormat::Bmp)
+            .unwrap();
+        let data_url = format!(
+            "data:image/bmp;base64,{}",
+            BASE64_STANDARD.encode(bmp.into_inner())
+        );
+
+        let (media_type, data) = prepare_image(&data_url, ImageMediaType::JPEG);
+        assert!(matches!(media_type, ImageMediaType::PNG));
+        assert!(data.starts_with("iVBORw0KGgo"));
+        let decoded = BASE64_STANDARD.decode(&data).unwrap();
+        assert_eq!(image::guess_format(&decoded).unwrap(), ImageFormat::Png);
+
+        // Accepted formats and undecodable data pass through unchanged
+        let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAA...";
+        let (media_type, data) = prepare_image(png, ImageMediaType::JPEG);
+        assert!(matches!(media_type, ImageMediaType::PNG));
+        assert_eq!(data, "iVBORw0KGgoAAAANSUhEUgAA...");
+
+        let (media_type, data) = prepare_image("somebase64data", ImageMediaType::JPEG);
+        assert!(matches!(media_type, ImageMediaType::JPEG));
+        assert_eq!(data, "somebase64data");
+    }
+
+    #[test]
+    fn test_avif_is_detected() {
+        assert!(is_avif(
+            "data:image/avif;base64,AAAAHGZ0eXBhdmlmAAAAAG1pZjE="
+        ));
+        // Without a prefix, or under the wrong one, the ftyp brand gives it away
+        assert!(is_avif("AAAAHGZ0eXBhdmlmAAAAAG1pZjE="));
+        assert!(is_avif("data:image/jpeg;base64,AAAAIGZ0eXBhdmlz"));
+
+        assert!(!is_avif("data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAA"));
+        assert!(!is_avif("/9j/4AAQSkZJRgABAQ"));
+        assert!(!is_avif("short"));
+    }
+
+    #[tokio::test]
+    async fn test_configured_headers_are_sent() {
+        use axum::{Router, http::HeaderMap, routing::get};
+        use reqwest::header::{HeaderName, HeaderValue};
+        use std::sync::{Arc, Mutex};
+
+        // Capture the headers of the request the server receives
+        let captured = Arc::new(Mutex::new(HeaderMap::new()));
+        let sink = captured.clone();
+        let app = Router::new().route(
+            "/",
+            get(move |headers: HeaderMap| async move {
+                *sink.lock().unwrap() = headers;
+                "ok"
+            }),
+        );
+        let url = crate::llm::mock::serve_local(app).await.unwrap() + "/";
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-project"),
+            HeaderValue::from_static("browser-ai"),
+        );
+        let client = build_http_client(&HttpClientConfig {
+            user_agent: "custom-agent/1.0".to_string(),
+            headers,
+            ..HttpClientConfig::default()
+        });
+        client.get(&url).send().await.unwrap();
+
+        let received = captured.lock().unwrap();
+        assert_eq!(received["user-agent"], "custom-agent/1.0");
+        assert_eq!(received["x-project"], "browser-ai");
+    }
+
+    #[test]
+    fn test_large_screenshot_is_downscaled() {
+        use base64::prelude::*;
+        use image::{ImageFormat, RgbImage};
+        use std::io::Cursor;
+
+        let encode = |w, h| {
+            let mut png = Cursor::new(Vec::new());
+            RgbImage::new(w, h)
+                .write_to(&mut png, ImageFormat::Png)
+                .unwrap();
+            format!(
+                "data:image/png;base64,{}",
+                BASE64_STANDARD.encode(png.into_inner())
+            )
+        };
+
+        // A tall full-page capture keeps its aspect ratio within the bound
+        let tall = encode(100, 400);
+        let scaled = downscale_image(&tall, 200);
+        let data = scaled.strip_prefix("data:image/jpeg;base64,").unwrap();
+        let decoded = image::load_from_memory(&BASE64_STANDARD.decode(data).unwrap()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (50, 200));
+
+        // Small images and undecodable data pass through unchanged
+        let small = encode(10, 10);
+        assert_eq!(downscale_image(&small, 200), small);
+        assert_eq!(downscale_image("not an image", 200), "not an image");
+    }
+
+    #[tokio::test]
+    async fn test_seed_is_sent_in_generation_config() {
+        use crate::llm::mock::{GeminiCall, spawn_scripted_gemini, text_reply};
+
+        let (url, calls) =
+            spawn_scripted_gemini(|call: GeminiCall| async move { text_reply(&call, "Halo") })
+                .await;
+
+        let client = gemini_client("test-key", Some(&url), reqwest::Client::new());
+        let llm = GeminiProvider::new(client, ImageMediaType::JPEG);
+        llm.clone()
+            .with_seed(Some(42))
+            .complete("gemini-2.5-flash", "Halo", None, None)
+            .await
+            .unwrap();
+        llm.complete("gemini-2.5-flash", "Halo", None, None)
+            .await
+            .unwrap();
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls[0].body["generation_config"]["seed"], 42);
+        assert!(calls[1].body.get("generation_config").is_none());
+    }
 }
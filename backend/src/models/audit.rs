@@ -0,0 +1,163 @@
+//! Metadata about what page data each agent request sent to the model, so
+//! users can check what left their browser. Content itself is never stored,
+//! and only requests with a session are recorded, listed per session.
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::agent::context::PageText;
+use crate::dtos::AgentRequest;
+
+/// Maximum number of audit entries kept; the oldest are dropped first.
+pub const MAX_AUDIT_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    /// Unix time in seconds
+    pub timestamp: u64,
+    pub session_id: String,
+    pub url: Option<String>,
+    pub model: String,
+    /// Characters of page content sent
+    pub content_length: usize,
+    /// Characters of selected text sent
+    pub selection_length: usize,
+    pub screenshot_sent: bool,
+    /// Earlier messages sent along with the query
+    pub history_messages: usize,
+}
+
+impl AuditEntry {
+    /// Entry for a prompt that carried `sent` page text and `history_messages`
+    /// earlier messages, plus the request's screenshot. `None` for requests
+    /// that aren't recorded: incognito ones and those without a session.
+    pub fn new(
+        request: &AgentRequest,
+        model: &str,
+        sent: Option<PageText>,
+        history_messages: usize,
+        timestamp: u64,
+    ) -> Option<Self> {
+        let session_id = request.session_id.clone().filter(|_| !request.incognito)?;
+        let (content_length, selection_length) = match sent {
+            Some(PageText::Content(text)) => (text.chars().count(), 0),
+            Some(PageText::Selection(text)) => (0, text.chars().count()),
+            None => (0, 0),
+        };
+        Some(Self {
+            timestamp,
+            session_id,
+            url: request.page_url.clone(),
+            model: model.to_string(),
+            content_length,
+            selection_length,
+            screenshot_sent: request.image.is_some(),
+            history_messages,
+        })
+    }
+}
+
+/// Current Unix time in seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[derive(Default)]
+pub struct AuditLog {
+    entries: VecDeque<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, entry: AuditEntry) {
+        if self.entries.len() == MAX_AUDIT_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// A session's entries with `from <= timestamp <= to`, oldest first.
+    /// Open ends are unbounded.
+    pub fn query(&self, session_id: &str, from: Option<u64>, to: Option<u64>) -> Vec<AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.session_id == session_id)
+            .filter(|entry| from.is_none_or(|from| entry.timestamp >= from))
+            .filter(|entry| to.is_none_or(|to| entry.timestamp <= to))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(session_id: &str, timestamp: u64) -> AuditEntry {
+        let request: AgentRequest = serde_json::from_value(serde_json::json!({
+            "query": "rangkum",
+            "session_id": session_id,
+            "page_url": "https://example.com",
+            "page_content": "Halo dunia",
+        }))
+        .unwrap();
+        let sent = PageText::of(&request);
+        AuditEntry::new(&request, "gemini-2.5-flash", sent, 0, timestamp).unwrap()
+    }
+
+    #[test]
+    fn test_query_filters_by_session_and_time_range() {
+        let mut log = AuditLog::new();
+        for timestamp in [100, 200, 300] {
+            log.record(entry("s1", timestamp));
+        }
+        log.record(entry("s2", 200));
+
+        let timestamps = |entries: Vec<AuditEntry>| -> Vec<u64> {
+            entries.iter().map(|entry| entry.timestamp).collect()
+        };
+        assert_eq!(
+            timestamps(log.query("s1", Some(150), Some(300))),
+            vec![200, 300]
+        );
+        assert_eq!(timestamps(log.query("s1", None, Some(100))), vec![100]);
+        assert_eq!(timestamps(log.query("s1", None, None)), vec![100, 200, 300]);
+        assert_eq!(timestamps(log.query("s2", None, None)), vec![200]);
+        assert!(log.query("s3", None, None).is_empty());
+    }
+
+    #[test]
+    fn test_entry_counts_only_the_text_sent() {
+        let request: AgentRequest = serde_json::from_value(serde_json::json!({
+            "query": "rangkum",
+            "session_id": "s1",
+            "page_content": "Halo dunia",
+            "selection": "Halo",
+        }))
+        .unwrap();
+        let entry =
+            AuditEntry::new(&request, "gemini-2.5-flash", PageText::of(&request), 0, 1).unwrap();
+        assert_eq!((entry.content_length, entry.selection_length), (0, 4));
+
+        let entry = AuditEntry::new(&request, "gemini-2.5-flash", None, 0, 1).unwrap();
+        assert_eq!((entry.content_length, entry.selection_length), (0, 0));
+    }
+
+    #[test]
+    fn test_incognito_and_sessionless_requests_are_not_recorded() {
+        for request in [
+            serde_json::json!({ "query": "rangkum", "session_id": "s1", "incognito": true }),
+            serde_json::json!({ "query": "rangkum" }),
+        ] {
+            let request: AgentRequest = serde_json::from_value(request).unwrap();
+            assert!(AuditEntry::new(&request, "gemini-2.5-flash", None, 0, 1).is_none());
+        }
+    }
+}
@@ -19,10 +19,16 @@ pub struct ChatRequest {
 #[derive(Serialize)]
 pub struct ChatResponse {
     pub response: String,
+    /// The model's reasoning, when the request asked for it and the model gave it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prompt_tokens: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub response_tokens: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_tokens: Option<i32>,
+    /// Whether the answer was served from the response cache
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
 }
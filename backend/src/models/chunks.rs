@@ -0,0 +1,283 @@
+//! Reassembly of WebSocket messages too large for a single frame.
+//!
+//! The extension splits a large message (usually an `ActionResult` carrying a
+//! screenshot) into `chunked_data` frames that share the action's
+//! `request_id`. Once all `total` chunks have arrived, their `data` joined in
+//! `seq` order is the text of the original message.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How long a partly received message may go without a new chunk before it is dropped.
+pub const CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most chunks a single message may be split into.
+pub const MAX_CHUNKS: u32 = 1024;
+/// Most chunked messages a connection may be receiving at once.
+pub const MAX_PENDING_MESSAGES: usize = 16;
+/// Most bytes a single reassembled message may hold.
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+/// Most bytes of partly received messages a connection may hold at once.
+pub const MAX_PENDING_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum ChunkError {
+    /// `seq` is not below `total`, or `total` is zero or above `MAX_CHUNKS`
+    InvalidChunk {
+        seq: u32,
+        total: u32,
+    },
+    /// A chunk disagrees with earlier chunks about the number of chunks
+    TotalMismatch {
+        expected: u32,
+        got: u32,
+    },
+    TooManyPending,
+    /// The message would grow past `MAX_MESSAGE_BYTES`
+    MessageTooLarge,
+    /// The connection's partly received messages would grow past `MAX_PENDING_BYTES`
+    TooManyPendingBytes,
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidChunk { seq, total } => {
+                write!(f, "Invalid chunk {} of {}", seq, total)
+            }
+            Self::TotalMismatch { expected, got } => {
+                write!(f, "Chunk total changed from {} to {}", expected, got)
+            }
+            Self::TooManyPending => write!(
+                f,
+                "Too many chunked messages in progress (max {})",
+                MAX_PENDING_MESSAGES
+            ),
+            Self::MessageTooLarge => write!(
+                f,
+                "Chunked message is too large (max {} bytes)",
+                MAX_MESSAGE_BYTES
+            ),
+            Self::TooManyPendingBytes => write!(
+                f,
+                "Too much chunked data in progress (max {} bytes)",
+                MAX_PENDING_BYTES
+            ),
+        }
+    }
+}
+
+/// A chunked message dropped before all of its chunks arrived.
+#[derive(Debug, PartialEq)]
+pub struct IncompleteMessage {
+    pub request_id: String,
+    pub received: u32,
+    pub total: u32,
+}
+
+struct PendingMessage {
+    parts: Vec<Option<String>>,
+    received: u32,
+    bytes: usize,
+    last_chunk: Instant,
+}
+
+/// Chunks received so far on one connection, by `request_id`.
+#[derive(Default)]
+pub struct ChunkAssembler {
+    pending: HashMap<String, PendingMessage>,
+    /// Total `bytes` of the `pending` messages
+    bytes: usize,
+}
+
+impl ChunkAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk, returning the whole message once every chunk has arrived.
+    /// A chunk that doesn't fit the message drops what was received for it.
+    pub fn push(
+        &mut self,
+        request_id: &str,
+        seq: u32,
+        total: u32,
+        data: String,
+        now: Instant,
+    ) -> Result<Option<String>, ChunkError> {
+        if total == 0 || total > MAX_CHUNKS || seq >= total {
+            self.remove(request_id);
+            return Err(ChunkError::InvalidChunk { seq, total });
+        }
+        if !self.pending.contains_key(request_id) && self.pending.len() >= MAX_PENDING_MESSAGES {
+            return Err(ChunkError::TooManyPending);
+        }
+
+        let message = self
+            .pending
+            .entry(request_id.to_string())
+            .or_insert_with(|| PendingMessage {
+                parts: vec![None; total as usize],
+                received: 0,
+                bytes: 0,
+                last_chunk: now,
+            });
+        let expected = message.parts.len() as u32;
+        if expected != total {
+            self.remove(request_id);
+            return Err(ChunkError::TotalMismatch {
+                expected,
+                got: total,
+            });
+        }
+
+        // A resent chunk replaces the earlier copy
+        let part = &mut message.parts[seq as usize];
+        let replaced = part.as_ref().map_or(0, String::len);
+        let message_bytes = message.bytes - replaced + data.len();
+        let pending_bytes = self.bytes - replaced + data.len();
+        if message_bytes > MAX_MESSAGE_BYTES {
+            self.remove(request_id);
+            return Err(ChunkError::MessageTooLarge);
+        }
+        if pending_bytes > MAX_PENDING_BYTES {
+            self.remove(request_id);
+            return Err(ChunkError::TooManyPendingBytes);
+        }
+
+        if part.is_none() {
+            message.received += 1;
+        }
+        *part = Some(data);
+        message.bytes = message_bytes;
+        message.last_chunk = now;
+        self.bytes = pending_bytes;
+
+        if message.received < total {
+            return Ok(None);
+        }
+        Ok(self
+            .remove(request_id)
+            .map(|message| message.parts.into_iter().flatten().collect()))
+    }
+
+    /// Drops a partly received message, releasing its bytes.
+    fn remove(&mut self, request_id: &str) -> Option<PendingMessage> {
+        let message = self.pending.remove(request_id)?;
+        self.bytes -= message.bytes;
+        Some(message)
+    }
+
+    /// Drops messages that received no chunk within `CHUNK_TIMEOUT` of `now`.
+    pub fn expire(&mut self, now: Instant) -> Vec<IncompleteMessage> {
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, message)| now.duration_since(message.last_chunk) >= CHUNK_TIMEOUT)
+            .map(|(request_id, _)| request_id.clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|request_id| {
+                let message = self.remove(&request_id)?;
+                Some(IncompleteMessage {
+                    request_id,
+                    received: message.received,
+                    total: message.parts.len() as u32,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reassembles_three_chunks_in_any_order() {
+        let mut chunks = ChunkAssembler::new();
+        let now = Instant::now();
+
+        assert_eq!(chunks.push("r1", 2, 3, "ghi".into(), now), Ok(None));
+        assert_eq!(chunks.push("r1", 0, 3, "abc".into(), now), Ok(None));
+        assert_eq!(
+            chunks.push("r1", 1, 3, "def".into(), now),
+            Ok(Some("abcdefghi".to_string()))
+        );
+        assert!(chunks.expire(now + CHUNK_TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn test_missing_chunk_expires() {
+        let mut chunks = ChunkAssembler::new();
+        let now = Instant::now();
+
+        chunks.push("r1", 0, 3, "abc".into(), now).unwrap();
+        chunks.push("r1", 2, 3, "ghi".into(), now).unwrap();
+        assert!(chunks.expire(now + CHUNK_TIMEOUT / 2).is_empty());
+
+        assert_eq!(
+            chunks.expire(now + CHUNK_TIMEOUT),
+            vec![IncompleteMessage {
+                request_id: "r1".to_string(),
+                received: 2,
+                total: 3,
+            }]
+        );
+        // The late chunk starts a new message instead of completing the dropped one
+        assert_eq!(
+            chunks.push("r1", 1, 3, "def".into(), now + CHUNK_TIMEOUT),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_chunks() {
+        let mut chunks = ChunkAssembler::new();
+        let now = Instant::now();
+
+        assert_eq!(
+            chunks.push("r1", 3, 3, "x".into(), now),
+            Err(ChunkError::InvalidChunk { seq: 3, total: 3 })
+        );
+        chunks.push("r1", 0, 3, "abc".into(), now).unwrap();
+        assert_eq!(
+            chunks.push("r1", 1, 4, "def".into(), now),
+            Err(ChunkError::TotalMismatch {
+                expected: 3,
+                got: 4
+            })
+        );
+        assert!(chunks.expire(now + CHUNK_TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_oversized_messages() {
+        let mut chunks = ChunkAssembler::new();
+        let now = Instant::now();
+        let half = "a".repeat(MAX_MESSAGE_BYTES / 2);
+
+        chunks.push("r1", 0, 3, half.clone(), now).unwrap();
+        chunks.push("r1", 1, 3, half.clone(), now).unwrap();
+        assert_eq!(
+            chunks.push("r1", 2, 3, "a".into(), now),
+            Err(ChunkError::MessageTooLarge)
+        );
+        // The oversized message was dropped along with its bytes
+        assert!(chunks.expire(now + CHUNK_TIMEOUT).is_empty());
+        assert_eq!(chunks.bytes, 0);
+
+        // Messages that fit on their own still share the connection's limit
+        chunks.push("r2", 0, 2, half.clone(), now).unwrap();
+        chunks.push("r3", 0, 2, half.clone(), now).unwrap();
+        chunks.push("r4", 0, 2, half.clone(), now).unwrap();
+        chunks.push("r5", 0, 2, half.clone(), now).unwrap();
+        assert_eq!(
+            chunks.push("r6", 0, 2, half, now),
+            Err(ChunkError::TooManyPendingBytes)
+        );
+        assert_eq!(chunks.bytes, MAX_PENDING_BYTES);
+    }
+}
@@ -1,4 +1,10 @@
+pub mod audit;
 pub mod chat;
+pub mod chunks;
+pub mod run;
+pub mod screenshots;
+pub mod snapshot;
+pub mod transcript;
 pub mod ws;
 
 pub use chat::ChatResponse;
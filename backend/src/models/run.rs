@@ -0,0 +1,202 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::agent::steps::AgentStep;
+use crate::dtos::AgentRequest;
+use crate::dtos::agent::{
+    AttachedFileDto, ChatMessageDto, Difficulty, OnToolError, ResponseFormat, Verbosity,
+};
+
+/// Maximum number of agent runs kept for resuming.
+pub const MAX_AGENT_RUNS: usize = 100;
+
+/// Context of a tool-enabled agent run, kept so it can be resumed after the
+/// user completes a step manually (e.g. solving a CAPTCHA).
+#[derive(Debug, Clone)]
+pub struct AgentRun {
+    pub session_id: String,
+    /// The task the run was started with.
+    pub task: String,
+    pub history: Vec<ChatMessageDto>,
+    pub verbosity: Verbosity,
+    pub format: Option<ResponseFormat>,
+    pub difficulty: Option<Difficulty>,
+    pub model: Option<String>,
+    pub translate_to: Option<String>,
+    pub concise_actions: bool,
+    pub on_tool_error: OnToolError,
+    pub safe_mode: bool,
+    /// Files sent with the task, so `upload_file` still works after resuming
+    pub files: Option<Vec<AttachedFileDto>>,
+    /// Turns completed by this run, with the tool actions taken in each
+    pub turns: Vec<RunTurn>,
+}
+
+/// One completed turn of a run.
+#[derive(Debug, Clone)]
+pub struct RunTurn {
+    pub query: String,
+    pub steps: Vec<AgentStep>,
+    pub response: String,
+}
+
+impl AgentRun {
+    pub fn new(session_id: &str, request: &AgentRequest) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            task: request.query.clone(),
+            history: request.history.clone().unwrap_or_default(),
+            verbosity: request.verbosity,
+            format: request.format,
+            difficulty: request.difficulty,
+            model: request.model.clone(),
+            translate_to: request.translate_to.clone(),
+            concise_actions: request.concise_actions,
+            on_tool_error: request.on_tool_error,
+            safe_mode: request.safe_mode,
+            files: request.files.clone(),
+            turns: Vec::new(),
+        }
+    }
+
+    /// Appends a completed turn to the run's history.
+    pub fn record_turn(&mut self, query: &str, response: &str, steps: Vec<AgentStep>) {
+        self.history.push(ChatMessageDto {
+            role: "user".to_string(),
+            content: query.to_string(),
+        });
+        self.history.push(ChatMessageDto {
+            role: "assistant".to_string(),
+            content: response.to_string(),
+        });
+        self.turns.push(RunTurn {
+            query: query.to_string(),
+            steps,
+            response: response.to_string(),
+        });
+    }
+
+    /// Builds the request that continues the run: the page is re-scanned since
+    /// the user changed it, then the original task carries on.
+    pub fn resume_request(&self) -> AgentRequest {
+        AgentRequest {
+            query: format!(
+                "I completed the manual step in the browser. Re-scan the page with get_interactive_elements() and continue the original task: {}",
+                self.task
+            ),
+            session_id: Some(self.session_id.clone()),
+            stream: true,
+            image: None,
+            custom_instruction: None,
+            interactive_elements: None,
+            page_content: None,
+            page_url: None,
+            selection: None,
+            history: Some(self.history.clone()),
+            tools: true,
+            verbosity: self.verbosity,
+            format: self.format,
+            incognito: false,
+            difficulty: self.difficulty,
+            model: self.model.clone(),
+            translate_to: self.translate_to.clone(),
+            seed: None,
+            concise_actions: self.concise_actions,
+            use_content: true,
+            use_screenshot: true,
+            show_reasoning: false,
+            on_tool_error: self.on_tool_error,
+            safe_mode: self.safe_mode,
+            files: self.files.clone(),
+        }
+    }
+}
+
+/// Agent runs by id, evicting the oldest run once the cap is reached.
+#[derive(Default)]
+pub struct AgentRunStore {
+    runs: HashMap<String, AgentRun>,
+    order: VecDeque<String>,
+}
+
+impl AgentRunStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, run_id: String, run: AgentRun) {
+        if !self.runs.contains_key(&run_id) {
+            self.order.push_back(run_id.clone());
+            if self.order.len() > MAX_AGENT_RUNS
+                && let Some(oldest) = self.order.pop_front()
+            {
+                self.runs.remove(&oldest);
+            }
+        }
+        self.runs.insert(run_id, run);
+    }
+
+    pub fn get(&self, run_id: &str) -> Option<&AgentRun> {
+        self.runs.get(run_id)
+    }
+
+    pub fn get_mut(&mut self, run_id: &str) -> Option<&mut AgentRun> {
+        self.runs.get_mut(run_id)
+    }
+
+    /// Runs of a session, oldest first.
+    pub fn for_session<'a>(&'a self, session_id: &'a str) -> impl Iterator<Item = &'a AgentRun> {
+        self.order
+            .iter()
+            .filter_map(|run_id| self.runs.get(run_id))
+            .filter(move |run| run.session_id == session_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(query: &str) -> AgentRequest {
+        serde_json::from_value(serde_json::json!({
+            "query": query,
+            "session_id": "s1",
+            "verbosity": "terse",
+            "files": [{ "name": "cv.pdf", "data": "data:application/pdf;base64,JVBERi0=" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resume_continues_with_updated_context() {
+        let mut run = AgentRun::new("s1", &request("daftar akun baru"));
+        run.record_turn(
+            "daftar akun baru",
+            "Silakan selesaikan CAPTCHA terlebih dahulu.",
+            Vec::new(),
+        );
+
+        let resumed = run.resume_request();
+        assert!(resumed.wants_tools());
+        assert_eq!(resumed.session_id.as_deref(), Some("s1"));
+        assert!(resumed.query.contains("get_interactive_elements()"));
+        assert!(resumed.query.ends_with("daftar akun baru"));
+        assert_eq!(resumed.verbosity, Verbosity::Terse);
+        // Attached files can still be uploaded
+        assert_eq!(resumed.files.unwrap()[0].name, "cv.pdf");
+
+        let history = resumed.history.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].role, "assistant");
+        assert!(history[1].content.contains("CAPTCHA"));
+    }
+
+    #[test]
+    fn test_store_evicts_oldest_run() {
+        let mut store = AgentRunStore::new();
+        for i in 0..=MAX_AGENT_RUNS {
+            store.insert(i.to_string(), AgentRun::new("s1", &request("task")));
+        }
+        assert!(store.get("0").is_none());
+        assert!(store.get(&MAX_AGENT_RUNS.to_string()).is_some());
+    }
+}
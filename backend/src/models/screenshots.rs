@@ -0,0 +1,119 @@
+//! Screenshots the agent captures while it works, fanned out to clients
+//! watching a session so they see the browser state the agent sees.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Screenshots buffered per session for a slow watcher before the oldest are skipped.
+pub const SCREENSHOT_FEED_CAPACITY: usize = 8;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenshotEvent {
+    /// Tool that captured the screenshot, e.g. `full_page_screenshot`
+    pub tool: String,
+    /// Image as a data URL
+    pub image: String,
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+}
+
+/// Per-session channels of captured screenshots.
+#[derive(Default)]
+pub struct ScreenshotFeeds {
+    feeds: HashMap<String, broadcast::Sender<ScreenshotEvent>>,
+}
+
+impl ScreenshotFeeds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts watching a session's screenshots.
+    pub fn subscribe(&mut self, session_id: &str) -> broadcast::Receiver<ScreenshotEvent> {
+        self.feeds
+            .entry(session_id.to_string())
+            .or_insert_with(|| broadcast::channel(SCREENSHOT_FEED_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Drops a session's feed once its last watcher is gone.
+    pub fn release(&mut self, session_id: &str) {
+        if self
+            .feeds
+            .get(session_id)
+            .is_some_and(|feed| feed.receiver_count() == 0)
+        {
+            self.feeds.remove(session_id);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn is_watched(&self, session_id: &str) -> bool {
+        self.feeds.contains_key(session_id)
+    }
+
+    /// Sends a screenshot to the session's watchers. A feed nobody watches
+    /// anymore is dropped.
+    pub fn publish(&mut self, session_id: &str, event: ScreenshotEvent) {
+        let Some(feed) = self.feeds.get(session_id) else {
+            return;
+        };
+        if feed.send(event).is_err() {
+            self.feeds.remove(session_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(tool: &str) -> ScreenshotEvent {
+        ScreenshotEvent {
+            tool: tool.to_string(),
+            image: "data:image/png;base64,AAAA".to_string(),
+            timestamp: 1,
+        }
+    }
+
+    #[test]
+    fn test_watchers_receive_their_sessions_screenshots() {
+        let mut feeds = ScreenshotFeeds::new();
+        let mut s1 = feeds.subscribe("s1");
+        let mut s2 = feeds.subscribe("s2");
+
+        feeds.publish("s1", event("capture_element"));
+
+        assert_eq!(s1.try_recv().unwrap().tool, "capture_element");
+        assert!(s2.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_unwatched_feed_is_dropped() {
+        let mut feeds = ScreenshotFeeds::new();
+        // Nobody ever watched: nothing to send to
+        feeds.publish("s1", event("full_page_screenshot"));
+        assert!(feeds.feeds.is_empty());
+
+        drop(feeds.subscribe("s1"));
+        feeds.publish("s1", event("full_page_screenshot"));
+        assert!(feeds.feeds.is_empty());
+    }
+
+    #[test]
+    fn test_feed_is_released_with_its_last_watcher() {
+        let mut feeds = ScreenshotFeeds::new();
+        let first = feeds.subscribe("s1");
+        let second = feeds.subscribe("s1");
+
+        drop(first);
+        feeds.release("s1");
+        assert!(feeds.is_watched("s1"));
+
+        drop(second);
+        feeds.release("s1");
+        assert!(!feeds.is_watched("s1"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+/// Maximum number of URLs to keep snapshots for.
+pub const MAX_PAGE_SNAPSHOTS: usize = 100;
+/// Maximum number of characters stored per snapshot.
+pub const MAX_SNAPSHOT_CHARS: usize = 5000;
+
+pub struct PageSnapshot {
+    pub hash: u64,
+    pub content: String,
+}
+
+/// Lines added and removed between two snapshots of the same page.
+#[derive(Debug, Default, PartialEq)]
+pub struct PageDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl PageDiff {
+    pub fn between(old: &str, new: &str) -> Self {
+        let old_lines: HashSet<&str> = non_empty_lines(old).collect();
+        let new_lines: HashSet<&str> = non_empty_lines(new).collect();
+
+        Self {
+            added: non_empty_lines(new)
+                .filter(|line| !old_lines.contains(line))
+                .map(str::to_string)
+                .collect(),
+            removed: non_empty_lines(old)
+                .filter(|line| !new_lines.contains(line))
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Formats the diff as a prompt section.
+    pub fn to_prompt(&self) -> String {
+        if self.is_empty() {
+            return "## Changes Since Last Visit\nNo changes since the last visit.".to_string();
+        }
+
+        let mut out = "## Changes Since Last Visit".to_string();
+        if !self.added.is_empty() {
+            out.push_str("\nAdded:");
+            for line in &self.added {
+                out.push_str(&format!("\n+ {}", line));
+            }
+        }
+        if !self.removed.is_empty() {
+            out.push_str("\nRemoved:");
+            for line in &self.removed {
+                out.push_str(&format!("\n- {}", line));
+            }
+        }
+        out
+    }
+}
+
+fn non_empty_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Per-URL page snapshots, evicting the oldest URL once the cap is reached.
+#[derive(Default)]
+pub struct PageSnapshotStore {
+    snapshots: HashMap<String, PageSnapshot>,
+    order: VecDeque<String>,
+}
+
+impl PageSnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a snapshot for the URL, returning the diff against the previous
+    /// snapshot if the URL was seen before.
+    pub fn record(&mut self, url: &str, content: &str) -> Option<PageDiff> {
+        let content: String = content.chars().take(MAX_SNAPSHOT_CHARS).collect();
+        let hash = content_hash(&content);
+
+        let diff = self.snapshots.get(url).map(|previous| {
+            if previous.hash == hash {
+                PageDiff::default()
+            } else {
+                PageDiff::between(&previous.content, &content)
+            }
+        });
+
+        if diff.is_none() {
+            self.order.push_back(url.to_string());
+            if self.order.len() > MAX_PAGE_SNAPSHOTS
+                && let Some(oldest) = self.order.pop_front()
+            {
+                self.snapshots.remove(&oldest);
+            }
+        }
+        self.snapshots
+            .insert(url.to_string(), PageSnapshot { hash, content });
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_visit_has_no_diff() {
+        let mut store = PageSnapshotStore::new();
+        assert!(store.record("https://example.com", "Hello").is_none());
+    }
+
+    #[test]
+    fn test_unchanged_page_reports_no_changes() {
+        let mut store = PageSnapshotStore::new();
+        store.record("https://example.com", "Headline A\nStory A");
+        let diff = store
+            .record("https://example.com", "Headline A\nStory A")
+            .unwrap();
+        assert!(diff.is_empty());
+        assert!(diff.to_prompt().contains("No changes"));
+    }
+
+    #[test]
+    fn test_changed_page_produces_diff() {
+        let mut store = PageSnapshotStore::new();
+        store.record("https://example.com", "Headline A\nStory A");
+        let diff = store
+            .record("https://example.com", "Headline B\nStory A")
+            .unwrap();
+        assert!(!diff.is_empty());
+        assert_eq!(diff.added, vec!["Headline B"]);
+        assert_eq!(diff.removed, vec!["Headline A"]);
+    }
+
+    #[test]
+    fn test_store_is_capped() {
+        let mut store = PageSnapshotStore::new();
+        for i in 0..MAX_PAGE_SNAPSHOTS + 5 {
+            store.record(&format!("https://example.com/{}", i), "content");
+        }
+        assert_eq!(store.snapshots.len(), MAX_PAGE_SNAPSHOTS);
+        // The oldest URL was evicted, so it is treated as a first visit
+        assert!(store.record("https://example.com/0", "content").is_none());
+    }
+}
@@ -0,0 +1,130 @@
+//! Shareable transcript of a session's agent runs, for bug reports: each
+//! user message, the tool actions taken for it and the final answer.
+
+use serde::Serialize;
+
+use crate::agent::steps::AgentStep;
+use crate::models::run::AgentRun;
+
+#[derive(Debug, Serialize)]
+pub struct Transcript {
+    pub session_id: String,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TranscriptEntry {
+    Message { role: String, content: String },
+    Action(AgentStep),
+}
+
+impl Transcript {
+    /// Assembles the transcript from a session's runs, oldest first.
+    pub fn from_runs<'a>(session_id: &str, runs: impl IntoIterator<Item = &'a AgentRun>) -> Self {
+        let mut entries = Vec::new();
+        for turn in runs.into_iter().flat_map(|run| &run.turns) {
+            entries.push(TranscriptEntry::Message {
+                role: "user".to_string(),
+                content: turn.query.clone(),
+            });
+            entries.extend(turn.steps.iter().cloned().map(TranscriptEntry::Action));
+            entries.push(TranscriptEntry::Message {
+                role: "assistant".to_string(),
+                content: turn.response.clone(),
+            });
+        }
+
+        Self {
+            session_id: session_id.to_string(),
+            entries,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# Transcript {}\n", self.session_id);
+        for entry in &self.entries {
+            match entry {
+                TranscriptEntry::Message { role, content } => {
+                    markdown.push_str(&format!("\n## {}\n\n{}\n", role, content));
+                }
+                TranscriptEntry::Action(step) => {
+                    markdown.push_str(&format!("\n- **Action:** `{}`\n", step.action));
+                    if let Some(thought) = &step.thought {
+                        markdown.push_str(&format!("  - Thought: {}\n", thought));
+                    }
+                    if let Some(result) = &step.result {
+                        markdown.push_str(&format!("  - Result: {}\n", result));
+                    }
+                }
+            }
+        }
+        markdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::AgentRequest;
+
+    fn step(action: &str, result: &str) -> AgentStep {
+        AgentStep {
+            thought: None,
+            action: action.to_string(),
+            result: Some(result.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_transcript_has_messages_and_actions_in_order() {
+        let request: AgentRequest = serde_json::from_value(
+            serde_json::json!({ "query": "klik login", "session_id": "s1" }),
+        )
+        .unwrap();
+        let mut run = AgentRun::new("s1", &request);
+        run.record_turn(
+            "klik login",
+            "Sudah saya klik.",
+            vec![
+                step(
+                    r#"get_interactive_elements({})"#,
+                    "[button ref=3 \"Login\"]",
+                ),
+                step(r#"click_element({"ref":3})"#, "Success"),
+            ],
+        );
+        run.record_turn("terima kasih", "Sama-sama.", Vec::new());
+
+        let transcript = Transcript::from_runs("s1", [&run]);
+        let kinds: Vec<String> = transcript
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                TranscriptEntry::Message { role, .. } => role.clone(),
+                TranscriptEntry::Action(step) => step.action.clone(),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "user",
+                "get_interactive_elements({})",
+                r#"click_element({"ref":3})"#,
+                "assistant",
+                "user",
+                "assistant",
+            ]
+        );
+
+        let json = serde_json::to_value(&transcript).unwrap();
+        assert_eq!(json["entries"][1]["kind"], "action");
+        assert_eq!(json["entries"][1]["result"], "[button ref=3 \"Login\"]");
+
+        let markdown = transcript.to_markdown();
+        let login = markdown.find("klik login").unwrap();
+        let click = markdown.find("click_element").unwrap();
+        let answer = markdown.find("Sudah saya klik.").unwrap();
+        assert!(login < click && click < answer);
+    }
+}
@@ -19,11 +19,44 @@ pub enum WsMessage {
         command: ActionCommand,
     },
     ActionResult(ActionResult),
+    /// Sent before the server closes the connection; the client should wait
+    /// `after_ms` before reconnecting
+    #[serde(rename = "reconnect")]
+    Reconnect {
+        after_ms: u64,
+    },
+    /// Part `seq` (0-based) of `total` of a message too large for one frame;
+    /// the joined `data` is the message's JSON text
+    #[serde(rename = "chunked_data")]
+    ChunkedData {
+        request_id: String,
+        seq: u32,
+        total: u32,
+        data: String,
+    },
+    /// Progress of a browser action, for the extension's activity timeline
+    #[serde(rename = "action_log")]
+    ActionLog {
+        request_id: String,
+        /// The command's `type`, e.g. `click_element`
+        tool: String,
+        status: ActionLogStatus,
+        /// Unix time in milliseconds
+        timestamp: u64,
+    },
     #[serde(other)]
     Unknown,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionLogStatus {
+    Started,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type")]
 pub enum ActionCommand {
     #[serde(rename = "navigate_to")]
@@ -39,12 +72,246 @@ pub enum ActionCommand {
         ref_id: i32,
         text: String,
     },
+    #[serde(rename = "clear_field")]
+    ClearField {
+        #[serde(rename = "ref")]
+        ref_id: i32,
+    },
+    #[serde(rename = "set_checked")]
+    SetChecked {
+        #[serde(rename = "ref")]
+        ref_id: i32,
+        checked: bool,
+    },
+    /// Attaches a file the user provided to a file input
+    #[serde(rename = "upload_file")]
+    UploadFile {
+        #[serde(rename = "ref")]
+        ref_id: i32,
+        file_name: String,
+        mime_type: Option<String>,
+        /// File content, base64 without a data-URL prefix
+        data_base64: String,
+    },
     #[serde(rename = "scroll_to")]
     ScrollTo { x: i32, y: i32 },
+    #[serde(rename = "scroll_element")]
+    ScrollElement {
+        #[serde(rename = "ref")]
+        ref_id: i32,
+        dx: i32,
+        dy: i32,
+    },
     #[serde(rename = "get_page_content")]
     GetPageContent { max_length: Option<usize> },
     #[serde(rename = "get_interactive_elements")]
     GetInteractiveElements { limit: Option<usize> },
+    #[serde(rename = "go_history")]
+    GoHistory { delta: i32 },
+    #[serde(rename = "count_elements")]
+    CountElements { selector: String },
+    #[serde(rename = "handle_dialog")]
+    HandleDialog {
+        action: DialogAction,
+        prompt_text: Option<String>,
+    },
+    #[serde(rename = "get_selection")]
+    GetSelection,
+    #[serde(rename = "get_performance")]
+    GetPerformance,
+    #[serde(rename = "get_live_regions")]
+    GetLiveRegions,
+    #[serde(rename = "get_console_logs")]
+    GetConsoleLogs { levels: Vec<ConsoleLevel> },
+    #[serde(rename = "full_page_screenshot")]
+    FullPageScreenshot,
+    #[serde(rename = "capture_region")]
+    CaptureRegion { x: i32, y: i32, w: u32, h: u32 },
+    #[serde(rename = "capture_element")]
+    CaptureElement {
+        #[serde(rename = "ref")]
+        ref_id: i32,
+    },
+}
+
+/// How to respond to a pending native dialog (`alert`, `confirm`, `prompt`).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DialogAction {
+    Accept,
+    Dismiss,
+}
+
+/// Severity of a console message; only errors and warnings are captured.
+/// `get_console_logs` with no levels returns both.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleLevel {
+    Error,
+    Warn,
+}
+
+/// `data` of a `navigate_to` result.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NavigationData {
+    pub navigated_to: String,
+}
+
+/// `data` of a `set_checked` result.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CheckedData {
+    pub checked: bool,
+    /// False when the element was already in the requested state
+    pub changed: bool,
+}
+
+/// `data` of a `scroll_element` result: the container's new scroll offsets.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScrollPosition {
+    pub scroll_top: f64,
+    pub scroll_left: f64,
+    #[serde(default)]
+    pub at_bottom: bool,
+}
+
+/// `data` of a `get_page_content` result.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PageContentData {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    pub text: String,
+}
+
+/// `data` of a `get_interactive_elements` result, in document order.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InteractiveElementsData {
+    pub elements: Vec<ElementData>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ElementData {
+    pub id: i32,
+    pub role: String,
+    #[serde(default)]
+    pub name: String,
+    /// Set for checkboxes, radio buttons and switches
+    pub checked: Option<bool>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// `data` of an `upload_file` result.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UploadData {
+    pub file_name: String,
+    /// Size in bytes of the attached file
+    pub size: u64,
+}
+
+/// `data` of a `go_history` result.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HistoryData {
+    pub url: String,
+}
+
+/// `data` of a `count_elements` result.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CountData {
+    pub count: u64,
+}
+
+/// `data` of a `handle_dialog` result.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DialogData {
+    pub action: DialogAction,
+}
+
+/// `data` of a `get_selection` result; `text` is empty when nothing is selected.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SelectionData {
+    pub text: String,
+    pub context: Option<SelectionContext>,
+}
+
+/// The block around a selection.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SelectionContext {
+    pub tag: String,
+    pub text: String,
+}
+
+/// Page load metrics returned by `get_performance`, in milliseconds. Metrics
+/// the browser doesn't provide are `None`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PerformanceMetrics {
+    #[serde(default)]
+    pub available: bool,
+    pub ttfb_ms: Option<u64>,
+    pub dom_content_loaded_ms: Option<u64>,
+    pub load_ms: Option<u64>,
+    pub lcp_ms: Option<u64>,
+    /// Bytes transferred for the document
+    pub transfer_size: Option<u64>,
+    #[serde(default)]
+    pub resource_count: u32,
+    #[serde(default)]
+    pub slowest_resources: Vec<ResourceTiming>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourceTiming {
+    pub url: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub duration_ms: u64,
+}
+
+/// ARIA live regions returned by `get_live_regions`: their current text, and
+/// what they announced since the page loaded (most recent last).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LiveRegions {
+    #[serde(default)]
+    pub regions: Vec<LiveRegion>,
+    #[serde(default)]
+    pub announcements: Vec<LiveAnnouncement>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LiveRegion {
+    /// `polite` or `assertive`
+    pub politeness: String,
+    pub role: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LiveAnnouncement {
+    pub text: String,
+    pub politeness: String,
+    /// How long ago the text was announced
+    pub age_ms: u64,
+}
+
+/// Console messages returned by `get_console_logs`, most recent last.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ConsoleLogs {
+    #[serde(default)]
+    pub entries: Vec<ConsoleEntry>,
+    /// Matching messages left out to stay within the cap
+    #[serde(default)]
+    pub omitted: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConsoleEntry {
+    pub level: ConsoleLevel,
+    pub message: String,
+    /// Script location for uncaught errors, e.g. `app.js:12:5`
+    pub source: Option<String>,
+    /// How long ago the message was logged
+    pub age_ms: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -66,6 +333,43 @@ mod tests {
         assert_eq!(serialized, r#"{"type":"Ping"}"#);
     }
 
+    #[test]
+    fn test_reconnect_serialization() {
+        let msg = WsMessage::Reconnect { after_ms: 1500 };
+        let serialized = serde_json::to_string(&msg).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"reconnect","data":{"after_ms":1500}}"#
+        );
+
+        let parsed: WsMessage = serde_json::from_str(&serialized).unwrap();
+        assert!(matches!(parsed, WsMessage::Reconnect { after_ms: 1500 }));
+    }
+
+    #[test]
+    fn test_action_log_serialization() {
+        let msg = WsMessage::ActionLog {
+            request_id: "r1".to_string(),
+            tool: "click_element".to_string(),
+            status: ActionLogStatus::Succeeded,
+            timestamp: 1760600000000,
+        };
+        let serialized = serde_json::to_string(&msg).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_log","data":{"request_id":"r1","tool":"click_element","status":"succeeded","timestamp":1760600000000}}"#
+        );
+
+        let parsed: WsMessage = serde_json::from_str(&serialized).unwrap();
+        assert!(matches!(
+            parsed,
+            WsMessage::ActionLog {
+                status: ActionLogStatus::Succeeded,
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn test_action_command_serialization() {
         let cmd = WsMessage::ActionRequest {
@@ -91,6 +395,284 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_clear_field_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::ClearField { ref_id: 7 },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"clear_field","ref":7}}}"#
+        );
+
+        let cmd: ActionCommand = serde_json::from_str(r#"{"type":"clear_field","ref":7}"#).unwrap();
+        assert!(matches!(cmd, ActionCommand::ClearField { ref_id: 7 }));
+    }
+
+    #[test]
+    fn test_set_checked_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::SetChecked {
+                ref_id: 4,
+                checked: true,
+            },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"set_checked","ref":4,"checked":true}}}"#
+        );
+    }
+
+    #[test]
+    fn test_upload_file_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::UploadFile {
+                ref_id: 6,
+                file_name: "cv.pdf".to_string(),
+                mime_type: Some("application/pdf".to_string()),
+                data_base64: "JVBERi0=".to_string(),
+            },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"upload_file","ref":6,"file_name":"cv.pdf","mime_type":"application/pdf","data_base64":"JVBERi0="}}}"#
+        );
+
+        let data: UploadData = serde_json::from_str(r#"{"file_name":"cv.pdf","size":5}"#).unwrap();
+        assert_eq!(data.size, 5);
+    }
+
+    #[test]
+    fn test_scroll_element_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::ScrollElement {
+                ref_id: 9,
+                dx: 0,
+                dy: 400,
+            },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"scroll_element","ref":9,"dx":0,"dy":400}}}"#
+        );
+    }
+
+    #[test]
+    fn test_go_history_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::GoHistory { delta: -2 },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"go_history","delta":-2}}}"#
+        );
+    }
+
+    #[test]
+    fn test_count_elements_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::CountElements {
+                selector: "table tr".to_string(),
+            },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"count_elements","selector":"table tr"}}}"#
+        );
+    }
+
+    #[test]
+    fn test_handle_dialog_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::HandleDialog {
+                action: DialogAction::Accept,
+                prompt_text: Some("yes".to_string()),
+            },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"handle_dialog","action":"accept","prompt_text":"yes"}}}"#
+        );
+
+        let cmd: ActionCommand = serde_json::from_str(
+            r#"{"type":"handle_dialog","action":"dismiss","prompt_text":null}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            cmd,
+            ActionCommand::HandleDialog {
+                action: DialogAction::Dismiss,
+                prompt_text: None
+            }
+        ));
+    }
+
+    #[test]
+    fn test_get_selection_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::GetSelection,
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"get_selection"}}}"#
+        );
+    }
+
+    #[test]
+    fn test_get_performance_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::GetPerformance,
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"get_performance"}}}"#
+        );
+
+        let metrics: PerformanceMetrics = serde_json::from_value(serde_json::json!({
+            "available": true,
+            "ttfb_ms": 250,
+            "dom_content_loaded_ms": 900,
+            "load_ms": 1500,
+            "lcp_ms": null,
+            "transfer_size": 2048,
+            "resource_count": 2,
+            "slowest_resources": [{ "url": "https://cdn.test/b.png", "type": "img", "duration_ms": 400 }]
+        }))
+        .unwrap();
+        assert_eq!(metrics.ttfb_ms, Some(250));
+        assert_eq!(metrics.lcp_ms, None);
+        assert_eq!(metrics.slowest_resources[0].kind, "img");
+    }
+
+    #[test]
+    fn test_chunked_data_deserialization() {
+        let json = r#"{"type":"chunked_data","data":{"request_id":"123","seq":1,"total":3,"data":"{\"type\":"}}"#;
+        let msg: WsMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            msg,
+            WsMessage::ChunkedData { request_id, seq: 1, total: 3, data }
+                if request_id == "123" && data == r#"{"type":"#
+        ));
+    }
+
+    #[test]
+    fn test_get_live_regions_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::GetLiveRegions,
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"get_live_regions"}}}"#
+        );
+
+        let live: LiveRegions = serde_json::from_value(serde_json::json!({
+            "regions": [{ "politeness": "polite", "role": "status", "text": "" }],
+            "announcements": [{ "text": "Saved", "politeness": "polite", "age_ms": 1200 }]
+        }))
+        .unwrap();
+        assert_eq!(live.regions[0].role.as_deref(), Some("status"));
+        assert_eq!(live.announcements[0].text, "Saved");
+
+        let empty: LiveRegions = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(empty.regions.is_empty() && empty.announcements.is_empty());
+    }
+
+    #[test]
+    fn test_get_console_logs_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::GetConsoleLogs {
+                levels: vec![ConsoleLevel::Error, ConsoleLevel::Warn],
+            },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"get_console_logs","levels":["error","warn"]}}}"#
+        );
+
+        let logs: ConsoleLogs = serde_json::from_value(serde_json::json!({
+            "entries": [{
+                "level": "error",
+                "message": "TypeError: x is undefined",
+                "source": "app.js:12:5",
+                "age_ms": 800
+            }],
+            "omitted": 3
+        }))
+        .unwrap();
+        assert_eq!(logs.entries[0].level, ConsoleLevel::Error);
+        assert_eq!(logs.entries[0].source.as_deref(), Some("app.js:12:5"));
+        assert_eq!(logs.omitted, 3);
+
+        let empty: ConsoleLogs = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(empty.entries.is_empty() && empty.omitted == 0);
+    }
+
+    #[test]
+    fn test_capture_element_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::CaptureElement { ref_id: 7 },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"capture_element","ref":7}}}"#
+        );
+    }
+
+    #[test]
+    fn test_full_page_screenshot_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::FullPageScreenshot,
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"full_page_screenshot"}}}"#
+        );
+    }
+
+    #[test]
+    fn test_capture_region_serialization() {
+        let cmd = WsMessage::ActionRequest {
+            request_id: "123".to_string(),
+            command: ActionCommand::CaptureRegion {
+                x: 0,
+                y: 1200,
+                w: 800,
+                h: 600,
+            },
+        };
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"action_request","data":{"request_id":"123","command":{"type":"capture_region","x":0,"y":1200,"w":800,"h":600}}}"#
+        );
+    }
+
     #[test]
     fn test_action_result_serialization() {
         let res = WsMessage::ActionResult(ActionResult {
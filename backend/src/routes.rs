@@ -1,32 +1,78 @@
-use crate::handler::agent_handler;
-use crate::models::ws::{ActionCommand, WsMessage};
+use crate::error;
+use crate::handler::{
+    agent_handler, audit_handler, compare_handler, instruction_handler, key_handler,
+    screenshot_handler, transcript_handler, vision_handler,
+};
+use crate::models::chunks::ChunkAssembler;
+use crate::models::ws::{ActionCommand, ActionResult, WsMessage};
 use crate::state::AppState;
 use axum::{
     Router,
     extract::{
-        State,
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        DefaultBodyLimit, State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
     },
+    middleware,
     response::IntoResponse,
     routing::{get, post},
 };
 use futures::{SinkExt, StreamExt};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use uuid::Uuid;
 
+/// Largest `/agent/run` body: a screenshot, the page content and up to
+/// `MAX_UPLOAD_TOTAL_BYTES` of files as base64. axum allows 2 MB by default.
+pub const AGENT_RUN_BODY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// How often partly received chunked messages are checked for missing chunks.
+const CHUNK_EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+
 pub fn app_router(state: Arc<AppState>) -> Router {
+    let origins = if state.config.cors_origins.is_empty() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(state.config.cors_origins.clone())
+    };
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(origins)
         .allow_methods(Any)
         .allow_headers(Any);
 
     Router::new()
         .route("/health", get(health_check))
-        .route("/agent/run", post(agent_handler::run_agent))
+        .route(
+            "/agent/run",
+            post(agent_handler::run_agent).layer(DefaultBodyLimit::max(AGENT_RUN_BODY_LIMIT)),
+        )
+        .route(
+            "/agent/run/{run_id}/resume",
+            post(agent_handler::resume_agent),
+        )
+        .route("/agent/compare", post(compare_handler::compare_models))
+        .route("/key/status", get(key_handler::key_status))
+        .route(
+            "/instruction/validate",
+            post(instruction_handler::validate_custom_instruction),
+        )
+        .route(
+            "/session/{session_id}/transcript",
+            get(transcript_handler::session_transcript),
+        )
+        .route(
+            "/session/{session_id}/audit",
+            get(audit_handler::list_audit),
+        )
+        .route(
+            "/session/{session_id}/screenshots",
+            get(screenshot_handler::screenshot_stream),
+        )
+        .route("/vision", post(vision_handler::analyze_image))
         .route("/ws", get(ws_handler))
         .with_state(state)
+        .layer(middleware::from_fn(error::uniform_errors))
         .layer(cors)
 }
 
@@ -66,97 +112,43 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
             {
                 break;
             }
+            // The server is shutting down; the client reconnects once it sees the close
+            if matches!(msg, WsMessage::Reconnect { .. }) {
+                let close = CloseFrame {
+                    code: close_code::RESTART,
+                    reason: "Server restarting".into(),
+                };
+                let _ = sink.send(Message::Close(Some(close))).await;
+                break;
+            }
         }
         tracing::info!("Send task terminated for session_id={}", session_id_clone);
     });
 
-    while let Some(msg) = stream.next().await {
-        if let Ok(Message::Text(text)) = msg {
-            match serde_json::from_str::<WsMessage>(&text) {
-                Ok(WsMessage::Ping) => {
-                    let _ = tx.send(WsMessage::Pong);
-                }
-                Ok(WsMessage::SessionUpdate { url, title }) => {
-                    tracing::info!("Context update: url={}, title={:?}", url, title);
-                }
-                Ok(WsMessage::ActionRequest {
-                    request_id,
-                    command,
-                }) => {
-                    match &command {
-                        ActionCommand::NavigateTo { url } => {
-                            tracing::info!(
-                                "ActionRequest[{}]: navigate_to url={}",
-                                request_id,
-                                url
-                            );
-                        }
-                        ActionCommand::ClickElement { ref_id } => {
-                            tracing::info!(
-                                "ActionRequest[{}]: click_element ref={}",
-                                request_id,
-                                ref_id
-                            );
-                        }
-                        ActionCommand::TypeText { ref_id, text } => {
-                            tracing::info!(
-                                "ActionRequest[{}]: type_text ref={}, text={}",
-                                request_id,
-                                ref_id,
-                                text
-                            );
-                        }
-                        ActionCommand::ScrollTo { x, y } => {
-                            tracing::info!(
-                                "ActionRequest[{}]: scroll_to x={}, y={}",
-                                request_id,
-                                x,
-                                y
-                            );
-                        }
-                        ActionCommand::GetPageContent { max_length } => {
-                            tracing::info!(
-                                "ActionRequest[{}]: get_page_content max_length={:?}",
-                                request_id,
-                                max_length
-                            );
-                        }
-                        ActionCommand::GetInteractiveElements { limit } => {
-                            tracing::info!(
-                                "ActionRequest[{}]: get_interactive_elements limit={:?}",
-                                request_id,
-                                limit
-                            );
-                        }
-                    }
-                    // NOTE: ActionRequest FROM the client is unusual in this architecture.
-                    // The backend sends ActionRequest TO the client (via tools), and the client
-                    // sends ActionResult back. This handler is for when the client echoes an
-                    // ActionRequest (which shouldn't happen in normal flow).
-                    // DO NOT echo back - wait for the real ActionResult from frontend.
+    let mut chunks = ChunkAssembler::new();
+    let mut expiry = tokio::time::interval(CHUNK_EXPIRY_INTERVAL);
+    loop {
+        tokio::select! {
+            msg = stream.next() => {
+                let Some(msg) = msg else { break };
+                if let Ok(Message::Text(text)) = msg {
+                    handle_text(&state, &tx, &mut chunks, &text).await;
+                }
+            }
+            _ = expiry.tick() => {
+                for incomplete in chunks.expire(Instant::now()) {
                     tracing::warn!(
-                        "Received ActionRequest from client (unexpected): {}",
-                        request_id
+                        "Chunked message[{}] timed out with {} of {} chunks",
+                        incomplete.request_id,
+                        incomplete.received,
+                        incomplete.total
                     );
-                }
-                Ok(WsMessage::ActionResult(res)) => {
-                    tracing::info!(
-                        "ActionResult received[{}]: success={}, error={:?}, data={:?}",
-                        res.request_id,
-                        res.success,
-                        res.error,
-                        res.data
+                    let error = format!(
+                        "Timed out waiting for the rest of the result: received {} of {} chunks",
+                        incomplete.received, incomplete.total
                     );
-                    let request_id = res.request_id.clone();
-                    state.complete_pending_action(&request_id, res).await;
+                    fail_chunked_action(&state, incomplete.request_id, error).await;
                 }
-                Ok(WsMessage::Unknown) => {
-                    tracing::warn!("Unknown WebSocket message type");
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to parse WebSocket message: {}", e);
-                }
-                _ => {}
             }
         }
     }
@@ -166,3 +158,237 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     state.unregister_connection(&session_id).await;
     tracing::info!("WebSocket disconnected: session_id={}", session_id);
 }
+
+/// Handles a text frame, reassembling `chunked_data` frames into the message they carry.
+async fn handle_text(
+    state: &Arc<AppState>,
+    tx: &mpsc::UnboundedSender<WsMessage>,
+    chunks: &mut ChunkAssembler,
+    text: &str,
+) {
+    let message = match serde_json::from_str::<WsMessage>(text) {
+        Ok(WsMessage::ChunkedData { request_id, .. })
+            if !state.has_pending_action(&request_id).await =>
+        {
+            // Only results of actions still being waited for are chunked
+            tracing::warn!("Chunked message[{}] has no pending action", request_id);
+            return;
+        }
+        Ok(WsMessage::ChunkedData {
+            request_id,
+            seq,
+            total,
+            data,
+        }) => match chunks.push(&request_id, seq, total, data, Instant::now()) {
+            Ok(None) => return,
+            Ok(Some(text)) => match serde_json::from_str::<WsMessage>(&text) {
+                Ok(WsMessage::ChunkedData { .. }) => {
+                    tracing::warn!("Chunked message[{}] contains chunks", request_id);
+                    return;
+                }
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::warn!("Failed to parse chunked message[{}]: {}", request_id, e);
+                    let error = format!("Invalid chunked result: {}", e);
+                    fail_chunked_action(state, request_id, error).await;
+                    return;
+                }
+            },
+            Err(e) => {
+                tracing::warn!("Chunked message[{}]: {}", request_id, e);
+                fail_chunked_action(state, request_id, e.to_string()).await;
+                return;
+            }
+        },
+        Ok(message) => message,
+        Err(e) => {
+            tracing::warn!("Failed to parse WebSocket message: {}", e);
+            return;
+        }
+    };
+    handle_message(state, tx, message).await;
+}
+
+/// Fails the action whose result couldn't be reassembled, so its tool call
+/// returns the reason instead of waiting for its own timeout.
+async fn fail_chunked_action(state: &AppState, request_id: String, error: String) {
+    let result = ActionResult {
+        request_id: request_id.clone(),
+        success: false,
+        error: Some(error),
+        data: None,
+    };
+    state.complete_pending_action(&request_id, result).await;
+}
+
+async fn handle_message(
+    state: &Arc<AppState>,
+    tx: &mpsc::UnboundedSender<WsMessage>,
+    message: WsMessage,
+) {
+    match message {
+        WsMessage::Ping => {
+            let _ = tx.send(WsMessage::Pong);
+        }
+        WsMessage::SessionUpdate { url, title } => {
+            tracing::info!("Context update: url={}, title={:?}", url, title);
+        }
+        WsMessage::ActionRequest {
+            request_id,
+            command,
+        } => {
+            match &command {
+                ActionCommand::NavigateTo { url } => {
+                    tracing::info!("ActionRequest[{}]: navigate_to url={}", request_id, url);
+                }
+                ActionCommand::ClickElement { ref_id } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: click_element ref={}",
+                        request_id,
+                        ref_id
+                    );
+                }
+                ActionCommand::TypeText { ref_id, text } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: type_text ref={}, text={}",
+                        request_id,
+                        ref_id,
+                        text
+                    );
+                }
+                ActionCommand::ClearField { ref_id } => {
+                    tracing::info!("ActionRequest[{}]: clear_field ref={}", request_id, ref_id);
+                }
+                ActionCommand::SetChecked { ref_id, checked } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: set_checked ref={}, checked={}",
+                        request_id,
+                        ref_id,
+                        checked
+                    );
+                }
+                ActionCommand::UploadFile {
+                    ref_id,
+                    file_name,
+                    data_base64,
+                    ..
+                } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: upload_file ref={}, file_name={}, base64_len={}",
+                        request_id,
+                        ref_id,
+                        file_name,
+                        data_base64.len()
+                    );
+                }
+                ActionCommand::ScrollTo { x, y } => {
+                    tracing::info!("ActionRequest[{}]: scroll_to x={}, y={}", request_id, x, y);
+                }
+                ActionCommand::ScrollElement { ref_id, dx, dy } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: scroll_element ref={}, dx={}, dy={}",
+                        request_id,
+                        ref_id,
+                        dx,
+                        dy
+                    );
+                }
+                ActionCommand::GetPageContent { max_length } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: get_page_content max_length={:?}",
+                        request_id,
+                        max_length
+                    );
+                }
+                ActionCommand::GetInteractiveElements { limit } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: get_interactive_elements limit={:?}",
+                        request_id,
+                        limit
+                    );
+                }
+                ActionCommand::GoHistory { delta } => {
+                    tracing::info!("ActionRequest[{}]: go_history delta={}", request_id, delta);
+                }
+                ActionCommand::CountElements { selector } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: count_elements selector={}",
+                        request_id,
+                        selector
+                    );
+                }
+                ActionCommand::HandleDialog {
+                    action,
+                    prompt_text,
+                } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: handle_dialog action={:?}, prompt_text={:?}",
+                        request_id,
+                        action,
+                        prompt_text
+                    );
+                }
+                ActionCommand::GetSelection => {
+                    tracing::info!("ActionRequest[{}]: get_selection", request_id);
+                }
+                ActionCommand::GetPerformance => {
+                    tracing::info!("ActionRequest[{}]: get_performance", request_id);
+                }
+                ActionCommand::GetLiveRegions => {
+                    tracing::info!("ActionRequest[{}]: get_live_regions", request_id);
+                }
+                ActionCommand::GetConsoleLogs { levels } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: get_console_logs levels={:?}",
+                        request_id,
+                        levels
+                    );
+                }
+                ActionCommand::FullPageScreenshot => {
+                    tracing::info!("ActionRequest[{}]: full_page_screenshot", request_id);
+                }
+                ActionCommand::CaptureRegion { x, y, w, h } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: capture_region x={}, y={}, w={}, h={}",
+                        request_id,
+                        x,
+                        y,
+                        w,
+                        h
+                    );
+                }
+                ActionCommand::CaptureElement { ref_id } => {
+                    tracing::info!(
+                        "ActionRequest[{}]: capture_element ref={}",
+                        request_id,
+                        ref_id
+                    );
+                }
+            }
+            // NOTE: ActionRequest FROM the client is unusual in this architecture.
+            // The backend sends ActionRequest TO the client (via tools), and the client
+            // sends ActionResult back. This handler is for when the client echoes an
+            // ActionRequest (which shouldn't happen in normal flow).
+            // DO NOT echo back - wait for the real ActionResult from frontend.
+            tracing::warn!(
+                "Received ActionRequest from client (unexpected): {}",
+                request_id
+            );
+        }
+        WsMessage::ActionResult(res) => {
+            tracing::info!(
+                "ActionResult received[{}]: success={}, error={:?}, data={:?}",
+                res.request_id,
+                res.success,
+                res.error,
+                res.data
+            );
+            let request_id = res.request_id.clone();
+            state.complete_pending_action(&request_id, res).await;
+        }
+        WsMessage::Unknown => {
+            tracing::warn!("Unknown WebSocket message type");
+        }
+        _ => {}
+    }
+}
@@ -1,26 +1,105 @@
-use crate::llm::GeminiProvider;
+use crate::config::AppConfig;
+use crate::llm::cache::ResponseCache;
+use crate::llm::{GeminiProvider, build_http_client, gemini_client};
+use crate::models::audit::AuditLog;
+use crate::models::run::AgentRunStore;
+use crate::models::screenshots::ScreenshotFeeds;
+use crate::models::snapshot::PageSnapshotStore;
 use crate::models::ws::{ActionResult, WsMessage};
-use rig::client::ProviderClient;
+use crate::tools::pacing::ToolPacer;
+use axum::http::HeaderMap;
+use futures::future::join_all;
 use rig::providers::gemini;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{RwLock, mpsc, oneshot};
 
+/// Window over which clients are told to reconnect after a shutdown, so they
+/// don't all reconnect at once when the server comes back.
+pub const RECONNECT_SPREAD: Duration = Duration::from_secs(30);
+
+/// How long a shutdown waits for connections to send their reconnect message
+/// and close.
+pub const RECONNECT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Header carrying a user's own Gemini API key, used instead of the server's.
+pub const USER_API_KEY_HEADER: &str = "x-user-api-key";
+
+fn user_api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(USER_API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
 pub struct AppState {
+    pub config: AppConfig,
+    /// Gemini client over the shared, pooled HTTP client
+    pub gemini: gemini::Client,
+    /// The shared HTTP client, for calls rig doesn't cover
+    pub http: reqwest::Client,
     pub llm: GeminiProvider,
     pub active_connections: Arc<RwLock<HashMap<String, mpsc::UnboundedSender<WsMessage>>>>,
     pub pending_actions: Arc<RwLock<HashMap<String, oneshot::Sender<ActionResult>>>>,
+    pub page_snapshots: Arc<RwLock<PageSnapshotStore>>,
+    pub agent_runs: Arc<RwLock<AgentRunStore>>,
+    /// Metadata of what each agent request sent to the model
+    pub audit_log: Arc<RwLock<AuditLog>>,
+    /// Screenshots captured by the agent, for clients watching a session
+    pub screenshot_feeds: Arc<RwLock<ScreenshotFeeds>>,
+    pub tool_pacer: ToolPacer,
+    pub response_cache: ResponseCache,
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new(config: AppConfig) -> Self {
+        let http_client = build_http_client(&config.http);
+        let gemini = gemini_client(
+            &config.gemini_api_key,
+            config.gemini_base_url.as_deref(),
+            http_client.clone(),
+        );
+
         Self {
-            llm: GeminiProvider::new(gemini::Client::from_env()),
+            llm: GeminiProvider::new(gemini.clone(), config.default_image_type.clone()),
+            gemini,
+            http: http_client,
+            tool_pacer: ToolPacer::new(config.tool_min_delay),
+            response_cache: ResponseCache::new(config.response_cache_ttl),
+            config,
             active_connections: Arc::new(RwLock::new(HashMap::new())),
             pending_actions: Arc::new(RwLock::new(HashMap::new())),
+            page_snapshots: Arc::new(RwLock::new(PageSnapshotStore::new())),
+            agent_runs: Arc::new(RwLock::new(AgentRunStore::new())),
+            audit_log: Arc::new(RwLock::new(AuditLog::new())),
+            screenshot_feeds: Arc::new(RwLock::new(ScreenshotFeeds::new())),
         }
     }
 
+    /// Gemini client and provider for a request: the server's, or ones using
+    /// the key in the `X-User-Api-Key` header, over the same HTTP client.
+    /// The user's key is only held for the request and never logged.
+    pub fn gemini_for_request(&self, headers: &HeaderMap) -> (gemini::Client, GeminiProvider) {
+        let Some(api_key) = user_api_key(headers) else {
+            return (self.gemini.clone(), self.llm.clone());
+        };
+
+        let gemini = gemini_client(
+            api_key,
+            self.config.gemini_base_url.as_deref(),
+            self.http.clone(),
+        );
+        let llm = GeminiProvider::new(gemini.clone(), self.config.default_image_type.clone());
+        (gemini, llm)
+    }
+
+    /// The API key a request should use: the user's own, or the server's.
+    pub fn api_key_for_request<'a>(&'a self, headers: &'a HeaderMap) -> &'a str {
+        user_api_key(headers).unwrap_or(&self.config.gemini_api_key)
+    }
+
     pub async fn register_connection(
         &self,
         session_id: String,
@@ -33,6 +112,9 @@ impl AppState {
     pub async fn unregister_connection(&self, session_id: &str) {
         let mut connections = self.active_connections.write().await;
         connections.remove(session_id);
+        drop(connections);
+
+        self.tool_pacer.remove(session_id).await;
     }
 
     pub async fn get_connection(
@@ -43,6 +125,39 @@ impl AppState {
         connections.get(session_id).cloned()
     }
 
+    /// Tells every connected client to reconnect, spreading their delays
+    /// evenly across `spread`, then waits up to `RECONNECT_DRAIN_TIMEOUT` for
+    /// their connections to send it and close.
+    pub async fn request_reconnect(&self, spread: Duration) {
+        let senders: Vec<_> = self
+            .active_connections
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect();
+        let step = spread.as_millis() as u64 / senders.len().max(1) as u64;
+        for (i, sender) in senders.iter().enumerate() {
+            let _ = sender.send(WsMessage::Reconnect {
+                after_ms: step * i as u64,
+            });
+        }
+        tracing::info!("Asked {} clients to reconnect", senders.len());
+
+        // A connection's send task drops its receiver once it has closed the socket
+        let drained = join_all(senders.iter().map(|sender| sender.closed()));
+        if tokio::time::timeout(RECONNECT_DRAIN_TIMEOUT, drained)
+            .await
+            .is_err()
+        {
+            let open = senders.iter().filter(|sender| !sender.is_closed()).count();
+            tracing::warn!(
+                "{} connections still open after the reconnect request",
+                open
+            );
+        }
+    }
+
     pub async fn register_pending_action(
         &self,
         request_id: String,
@@ -52,6 +167,11 @@ impl AppState {
         pending.insert(request_id, sender);
     }
 
+    pub async fn has_pending_action(&self, request_id: &str) -> bool {
+        let pending = self.pending_actions.read().await;
+        pending.contains_key(request_id)
+    }
+
     pub async fn complete_pending_action(&self, request_id: &str, result: ActionResult) -> bool {
         let mut pending = self.pending_actions.write().await;
         if let Some(sender) = pending.remove(request_id) {
@@ -61,3 +181,29 @@ impl AppState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reconnect_waits_for_connections_to_close() {
+        let state = AppState::new(AppConfig::for_tests());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        state.register_connection("s1".to_string(), tx).await;
+
+        // Like the connection's send task: close after sending the reconnect
+        let send_task = tokio::spawn(async move { rx.recv().await });
+
+        tokio::time::timeout(
+            RECONNECT_DRAIN_TIMEOUT / 2,
+            state.request_reconnect(RECONNECT_SPREAD),
+        )
+        .await
+        .expect("request_reconnect should return once the connection closed");
+        assert!(matches!(
+            send_task.await.unwrap(),
+            Some(WsMessage::Reconnect { after_ms: 0 })
+        ));
+    }
+}
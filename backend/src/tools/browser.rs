@@ -5,6 +5,8 @@ use serde_json::json;
 use std::error::Error;
 use std::fmt;
 
+use crate::models::ws::{ConsoleLevel, DialogAction};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BrowserToolError(String);
 
@@ -137,6 +139,136 @@ impl Tool for TypeTool {
     }
 }
 
+/// Tool to empty an input field
+#[derive(Deserialize, Serialize)]
+pub struct ClearFieldTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct ClearFieldArgs {
+    #[serde(rename = "ref")]
+    pub ref_id: i32,
+}
+
+impl Tool for ClearFieldTool {
+    const NAME: &'static str = "clear_field";
+    type Error = BrowserToolError;
+    type Args = ClearFieldArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Clear the value of an input field using its reference ID. Use this before typing when replacing an existing value.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "ref": {
+                        "type": "integer",
+                        "description": "The reference ID of the input element to clear"
+                    }
+                },
+                "required": ["ref"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!("Clearing element with ref ID: {}", args.ref_id))
+    }
+}
+
+/// Tool to set a checkbox or radio button to a given state
+#[derive(Deserialize, Serialize)]
+pub struct SetCheckedTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct SetCheckedArgs {
+    #[serde(rename = "ref")]
+    pub ref_id: i32,
+    pub checked: bool,
+}
+
+impl Tool for SetCheckedTool {
+    const NAME: &'static str = "set_checked";
+    type Error = BrowserToolError;
+    type Args = SetCheckedArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Set a checkbox or radio button to checked or unchecked using its reference ID. Does nothing if it is already in that state, so prefer this over click_element for checkboxes.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "ref": {
+                        "type": "integer",
+                        "description": "The reference ID of the checkbox or radio button"
+                    },
+                    "checked": {
+                        "type": "boolean",
+                        "description": "The desired state"
+                    }
+                },
+                "required": ["ref", "checked"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!(
+            "Setting element with ref ID {} to checked={}",
+            args.ref_id, args.checked
+        ))
+    }
+}
+
+/// Tool to attach a file the user provided to a file input
+#[derive(Deserialize, Serialize)]
+pub struct UploadFileTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct UploadFileArgs {
+    #[serde(rename = "ref")]
+    pub ref_id: i32,
+    pub file_name: String,
+}
+
+impl Tool for UploadFileTool {
+    const NAME: &'static str = "upload_file";
+    type Error = BrowserToolError;
+    type Args = UploadFileArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Attach one of the files the user sent with their message to a file input (<input type=\"file\">) using its reference ID. Only files the user provided can be uploaded.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "ref": {
+                        "type": "integer",
+                        "description": "The reference ID of the file input"
+                    },
+                    "file_name": {
+                        "type": "string",
+                        "description": "Name of the attached file to upload"
+                    }
+                },
+                "required": ["ref", "file_name"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!(
+            "Uploading {} to element with ref ID {}",
+            args.file_name, args.ref_id
+        ))
+    }
+}
+
 /// Tool to scroll the page
 #[derive(Deserialize, Serialize)]
 pub struct ScrollTool;
@@ -179,6 +311,59 @@ impl Tool for ScrollTool {
     }
 }
 
+/// Tool to scroll an inner scrollable container
+#[derive(Deserialize, Serialize)]
+pub struct ScrollElementTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct ScrollElementArgs {
+    #[serde(rename = "ref")]
+    pub ref_id: i32,
+    #[serde(default)]
+    pub dx: i32,
+    #[serde(default)]
+    pub dy: i32,
+}
+
+impl Tool for ScrollElementTool {
+    const NAME: &'static str = "scroll_element";
+    type Error = BrowserToolError;
+    type Args = ScrollElementArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Scroll inside a scrollable container (chat pane, results panel, data grid) by an offset in pixels. Use when scroll_to doesn't move the content you need. The ref may be the container or any element inside it.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "ref": {
+                        "type": "integer",
+                        "description": "The reference ID of the container, or of an element inside it"
+                    },
+                    "dx": {
+                        "type": "integer",
+                        "description": "Pixels to scroll right (negative scrolls left)"
+                    },
+                    "dy": {
+                        "type": "integer",
+                        "description": "Pixels to scroll down (negative scrolls up)"
+                    }
+                },
+                "required": ["ref"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!(
+            "Scrolling element with ref ID {} by dx: {}, dy: {}",
+            args.ref_id, args.dx, args.dy
+        ))
+    }
+}
+
 /// Tool to get page content
 #[derive(Deserialize, Serialize)]
 pub struct GetPageContentTool;
@@ -253,6 +438,393 @@ impl Tool for GetInteractiveElementsTool {
     }
 }
 
+/// Tool to move through the tab's history
+#[derive(Deserialize, Serialize)]
+pub struct GoHistoryTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct GoHistoryArgs {
+    pub delta: i32,
+}
+
+impl Tool for GoHistoryTool {
+    const NAME: &'static str = "go_history";
+    type Error = BrowserToolError;
+    type Args = GoHistoryArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Move through the tab's history by a number of entries. Negative goes back, positive goes forward. Returns the resulting URL.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "delta": {
+                        "type": "integer",
+                        "description": "Number of history entries to move (e.g., -1 to go back one page, 2 to go forward two pages)"
+                    }
+                },
+                "required": ["delta"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!("Moving {} entries in history", args.delta))
+    }
+}
+
+/// Tool to count the elements matching a CSS selector
+#[derive(Deserialize, Serialize)]
+pub struct CountElementsTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct CountElementsArgs {
+    pub selector: String,
+}
+
+impl Tool for CountElementsTool {
+    const NAME: &'static str = "count_elements";
+    type Error = BrowserToolError;
+    type Args = CountElementsArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Count the elements on the page matching a CSS selector. Use this to cheaply verify page state after an action (e.g., that all rows loaded).".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "selector": {
+                        "type": "string",
+                        "description": "The CSS selector to match (e.g., \"table tbody tr\")"
+                    }
+                },
+                "required": ["selector"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!("Counting elements matching '{}'", args.selector))
+    }
+}
+
+/// Tool to accept or dismiss a native dialog (alert, confirm, prompt)
+#[derive(Deserialize, Serialize)]
+pub struct HandleDialogTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct HandleDialogArgs {
+    pub action: DialogAction,
+    pub prompt_text: Option<String>,
+}
+
+impl Tool for HandleDialogTool {
+    const NAME: &'static str = "handle_dialog";
+    type Error = BrowserToolError;
+    type Args = HandleDialogArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Accept or dismiss a native browser dialog (alert, confirm, prompt) that is blocking the page.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["accept", "dismiss"],
+                        "description": "Whether to accept (OK) or dismiss (Cancel) the dialog"
+                    },
+                    "prompt_text": {
+                        "type": "string",
+                        "description": "Text to enter when accepting a prompt() dialog"
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!("Handling dialog: {:?}", args.action))
+    }
+}
+
+/// Tool to get the text the user has selected on the page
+#[derive(Deserialize, Serialize)]
+pub struct GetSelectionTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct GetSelectionArgs {}
+
+impl Tool for GetSelectionTool {
+    const NAME: &'static str = "get_selection";
+    type Error = BrowserToolError;
+    type Args = GetSelectionArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Get the text the user has currently selected on the page, with the text of its surrounding block. Use this when the user refers to \"this\" or \"the selected text\".".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        }
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok("Getting selected text".to_string())
+    }
+}
+
+/// Tool to get page load timing for performance analysis
+#[derive(Deserialize, Serialize)]
+pub struct GetPerformanceTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct GetPerformanceArgs {}
+
+impl Tool for GetPerformanceTool {
+    const NAME: &'static str = "get_performance";
+    type Error = BrowserToolError;
+    type Args = GetPerformanceArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Get the page's load performance: time to first byte, DOMContentLoaded, load, Largest Contentful Paint, and the slowest resources. Use this when the user asks why a page is slow.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        }
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok("Getting performance metrics".to_string())
+    }
+}
+
+/// Tool to read what ARIA live regions currently show and have announced
+#[derive(Deserialize, Serialize)]
+pub struct GetLiveRegionsTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct GetLiveRegionsArgs {}
+
+impl Tool for GetLiveRegionsTool {
+    const NAME: &'static str = "get_live_regions";
+    type Error = BrowserToolError;
+    type Args = GetLiveRegionsArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Get the current text of the page's ARIA live regions (aria-live, role=status/alert/log) and the recent announcements they made, including toasts that have already disappeared. Use this to verify what a screen reader announced after an action.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        }
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok("Getting live regions".to_string())
+    }
+}
+
+/// Tool to read the page's recent console errors, warnings and uncaught errors
+#[derive(Deserialize, Serialize)]
+pub struct GetConsoleLogsTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct GetConsoleLogsArgs {
+    #[serde(default)]
+    pub levels: Vec<ConsoleLevel>,
+}
+
+impl Tool for GetConsoleLogsTool {
+    const NAME: &'static str = "get_console_logs";
+    type Error = BrowserToolError;
+    type Args = GetConsoleLogsArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Get the page's JavaScript console errors and warnings, including uncaught errors and unhandled promise rejections, logged since the agent started working on the page. Use when asked what errors the page is throwing or why something on the page is broken.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "levels": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": ["error", "warn"]
+                        },
+                        "description": "Only return messages of these levels, e.g. [\"error\"]. Returns both when omitted."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!("Getting console logs: {:?}", args.levels))
+    }
+}
+
+/// Tool to capture the whole page, not just the viewport, and answer a question about it
+#[derive(Deserialize, Serialize)]
+pub struct FullPageScreenshotTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct FullPageScreenshotArgs {
+    pub question: String,
+}
+
+impl Tool for FullPageScreenshotTool {
+    const NAME: &'static str = "full_page_screenshot";
+    type Error = BrowserToolError;
+    type Args = FullPageScreenshotArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Capture a screenshot of the entire page (not just the visible viewport) and see it as an image with the next message, to answer a question about it. Use this for visual analysis of long documents.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "question": {
+                        "type": "string",
+                        "description": "What to look for or describe in the full-page screenshot"
+                    }
+                },
+                "required": ["question"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!("Capturing full page to answer: {}", args.question))
+    }
+}
+
+/// Tool to capture a screenshot of a rectangular region of the page
+#[derive(Deserialize, Serialize)]
+pub struct CaptureRegionTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct CaptureRegionArgs {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+    #[serde(default)]
+    pub question: Option<String>,
+}
+
+impl Tool for CaptureRegionTool {
+    const NAME: &'static str = "capture_region";
+    type Error = BrowserToolError;
+    type Args = CaptureRegionArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Capture a screenshot of a rectangular region of the page, in page coordinates, and look at it. Use this to look at a specific area of a tall page instead of capturing the whole page.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "x": {
+                        "type": "integer",
+                        "description": "Left edge of the region in CSS pixels"
+                    },
+                    "y": {
+                        "type": "integer",
+                        "description": "Top edge of the region in CSS pixels, relative to the top of the page"
+                    },
+                    "w": {
+                        "type": "integer",
+                        "description": "Width of the region in CSS pixels"
+                    },
+                    "h": {
+                        "type": "integer",
+                        "description": "Height of the region in CSS pixels"
+                    },
+                    "question": {
+                        "type": "string",
+                        "description": "What to look for in the region (optional; describes it by default)"
+                    }
+                },
+                "required": ["x", "y", "w", "h"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!(
+            "Capturing region {}x{} at ({}, {})",
+            args.w, args.h, args.x, args.y
+        ))
+    }
+}
+
+/// Tool to capture a screenshot of a single element
+#[derive(Deserialize, Serialize)]
+pub struct CaptureElementTool;
+
+#[derive(Deserialize, Serialize)]
+pub struct CaptureElementArgs {
+    #[serde(rename = "ref")]
+    pub ref_id: i32,
+    #[serde(default)]
+    pub question: Option<String>,
+}
+
+impl Tool for CaptureElementTool {
+    const NAME: &'static str = "capture_element";
+    type Error = BrowserToolError;
+    type Args = CaptureElementArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Capture a screenshot of just one element (a chart, image, captcha, card) and look at it. Cheaper and more focused than a full screenshot when only that element matters.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "ref": {
+                        "type": "integer",
+                        "description": "The reference ID of the element to capture"
+                    },
+                    "question": {
+                        "type": "string",
+                        "description": "What to look for in the element (optional; describes it by default)"
+                    }
+                },
+                "required": ["ref"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(format!("Capturing element with ref ID {}", args.ref_id))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,6 +851,21 @@ mod tests {
         assert_eq!(args.text, "hello");
     }
 
+    #[tokio::test]
+    async fn test_clear_field_tool_serialization() {
+        let args_json = json!({ "ref": 7 });
+        let args: ClearFieldArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.ref_id, 7);
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_serialization() {
+        let args_json = json!({ "ref": 6, "file_name": "cv.pdf" });
+        let args: UploadFileArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.ref_id, 6);
+        assert_eq!(args.file_name, "cv.pdf");
+    }
+
     #[tokio::test]
     async fn test_scroll_tool_serialization() {
         let args_json = json!({ "x": 100, "y": 200 });
@@ -287,6 +874,15 @@ mod tests {
         assert_eq!(args.y, 200);
     }
 
+    #[tokio::test]
+    async fn test_scroll_element_serialization() {
+        let args_json = json!({ "ref": 9, "dy": 400 });
+        let args: ScrollElementArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.ref_id, 9);
+        assert_eq!(args.dx, 0);
+        assert_eq!(args.dy, 400);
+    }
+
     #[tokio::test]
     async fn test_get_page_content_serialization() {
         let args_json = json!({ "max_length": 1000 });
@@ -300,4 +896,69 @@ mod tests {
         let args: GetInteractiveElementsArgs = serde_json::from_value(args_json).unwrap();
         assert_eq!(args.limit, Some(50));
     }
+
+    #[tokio::test]
+    async fn test_go_history_serialization() {
+        let args_json = json!({ "delta": -2 });
+        let args: GoHistoryArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.delta, -2);
+    }
+
+    #[tokio::test]
+    async fn test_count_elements_serialization() {
+        let args_json = json!({ "selector": "table tr" });
+        let args: CountElementsArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.selector, "table tr");
+    }
+
+    #[tokio::test]
+    async fn test_handle_dialog_serialization() {
+        let args_json = json!({ "action": "accept", "prompt_text": "yes" });
+        let args: HandleDialogArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.action, DialogAction::Accept);
+        assert_eq!(args.prompt_text, Some("yes".to_string()));
+
+        let args_json = json!({ "action": "dismiss" });
+        let args: HandleDialogArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.action, DialogAction::Dismiss);
+        assert_eq!(args.prompt_text, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_console_logs_serialization() {
+        let args_json = json!({ "levels": ["error", "warn"] });
+        let args: GetConsoleLogsArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.levels, vec![ConsoleLevel::Error, ConsoleLevel::Warn]);
+
+        let args: GetConsoleLogsArgs = serde_json::from_value(json!({})).unwrap();
+        assert!(args.levels.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_capture_region_serialization() {
+        let args_json = json!({ "x": 0, "y": 1200, "w": 800, "h": 600 });
+        let args: CaptureRegionArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!((args.x, args.y, args.w, args.h), (0, 1200, 800, 600));
+    }
+
+    #[tokio::test]
+    async fn test_get_selection_serialization() {
+        let args_json = json!({});
+        let _args: GetSelectionArgs = serde_json::from_value(args_json).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_full_page_screenshot_serialization() {
+        let args_json = json!({ "question": "What does the chart at the bottom show?" });
+        let args: FullPageScreenshotArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.question, "What does the chart at the bottom show?");
+    }
+
+    #[tokio::test]
+    async fn test_set_checked_serialization() {
+        let args_json = json!({ "ref": 4, "checked": false });
+        let args: SetCheckedArgs = serde_json::from_value(args_json).unwrap();
+        assert_eq!(args.ref_id, 4);
+        assert!(!args.checked);
+    }
 }
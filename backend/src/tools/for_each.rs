@@ -0,0 +1,309 @@
+//! Repeats one page action for every item of a list, so bulk tasks ("add
+//! all these to the cart") take a single model round-trip instead of one per
+//! item.
+//!
+//! The action is a tool name and a JSON arguments template in which
+//! `{{item}}` is replaced by each item. The action runs through the regular
+//! browser tools, so their validation applies to every item. The call fails
+//! only when the action failed for every item.
+
+use rig::completion::ToolDefinition;
+use rig::tool::{Tool, ToolDyn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::tools::websocket::ToolError;
+
+/// Most items a single `for_each` call may run the action for.
+pub const MAX_FOR_EACH_ITEMS: usize = 25;
+
+/// Placeholder in the arguments template replaced by the current item.
+const ITEM_PLACEHOLDER: &str = "{{item}}";
+
+#[derive(Deserialize, Serialize)]
+pub struct ForEachArgs {
+    pub items: Vec<String>,
+    pub tool: String,
+    /// JSON object of the tool's arguments, containing `{{item}}`
+    pub args: String,
+}
+
+/// Runs one of `tools` once per item.
+pub struct ForEachTool {
+    tools: Vec<Box<dyn ToolDyn>>,
+}
+
+impl ForEachTool {
+    /// `tools` are the actions that may be repeated.
+    pub fn new(tools: Vec<Box<dyn ToolDyn>>) -> Self {
+        Self { tools }
+    }
+}
+
+impl Tool for ForEachTool {
+    const NAME: &'static str = "for_each";
+    type Error = ToolError;
+    type Args = ForEachArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let names: Vec<String> = self.tools.iter().map(|tool| tool.name()).collect();
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Run the same action for every item of a list in one call, e.g. click each \"Add to cart\" button or type each value. Use instead of calling the action repeatedly. Reports which items succeeded. At most {} items.",
+                MAX_FOR_EACH_ITEMS
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "items": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Values to run the action for, e.g. element Ref IDs [\"12\", \"15\"] or texts to type"
+                    },
+                    "tool": {
+                        "type": "string",
+                        "enum": names,
+                        "description": "The action to run for each item"
+                    },
+                    "args": {
+                        "type": "string",
+                        "description": "The action's arguments as a JSON object, with {{item}} where each item goes, e.g. {\"ref\": \"{{item}}\"} or {\"ref\": 4, \"text\": \"{{item}}\"}"
+                    }
+                },
+                "required": ["items", "tool", "args"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.items.is_empty() {
+            return Err(ToolError("items must not be empty".into()));
+        }
+        if args.items.len() > MAX_FOR_EACH_ITEMS {
+            return Err(ToolError(format!(
+                "At most {} items can be processed in one call, got {}",
+                MAX_FOR_EACH_ITEMS,
+                args.items.len()
+            )));
+        }
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name() == args.tool)
+            .ok_or_else(|| ToolError(format!("{} can't be used with for_each", args.tool)))?;
+        let template: Value = serde_json::from_str(&args.args)
+            .map_err(|e| ToolError(format!("args is not valid JSON: {}", e)))?;
+        let schema = tool.definition(String::new()).await.parameters;
+
+        let mut succeeded = 0;
+        let mut lines = Vec::new();
+        for item in &args.items {
+            let mut call_args = fill_template(&template, item);
+            coerce_integers(&mut call_args, &schema);
+            let call_args = call_args.to_string();
+            match tool.call(call_args).await {
+                Ok(output) => {
+                    succeeded += 1;
+                    // Tool output is a JSON string
+                    let output = serde_json::from_str::<String>(&output).unwrap_or(output);
+                    lines.push(format!("- {}: ok: {}", item, output));
+                }
+                Err(e) => lines.push(format!("- {}: failed: {}", item, e)),
+            }
+        }
+
+        let summary = format!(
+            "Ran {} for {} items: {} succeeded, {} failed.\n{}",
+            args.tool,
+            args.items.len(),
+            succeeded,
+            args.items.len() - succeeded,
+            lines.join("\n")
+        );
+        // A failure like any other tool's, so `on_tool_error` applies
+        if succeeded == 0 {
+            return Err(ToolError(summary));
+        }
+        Ok(summary)
+    }
+}
+
+/// Replaces `{{item}}` in the template's strings.
+fn fill_template(template: &Value, item: &str) -> Value {
+    match template {
+        Value::String(text) => Value::from(text.replace(ITEM_PLACEHOLDER, item)),
+        Value::Array(values) => values.iter().map(|v| fill_template(v, item)).collect(),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), fill_template(value, item)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Turns string arguments into numbers where the tool's `schema` declares an
+/// integer, so an item can fill `ref`. Text arguments stay strings even when
+/// the item is numeric.
+fn coerce_integers(args: &mut Value, schema: &Value) {
+    let Some(fields) = args.as_object_mut() else {
+        return;
+    };
+    for (key, value) in fields.iter_mut() {
+        let is_integer = schema["properties"][key.as_str()]["type"] == "integer";
+        if let (true, Some(number)) = (
+            is_integer,
+            value.as_str().and_then(|text| text.parse::<i64>().ok()),
+        ) {
+            *value = Value::from(number);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::tool::ToolError as RigToolError;
+    use rig::wasm_compat::WasmBoxedFuture;
+    use std::sync::{Arc, Mutex};
+
+    /// Records its arguments, failing for ref 13.
+    struct RecordingTool(Arc<Mutex<Vec<Value>>>);
+
+    impl ToolDyn for RecordingTool {
+        fn name(&self) -> String {
+            "click_element".to_string()
+        }
+
+        fn definition<'a>(&'a self, _prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+            Box::pin(async {
+                ToolDefinition {
+                    name: "click_element".to_string(),
+                    description: String::new(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "ref": { "type": "integer" },
+                            "text": { "type": "string" }
+                        }
+                    }),
+                }
+            })
+        }
+
+        fn call<'a>(&'a self, args: String) -> WasmBoxedFuture<'a, Result<String, RigToolError>> {
+            let args: Value = serde_json::from_str(&args).unwrap();
+            self.0.lock().unwrap().push(args.clone());
+            Box::pin(async move {
+                if args["ref"] == 13 {
+                    Err(RigToolError::ToolCallError(Box::new(ToolError(
+                        "Element not found".into(),
+                    ))))
+                } else {
+                    Ok(format!("\"Clicked element {}\"", args["ref"]))
+                }
+            })
+        }
+    }
+
+    fn for_each(calls: &Arc<Mutex<Vec<Value>>>) -> ForEachTool {
+        ForEachTool::new(vec![Box::new(RecordingTool(calls.clone()))])
+    }
+
+    #[tokio::test]
+    async fn test_runs_templated_action_for_each_item() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let args = ForEachArgs {
+            items: vec!["12".into(), "13".into(), "14".into()],
+            tool: "click_element".into(),
+            args: r#"{"ref": "{{item}}", "note": "item {{item}}"}"#.into(),
+        };
+        let summary = Tool::call(&for_each(&calls), args).await.unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                json!({ "ref": 12, "note": "item 12" }),
+                json!({ "ref": 13, "note": "item 13" }),
+                json!({ "ref": 14, "note": "item 14" }),
+            ]
+        );
+        assert!(summary.starts_with("Ran click_element for 3 items: 2 succeeded, 1 failed."));
+        assert!(summary.contains("- 12: ok: Clicked element 12"));
+        assert!(summary.contains("- 13: failed:"));
+        assert!(summary.contains("Element not found"));
+        assert!(summary.contains("- 14: ok: Clicked element 14"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unknown_tool_and_too_many_items() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let tool = for_each(&calls);
+
+        let args = ForEachArgs {
+            items: vec!["https://example.com".into()],
+            tool: "navigate_to".into(),
+            args: r#"{"url": "{{item}}"}"#.into(),
+        };
+        let err = Tool::call(&tool, args).await.unwrap_err();
+        assert!(err.0.contains("navigate_to can't be used"));
+
+        let args = ForEachArgs {
+            items: vec!["1".into(); MAX_FOR_EACH_ITEMS + 1],
+            tool: "click_element".into(),
+            args: r#"{"ref": "{{item}}"}"#.into(),
+        };
+        let err = Tool::call(&tool, args).await.unwrap_err();
+        assert!(err.0.contains("At most"));
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fails_when_every_item_fails() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let args = ForEachArgs {
+            items: vec!["13".into(), "13".into()],
+            tool: "click_element".into(),
+            args: r#"{"ref": "{{item}}"}"#.into(),
+        };
+        let err = Tool::call(&for_each(&calls), args).await.unwrap_err();
+        assert!(
+            err.0
+                .starts_with("Ran click_element for 2 items: 0 succeeded, 2 failed.")
+        );
+        assert!(err.0.contains("Element not found"));
+    }
+
+    #[tokio::test]
+    async fn test_numeric_text_stays_text() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let args = ForEachArgs {
+            items: vec!["12345".into(), "2024".into()],
+            tool: "click_element".into(),
+            args: r#"{"ref": 4, "text": "{{item}}"}"#.into(),
+        };
+        let summary = Tool::call(&for_each(&calls), args).await.unwrap();
+
+        // Only `ref` is an integer in the tool's schema
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                json!({ "ref": 4, "text": "12345" }),
+                json!({ "ref": 4, "text": "2024" }),
+            ]
+        );
+        assert!(summary.starts_with("Ran click_element for 2 items: 2 succeeded, 0 failed."));
+    }
+
+    #[test]
+    fn test_fill_template_keeps_text_items_as_strings() {
+        let template = json!({ "ref": 4, "text": "{{item}}", "tags": ["{{item}}"] });
+        assert_eq!(
+            fill_template(&template, "kopi susu"),
+            json!({ "ref": 4, "text": "kopi susu", "tags": ["kopi susu"] })
+        );
+    }
+}
@@ -1,2 +1,5 @@
 pub mod browser;
+pub mod for_each;
+pub mod pacing;
+pub mod registry;
 pub mod websocket;
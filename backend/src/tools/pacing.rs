@@ -0,0 +1,137 @@
+//! Per-session pacing of browser actions.
+//!
+//! Consecutive tool calls on a session are spaced by at least the configured
+//! minimum delay. The delay doubles when an action fails with a rate-limit-like
+//! error and halves back towards the minimum on success.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{Instant, sleep_until};
+
+/// Delay used after the first rate-limited action when the minimum is zero.
+const RATE_LIMIT_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound for the adaptive delay.
+const MAX_TOOL_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct SessionPacing {
+    next_call: Option<Instant>,
+    delay: Duration,
+}
+
+pub struct ToolPacer {
+    min_delay: Duration,
+    sessions: Mutex<HashMap<String, SessionPacing>>,
+}
+
+impl ToolPacer {
+    pub fn new(min_delay: Duration) -> Self {
+        Self {
+            min_delay,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits until the session may run its next action, reserving the slot
+    /// so concurrent calls on the same session are spaced out too.
+    pub async fn wait_turn(&self, session_id: &str) {
+        let start = {
+            let mut sessions = self.sessions.lock().await;
+            let pacing = sessions
+                .entry(session_id.to_string())
+                .or_insert_with(|| SessionPacing {
+                    next_call: None,
+                    delay: self.min_delay,
+                });
+
+            let now = Instant::now();
+            let start = pacing.next_call.map_or(now, |next| next.max(now));
+            pacing.next_call = Some(start + pacing.delay);
+            start
+        };
+
+        sleep_until(start).await;
+    }
+
+    /// Adapts the session's delay to the outcome of its last action.
+    pub async fn record_outcome(&self, session_id: &str, error: Option<&str>) {
+        let mut sessions = self.sessions.lock().await;
+        let Some(pacing) = sessions.get_mut(session_id) else {
+            return;
+        };
+
+        if error.is_some_and(is_rate_limited) {
+            pacing.delay = (pacing.delay * 2)
+                .max(RATE_LIMIT_BASE_DELAY)
+                .min(MAX_TOOL_DELAY);
+            tracing::warn!(
+                "Rate limited on session {}, spacing actions by {:?}",
+                session_id,
+                pacing.delay
+            );
+        } else if error.is_none() {
+            pacing.delay = (pacing.delay / 2).max(self.min_delay);
+        }
+    }
+
+    pub async fn remove(&self, session_id: &str) {
+        self.sessions.lock().await.remove(session_id);
+    }
+
+    #[cfg(test)]
+    async fn delay(&self, session_id: &str) -> Option<Duration> {
+        self.sessions.lock().await.get(session_id).map(|p| p.delay)
+    }
+}
+
+fn is_rate_limited(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["429", "rate limit", "rate-limit", "too many requests"]
+        .iter()
+        .any(|pattern| error.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_consecutive_calls_are_spaced() {
+        let min_delay = Duration::from_millis(50);
+        let pacer = ToolPacer::new(min_delay);
+
+        let start = Instant::now();
+        pacer.wait_turn("s1").await;
+        pacer.wait_turn("s1").await;
+        pacer.wait_turn("s1").await;
+        assert!(start.elapsed() >= min_delay * 2);
+
+        // Other sessions are paced independently
+        let start = Instant::now();
+        pacer.wait_turn("s2").await;
+        assert!(start.elapsed() < min_delay);
+    }
+
+    #[tokio::test]
+    async fn test_delay_adapts_to_rate_limits() {
+        let pacer = ToolPacer::new(Duration::ZERO);
+        pacer.wait_turn("s1").await;
+
+        pacer
+            .record_outcome("s1", Some("HTTP 429 Too Many Requests"))
+            .await;
+        assert_eq!(pacer.delay("s1").await, Some(RATE_LIMIT_BASE_DELAY));
+
+        pacer
+            .record_outcome("s1", Some("You are being rate limited"))
+            .await;
+        assert_eq!(pacer.delay("s1").await, Some(RATE_LIMIT_BASE_DELAY * 2));
+
+        // Ordinary failures leave the delay alone, successes bring it back down
+        pacer.record_outcome("s1", Some("Element not found")).await;
+        assert_eq!(pacer.delay("s1").await, Some(RATE_LIMIT_BASE_DELAY * 2));
+        pacer.record_outcome("s1", None).await;
+        assert_eq!(pacer.delay("s1").await, Some(RATE_LIMIT_BASE_DELAY));
+    }
+}
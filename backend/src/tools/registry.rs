@@ -0,0 +1,292 @@
+//! The set of browser tools given to the agent, in one place so tools can be
+//! enabled or disabled centrally.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rig::tool::ToolDyn;
+
+use crate::dtos::agent::AttachedFileDto;
+use crate::llm::image_relay::PendingImages;
+use crate::state::AppState;
+use crate::tools::for_each::ForEachTool;
+use crate::tools::websocket::{
+    WsCaptureElementTool, WsCaptureRegionTool, WsClearFieldTool, WsClickTool, WsCountElementsTool,
+    WsFullPageScreenshotTool, WsGetConsoleLogsTool, WsGetInteractiveElementsTool,
+    WsGetLiveRegionsTool, WsGetPageContentTool, WsGetPerformanceTool, WsGetSelectionTool,
+    WsGoHistoryTool, WsHandleDialogTool, WsNavigateTool, WsScrollElementTool, WsScrollTool,
+    WsSetCheckedTool, WsTypeTool, WsUploadFileTool,
+};
+
+/// Tools that only observe the page, the only ones given to the agent in safe
+/// mode. Screenshots may scroll to capture but restore the scroll position.
+pub const READ_ONLY_TOOLS: &[&str] = &[
+    "get_page_content",
+    "get_interactive_elements",
+    "count_elements",
+    "get_selection",
+    "get_performance",
+    "get_live_regions",
+    "get_console_logs",
+    "full_page_screenshot",
+    "capture_region",
+    "capture_element",
+];
+
+/// Keeps only the `READ_ONLY_TOOLS` of `tools`.
+pub fn read_only(tools: Vec<Box<dyn ToolDyn>>) -> Vec<Box<dyn ToolDyn>> {
+    tools
+        .into_iter()
+        .filter(|tool| READ_ONLY_TOOLS.contains(&tool.name().as_str()))
+        .collect()
+}
+
+/// Builds the browser tools for a session, leaving out those disabled in the
+/// config (`DISABLED_TOOLS`).
+///
+/// Tools are always returned in the same order so the tool list sent to the
+/// model is identical across requests.
+///
+/// `element_roles` are the roles of elements the client already scanned, used
+/// to validate `type_text`, `clear_field` and `set_checked` targets, including
+/// those `for_each` runs. `files` were sent with the request; with
+/// `ENABLE_FILE_UPLOAD` they can be attached to file inputs with
+/// `upload_file`, which is only given when there are any. Screenshot tools
+/// queue their captures in `images` for the agent's next model call.
+pub fn build_tools(
+    state: &Arc<AppState>,
+    session_id: &str,
+    element_roles: HashMap<i32, String>,
+    files: &[AttachedFileDto],
+    images: &PendingImages,
+) -> Vec<Box<dyn ToolDyn>> {
+    let session_id = session_id.to_string();
+    let disabled = &state.config.disabled_tools;
+    let enabled = |tools: Vec<Box<dyn ToolDyn>>| -> Vec<Box<dyn ToolDyn>> {
+        tools
+            .into_iter()
+            .filter(|tool| !disabled.contains(&tool.name()))
+            .collect()
+    };
+
+    let mut tools: Vec<Box<dyn ToolDyn>> = vec![Box::new(WsNavigateTool::new(
+        state.clone(),
+        session_id.clone(),
+    ))];
+    tools.extend(element_action_tools(state, &session_id, &element_roles));
+    // Without any action to repeat its schema would have an empty enum, which Gemini rejects
+    let repeatable = enabled(element_action_tools(state, &session_id, &element_roles));
+    if !repeatable.is_empty() {
+        tools.push(Box::new(ForEachTool::new(repeatable)));
+    }
+    let other_tools: Vec<Box<dyn ToolDyn>> = vec![
+        Box::new(WsScrollTool::new(state.clone(), session_id.clone())),
+        Box::new(WsScrollElementTool::new(state.clone(), session_id.clone())),
+        Box::new(WsGetPageContentTool::new(state.clone(), session_id.clone())),
+        Box::new(WsGetInteractiveElementsTool::new(
+            state.clone(),
+            session_id.clone(),
+        )),
+        Box::new(WsGoHistoryTool::new(state.clone(), session_id.clone())),
+        Box::new(WsCountElementsTool::new(state.clone(), session_id.clone())),
+        Box::new(WsHandleDialogTool::new(state.clone(), session_id.clone())),
+        Box::new(WsGetSelectionTool::new(state.clone(), session_id.clone())),
+        Box::new(WsGetPerformanceTool::new(state.clone(), session_id.clone())),
+        Box::new(WsGetLiveRegionsTool::new(state.clone(), session_id.clone())),
+        Box::new(WsGetConsoleLogsTool::new(state.clone(), session_id.clone())),
+        Box::new(
+            WsFullPageScreenshotTool::new(state.clone(), session_id.clone())
+                .with_pending_images(images.clone()),
+        ),
+        Box::new(
+            WsCaptureRegionTool::new(state.clone(), session_id.clone())
+                .with_pending_images(images.clone()),
+        ),
+        Box::new(
+            WsCaptureElementTool::new(state.clone(), session_id.clone())
+                .with_pending_images(images.clone()),
+        ),
+    ];
+    tools.extend(other_tools);
+    if state.config.file_upload && !files.is_empty() {
+        tools.push(Box::new(
+            WsUploadFileTool::new(state.clone(), session_id).with_files(files.to_vec()),
+        ));
+    }
+
+    enabled(tools)
+}
+
+/// Actions on a single element, which `for_each` may repeat over a list.
+fn element_action_tools(
+    state: &Arc<AppState>,
+    session_id: &str,
+    element_roles: &HashMap<i32, String>,
+) -> Vec<Box<dyn ToolDyn>> {
+    let session_id = session_id.to_string();
+    vec![
+        Box::new(WsClickTool::new(state.clone(), session_id.clone())),
+        Box::new(
+            WsTypeTool::new(state.clone(), session_id.clone())
+                .with_element_roles(element_roles.clone()),
+        ),
+        Box::new(
+            WsClearFieldTool::new(state.clone(), session_id.clone())
+                .with_element_roles(element_roles.clone()),
+        ),
+        Box::new(
+            WsSetCheckedTool::new(state.clone(), session_id)
+                .with_element_roles(element_roles.clone()),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    fn tool_names(config: AppConfig) -> Vec<String> {
+        let state = Arc::new(AppState::new(config));
+        build_tools(&state, "s1", HashMap::new(), &[], &PendingImages::default())
+            .iter()
+            .map(|tool| tool.name())
+            .collect()
+    }
+
+    #[test]
+    fn test_build_tools_default_set() {
+        let names = tool_names(AppConfig::for_tests());
+        assert_eq!(
+            names,
+            vec![
+                "navigate_to",
+                "click_element",
+                "type_text",
+                "clear_field",
+                "set_checked",
+                "for_each",
+                "scroll_to",
+                "scroll_element",
+                "get_page_content",
+                "get_interactive_elements",
+                "go_history",
+                "count_elements",
+                "handle_dialog",
+                "get_selection",
+                "get_performance",
+                "get_live_regions",
+                "get_console_logs",
+                "full_page_screenshot",
+                "capture_region",
+                "capture_element",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_tools_skips_disabled() {
+        let config = AppConfig {
+            disabled_tools: vec!["handle_dialog".to_string(), "capture_region".to_string()],
+            ..AppConfig::for_tests()
+        };
+        let names = tool_names(config);
+        assert_eq!(names.len(), 18);
+        assert!(!names.contains(&"handle_dialog".to_string()));
+        assert!(!names.contains(&"capture_region".to_string()));
+    }
+
+    #[test]
+    fn test_for_each_needs_an_action_to_repeat() {
+        let actions = ["click_element", "type_text", "clear_field", "set_checked"];
+        let config = AppConfig {
+            disabled_tools: actions.iter().map(|name| name.to_string()).collect(),
+            ..AppConfig::for_tests()
+        };
+        let names = tool_names(config);
+        assert!(!names.contains(&"for_each".to_string()));
+
+        let config = AppConfig {
+            disabled_tools: actions[1..].iter().map(|name| name.to_string()).collect(),
+            ..AppConfig::for_tests()
+        };
+        assert!(tool_names(config).contains(&"for_each".to_string()));
+    }
+
+    #[test]
+    fn test_upload_file_needs_flag_and_files() {
+        let files = [AttachedFileDto {
+            name: "cv.pdf".to_string(),
+            mime_type: None,
+            data: "JVBERi0=".to_string(),
+        }];
+        let has_upload = |file_upload: bool, files: &[AttachedFileDto]| {
+            let state = Arc::new(AppState::new(AppConfig {
+                file_upload,
+                ..AppConfig::for_tests()
+            }));
+            build_tools(
+                &state,
+                "s1",
+                HashMap::new(),
+                files,
+                &PendingImages::default(),
+            )
+            .iter()
+            .any(|tool| tool.name() == "upload_file")
+        };
+
+        assert!(has_upload(true, &files));
+        assert!(!has_upload(false, &files));
+        assert!(!has_upload(true, &[]));
+    }
+
+    #[test]
+    fn test_read_only_keeps_no_action_tools() {
+        let state = Arc::new(AppState::new(AppConfig::for_tests()));
+        let names: Vec<String> = read_only(build_tools(
+            &state,
+            "s1",
+            HashMap::new(),
+            &[],
+            &PendingImages::default(),
+        ))
+        .iter()
+        .map(|tool| tool.name())
+        .collect();
+
+        assert_eq!(names.len(), READ_ONLY_TOOLS.len());
+        for action in [
+            "navigate_to",
+            "click_element",
+            "type_text",
+            "clear_field",
+            "set_checked",
+            "for_each",
+            "scroll_to",
+            "scroll_element",
+            "go_history",
+            "handle_dialog",
+        ] {
+            assert!(
+                !names.contains(&action.to_string()),
+                "{} is available",
+                action
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_definitions_are_stable() {
+        let state = Arc::new(AppState::new(AppConfig::for_tests()));
+        let serialized = || async {
+            let mut definitions = Vec::new();
+            for tool in build_tools(&state, "s1", HashMap::new(), &[], &PendingImages::default()) {
+                definitions.push(tool.definition(String::new()).await);
+            }
+            serde_json::to_string(&definitions).unwrap()
+        };
+
+        assert_eq!(serialized().await, serialized().await);
+    }
+}
@@ -3,22 +3,68 @@
 //! These tools wrap the base browser tools and execute them via WebSocket
 //! connections to the browser extension.
 
+use base64::prelude::*;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::oneshot;
 use tokio::time::{Duration, timeout};
 use uuid::Uuid;
 
 use rig::completion::ToolDefinition;
+use rig::message::ImageMediaType;
 use rig::tool::Tool;
 
-use crate::models::ws::{ActionCommand, WsMessage};
+use crate::dtos::agent::AttachedFileDto;
+use crate::llm::image_relay::{PendingImage, PendingImages};
+use crate::llm::{MAX_SCREENSHOT_DIMENSION, downscale_image, prepare_image};
+use crate::models::screenshots::ScreenshotEvent;
+use crate::models::ws::{
+    ActionCommand, ActionLogStatus, CheckedData, ConsoleLogs, CountData, DialogAction, DialogData,
+    HistoryData, InteractiveElementsData, LiveRegions, NavigationData, PageContentData,
+    PerformanceMetrics, ScrollPosition, SelectionData, UploadData, WsMessage,
+};
 use crate::state::AppState;
 use crate::tools::browser::{
-    ClickArgs, ClickTool, GetInteractiveElementsArgs, GetInteractiveElementsTool,
-    GetPageContentArgs, GetPageContentTool, NavigateArgs, NavigateTool, ScrollArgs, ScrollTool,
-    TypeArgs, TypeTool,
+    CaptureElementArgs, CaptureElementTool, CaptureRegionArgs, CaptureRegionTool, ClearFieldArgs,
+    ClearFieldTool, ClickArgs, ClickTool, CountElementsArgs, CountElementsTool,
+    FullPageScreenshotArgs, FullPageScreenshotTool, GetConsoleLogsArgs, GetConsoleLogsTool,
+    GetInteractiveElementsArgs, GetInteractiveElementsTool, GetLiveRegionsArgs, GetLiveRegionsTool,
+    GetPageContentArgs, GetPageContentTool, GetPerformanceArgs, GetPerformanceTool,
+    GetSelectionArgs, GetSelectionTool, GoHistoryArgs, GoHistoryTool, HandleDialogArgs,
+    HandleDialogTool, NavigateArgs, NavigateTool, ScrollArgs, ScrollElementArgs, ScrollElementTool,
+    ScrollTool, SetCheckedArgs, SetCheckedTool, TypeArgs, TypeTool, UploadFileArgs, UploadFileTool,
 };
 
+/// Maximum number of history entries a single `go_history` call may move.
+const MAX_HISTORY_DELTA: i32 = 20;
+
+/// Largest width or height, in CSS pixels, a single `capture_region` call may request.
+const MAX_REGION_SIZE: u32 = 4096;
+
+/// What `capture_region` asks about the region when no question is given.
+const DEFAULT_REGION_QUESTION: &str = "Describe what is visible in this part of the page.";
+
+/// What `capture_element` asks about the element when no question is given.
+const DEFAULT_ELEMENT_QUESTION: &str = "Describe what this element shows.";
+
+/// Most console messages a single `get_console_logs` call returns; the most recent are kept.
+const MAX_CONSOLE_LOGS: usize = 50;
+
+/// Largest file, in bytes, a single `upload_file` call may attach.
+const MAX_UPLOAD_BYTES: usize = 1024 * 1024;
+
+/// Largest total size, in bytes, of the files sent with one request. As base64
+/// they take a third more, within `AGENT_RUN_BODY_LIMIT` next to a screenshot
+/// and the page content.
+pub const MAX_UPLOAD_TOTAL_BYTES: usize = 4 * 1024 * 1024;
+
+/// Element roles that accept typed text.
+const EDITABLE_ROLES: &[&str] = &["textbox", "searchbox", "combobox", "spinbutton"];
+
+/// Element roles with a checked state.
+const CHECKABLE_ROLES: &[&str] = &["checkbox", "radio", "switch", "menuitemcheckbox"];
+
 // --- Error Type ---
 #[derive(Debug)]
 pub struct ToolError(pub String);
@@ -37,20 +83,199 @@ pub(crate) async fn execute_tool(
     session_id: &str,
     command: ActionCommand,
 ) -> Result<String, String> {
+    let data = execute_action(state, session_id, command.clone()).await?;
+    format_action_data(&command, data.as_ref())
+}
+
+/// Formats `ActionResult.data` for the model in the shape `command` returns,
+/// failing when the extension sent data that doesn't fit it. The output only
+/// depends on the parsed fields, so identical data (e.g. the same interactive
+/// elements) always yields the same string, whatever order the keys came in.
+fn format_action_data(
+    command: &ActionCommand,
+    data: Option<&serde_json::Value>,
+) -> Result<String, String> {
+    let text = match command {
+        ActionCommand::NavigateTo { .. } => {
+            let navigation: NavigationData = parse_action_data(data, "navigation")?;
+            format!("Success. Navigated to {}", navigation.navigated_to)
+        }
+        ActionCommand::ClickElement { .. }
+        | ActionCommand::TypeText { .. }
+        | ActionCommand::ClearField { .. }
+        | ActionCommand::ScrollTo { .. } => "Success.".to_string(),
+        ActionCommand::SetChecked { .. } => {
+            let state: CheckedData = parse_action_data(data, "checked state")?;
+            let checked = if state.checked {
+                "checked"
+            } else {
+                "unchecked"
+            };
+            if state.changed {
+                format!("Success. The element is now {}.", checked)
+            } else {
+                format!("Success. The element was already {}.", checked)
+            }
+        }
+        ActionCommand::UploadFile { .. } => {
+            let upload: UploadData = parse_action_data(data, "upload")?;
+            format!(
+                "Success. Attached {} ({} bytes).",
+                upload.file_name, upload.size
+            )
+        }
+        ActionCommand::ScrollElement { .. } => {
+            let position: ScrollPosition = parse_action_data(data, "scroll position")?;
+            format!(
+                "Success. Container scrolled to top={:.0}, left={:.0}{}",
+                position.scroll_top,
+                position.scroll_left,
+                if position.at_bottom {
+                    " (reached the bottom)"
+                } else {
+                    ""
+                }
+            )
+        }
+        ActionCommand::GetPageContent { .. } => {
+            let page: PageContentData = parse_action_data(data, "page content")?;
+            format!("Title: {}\nURL: {}\n\n{}", page.title, page.url, page.text)
+        }
+        ActionCommand::GoHistory { .. } => {
+            let history: HistoryData = parse_action_data(data, "history")?;
+            format!("Success. Now at {}", history.url)
+        }
+        ActionCommand::CountElements { .. } => {
+            let count: CountData = parse_action_data(data, "element count")?;
+            format!("{} element(s) match the selector.", count.count)
+        }
+        ActionCommand::HandleDialog { .. } => {
+            let dialog: DialogData = parse_action_data(data, "dialog")?;
+            match dialog.action {
+                DialogAction::Accept => "Success. The dialog was accepted.".to_string(),
+                DialogAction::Dismiss => "Success. The dialog was dismissed.".to_string(),
+            }
+        }
+        ActionCommand::GetSelection => {
+            let selection: SelectionData = parse_action_data(data, "selection")?;
+            if selection.text.is_empty() {
+                "No text is selected on the page.".to_string()
+            } else if let Some(context) = selection.context {
+                format!(
+                    "Selected text: {}\nSurrounding <{}>: {}",
+                    selection.text, context.tag, context.text
+                )
+            } else {
+                format!("Selected text: {}", selection.text)
+            }
+        }
+        // These tools parse their own data
+        ActionCommand::GetInteractiveElements { .. }
+        | ActionCommand::GetPerformance
+        | ActionCommand::GetLiveRegions
+        | ActionCommand::FullPageScreenshot
+        | ActionCommand::CaptureRegion { .. }
+        | ActionCommand::CaptureElement { .. } => match data {
+            Some(data) => format!("Success. Data: {}", data),
+            None => "Success.".to_string(),
+        },
+    };
+    Ok(text)
+}
+
+/// Parses `ActionResult.data` as the `T` a command returns.
+fn parse_action_data<T: serde::de::DeserializeOwned>(
+    data: Option<&serde_json::Value>,
+    what: &str,
+) -> Result<T, String> {
+    let data = data.ok_or_else(|| format!("The browser returned no {} data", what))?;
+    T::deserialize(data).map_err(|e| format!("Invalid {} data from the browser: {}", what, e))
+}
+
+/// Lists interactive elements one per line, e.g. `[ref=3] button "Masuk"`.
+///
+/// At most `max` elements are listed, in document order. When there are more,
+/// disabled elements are left out first and a note says how many are missing.
+fn format_interactive_elements(
+    data: Option<&serde_json::Value>,
+    max: usize,
+) -> Result<String, String> {
+    let elements: InteractiveElementsData = parse_action_data(data, "interactive elements")?;
+    let total = elements.elements.len();
+    if total == 0 {
+        return Ok("No interactive elements found on the page.".to_string());
+    }
+
+    let enabled = elements.elements.iter().filter(|e| !e.disabled).count();
+    let mut disabled_slots = max.saturating_sub(enabled);
+    let mut enabled_slots = max;
+    let shown: Vec<_> = elements
+        .elements
+        .iter()
+        .filter(|element| {
+            let slots = if element.disabled {
+                &mut disabled_slots
+            } else {
+                &mut enabled_slots
+            };
+            if *slots == 0 {
+                return false;
+            }
+            *slots -= 1;
+            true
+        })
+        .collect();
+
+    let mut text = shown
+        .iter()
+        .map(|element| {
+            let checked = match element.checked {
+                Some(true) => " (checked)",
+                Some(false) => " (unchecked)",
+                None => "",
+            };
+            let disabled = if element.disabled { " (disabled)" } else { "" };
+            format!(
+                "[ref={}] {} \"{}\"{}{}",
+                element.id, element.role, element.name, checked, disabled
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if shown.len() < total {
+        text.push_str(&format!(
+            "\n(List truncated: showing {} of {} elements, disabled ones left out first. Scroll and scan again to find the others.)",
+            shown.len(),
+            total
+        ));
+    }
+    Ok(text)
+}
+
+/// Sends a command to the extension and waits for its `ActionResult.data`.
+async fn execute_action(
+    state: &Arc<AppState>,
+    session_id: &str,
+    command: ActionCommand,
+) -> Result<Option<serde_json::Value>, String> {
     // 1. Get connection
     let tx = state
         .get_connection(session_id)
         .await
         .ok_or("No active WebSocket connection for this session")?;
 
-    // 2. Register pending action
+    // 2. Space out actions on this session
+    state.tool_pacer.wait_turn(session_id).await;
+
+    // 3. Register pending action
     let request_id = Uuid::new_v4().to_string();
     let (tx_result, rx_result) = oneshot::channel();
     state
         .register_pending_action(request_id.clone(), tx_result)
         .await;
 
-    // 3. Send command
+    // 4. Send command
+    let tool = command_name(&command);
     let msg = WsMessage::ActionRequest {
         request_id: request_id.clone(),
         command,
@@ -63,21 +288,67 @@ pub(crate) async fn execute_tool(
         request_id,
         session_id
     );
+    let log = |status| {
+        // The timeline is informational; a closed connection is reported by the action itself
+        let _ = tx.send(WsMessage::ActionLog {
+            request_id: request_id.clone(),
+            tool: tool.clone(),
+            status,
+            timestamp: unix_now_ms(),
+        });
+    };
+    log(ActionLogStatus::Started);
 
-    // 4. Wait for result
-    let result = timeout(Duration::from_secs(30), rx_result)
-        .await
-        .map_err(|_| "Tool execution timed out after 30 seconds")?
-        .map_err(|_| "Response channel closed unexpectedly")?;
-
-    // 5. Return result
-    if result.success {
-        Ok(format!("Success. Data: {:?}", result.data))
+    // 5. Wait for result
+    let result = match timeout(Duration::from_secs(30), rx_result).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => {
+            log(ActionLogStatus::Failed);
+            return Err("Response channel closed unexpectedly".to_string());
+        }
+        Err(_) => {
+            log(ActionLogStatus::Failed);
+            return Err("Tool execution timed out after 30 seconds".to_string());
+        }
+    };
+    log(if result.success {
+        ActionLogStatus::Succeeded
     } else {
-        Err(format!("Error: {:?}", result.error))
+        ActionLogStatus::Failed
+    });
+
+    // 6. Adapt pacing and return result
+    let error = (!result.success).then(|| action_error(result.error));
+    state
+        .tool_pacer
+        .record_outcome(session_id, error.as_deref())
+        .await;
+
+    match error {
+        None => Ok(result.data),
+        Some(error) => Err(format!("Error: {}", error)),
     }
 }
 
+/// The extension's message for a failed action, as plain text.
+fn action_error(error: Option<String>) -> String {
+    error.unwrap_or_else(|| "The browser reported a failure without details".to_string())
+}
+
+/// The command's `type` tag, e.g. `click_element`.
+fn command_name(command: &ActionCommand) -> String {
+    serde_json::to_value(command)
+        .ok()
+        .and_then(|value| value["type"].as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
 // --- Tool Implementations with constructors ---
 
 pub struct WsNavigateTool {
@@ -112,6 +383,12 @@ impl Tool for WsNavigateTool {
                 "Navigation to system pages (chrome://, about://, file://) is not allowed".into(),
             ));
         }
+        check_navigation_domain(
+            &self.state.config.allowed_domains,
+            &self.state.config.blocked_domains,
+            &args.url,
+        )
+        .map_err(ToolError)?;
 
         execute_tool(
             &self.state,
@@ -160,11 +437,22 @@ impl Tool for WsClickTool {
 pub struct WsTypeTool {
     state: Arc<AppState>,
     session_id: String,
+    element_roles: HashMap<i32, String>,
 }
 
 impl WsTypeTool {
     pub fn new(state: Arc<AppState>, session_id: String) -> Self {
-        Self { state, session_id }
+        Self {
+            state,
+            session_id,
+            element_roles: HashMap::new(),
+        }
+    }
+
+    /// Roles of elements already known from the request, keyed by ref ID.
+    pub fn with_element_roles(mut self, element_roles: HashMap<i32, String>) -> Self {
+        self.element_roles = element_roles;
+        self
     }
 }
 
@@ -179,6 +467,8 @@ impl Tool for WsTypeTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        check_editable(&self.element_roles, args.ref_id).map_err(ToolError)?;
+
         execute_tool(
             &self.state,
             &self.session_id,
@@ -192,6 +482,209 @@ impl Tool for WsTypeTool {
     }
 }
 
+pub struct WsClearFieldTool {
+    state: Arc<AppState>,
+    session_id: String,
+    element_roles: HashMap<i32, String>,
+}
+
+impl WsClearFieldTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self {
+            state,
+            session_id,
+            element_roles: HashMap::new(),
+        }
+    }
+
+    /// Roles of elements already known from the request, keyed by ref ID.
+    pub fn with_element_roles(mut self, element_roles: HashMap<i32, String>) -> Self {
+        self.element_roles = element_roles;
+        self
+    }
+}
+
+impl Tool for WsClearFieldTool {
+    const NAME: &'static str = ClearFieldTool::NAME;
+    type Error = ToolError;
+    type Args = ClearFieldArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        ClearFieldTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        check_editable(&self.element_roles, args.ref_id).map_err(ToolError)?;
+
+        execute_tool(
+            &self.state,
+            &self.session_id,
+            ActionCommand::ClearField {
+                ref_id: args.ref_id,
+            },
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+pub struct WsSetCheckedTool {
+    state: Arc<AppState>,
+    session_id: String,
+    element_roles: HashMap<i32, String>,
+}
+
+impl WsSetCheckedTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self {
+            state,
+            session_id,
+            element_roles: HashMap::new(),
+        }
+    }
+
+    /// Roles of elements already known from the request, keyed by ref ID.
+    pub fn with_element_roles(mut self, element_roles: HashMap<i32, String>) -> Self {
+        self.element_roles = element_roles;
+        self
+    }
+}
+
+impl Tool for WsSetCheckedTool {
+    const NAME: &'static str = SetCheckedTool::NAME;
+    type Error = ToolError;
+    type Args = SetCheckedArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        SetCheckedTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        check_checkable(&self.element_roles, args.ref_id).map_err(ToolError)?;
+
+        execute_tool(
+            &self.state,
+            &self.session_id,
+            ActionCommand::SetChecked {
+                ref_id: args.ref_id,
+                checked: args.checked,
+            },
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+pub struct WsUploadFileTool {
+    state: Arc<AppState>,
+    session_id: String,
+    files: Vec<AttachedFileDto>,
+}
+
+impl WsUploadFileTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self {
+            state,
+            session_id,
+            files: Vec::new(),
+        }
+    }
+
+    /// Files sent with the request, the only ones that can be uploaded.
+    pub fn with_files(mut self, files: Vec<AttachedFileDto>) -> Self {
+        self.files = files;
+        self
+    }
+}
+
+impl Tool for WsUploadFileTool {
+    const NAME: &'static str = UploadFileTool::NAME;
+    type Error = ToolError;
+    type Args = UploadFileArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        let mut definition = UploadFileTool.definition(prompt).await;
+        let names: Vec<&str> = self.files.iter().map(|file| file.name.as_str()).collect();
+        definition.parameters["properties"]["file_name"]["enum"] = serde_json::json!(names);
+        definition
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let file = self
+            .files
+            .iter()
+            .find(|file| file.name == args.file_name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.files.iter().map(|file| file.name.as_str()).collect();
+                ToolError(format!(
+                    "No file named {} was attached. Attached files: {}",
+                    args.file_name,
+                    names.join(", ")
+                ))
+            })?;
+        let (data_type, data_base64) = upload_payload(&file.data).map_err(ToolError)?;
+
+        execute_tool(
+            &self.state,
+            &self.session_id,
+            ActionCommand::UploadFile {
+                ref_id: args.ref_id,
+                file_name: file.name.clone(),
+                mime_type: file.mime_type.clone().or(data_type),
+                data_base64,
+            },
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+/// Decoded size, in bytes, of files sent with a request, estimated from the
+/// length of their base64 content.
+pub fn attached_files_size(files: &[AttachedFileDto]) -> usize {
+    files
+        .iter()
+        .map(|file| {
+            let content = file
+                .data
+                .split_once(";base64,")
+                .map_or(file.data.as_str(), |(_, content)| content);
+            content.trim().len() / 4 * 3
+        })
+        .sum()
+}
+
+/// Splits an attached file (a data URL or bare base64) into its MIME type, if
+/// given, and base64 content, rejecting invalid data and files larger than
+/// `MAX_UPLOAD_BYTES`.
+fn upload_payload(data: &str) -> Result<(Option<String>, String), String> {
+    let (mime_type, content) = match data
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"))
+    {
+        Some((mime_type, content)) => (
+            Some(mime_type.to_string()).filter(|mime_type| !mime_type.is_empty()),
+            content,
+        ),
+        None => (None, data),
+    };
+    let content = content.trim();
+    let bytes = BASE64_STANDARD
+        .decode(content)
+        .map_err(|e| format!("The attached file is not valid base64: {}", e))?;
+    if bytes.len() > MAX_UPLOAD_BYTES {
+        return Err(format!(
+            "The attached file is {} bytes; at most {} bytes can be uploaded",
+            bytes.len(),
+            MAX_UPLOAD_BYTES
+        ));
+    }
+    Ok((mime_type, content.to_string()))
+}
+
 pub struct WsScrollTool {
     state: Arc<AppState>,
     session_id: String,
@@ -227,6 +720,42 @@ impl Tool for WsScrollTool {
     }
 }
 
+pub struct WsScrollElementTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsScrollElementTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsScrollElementTool {
+    const NAME: &'static str = ScrollElementTool::NAME;
+    type Error = ToolError;
+    type Args = ScrollElementArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        ScrollElementTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        execute_tool(
+            &self.state,
+            &self.session_id,
+            ActionCommand::ScrollElement {
+                ref_id: args.ref_id,
+                dx: args.dx,
+                dy: args.dy,
+            },
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
 pub struct WsGetPageContentTool {
     state: Arc<AppState>,
     session_id: String,
@@ -283,12 +812,1143 @@ impl Tool for WsGetInteractiveElementsTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        execute_tool(
+        let data = execute_action(
             &self.state,
             &self.session_id,
             ActionCommand::GetInteractiveElements { limit: args.limit },
         )
         .await
+        .map_err(ToolError)?;
+        format_interactive_elements(data.as_ref(), self.state.config.max_interactive_elements)
+            .map_err(ToolError)
+    }
+}
+
+pub struct WsGoHistoryTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsGoHistoryTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsGoHistoryTool {
+    const NAME: &'static str = GoHistoryTool::NAME;
+    type Error = ToolError;
+    type Args = GoHistoryArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        GoHistoryTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        validate_history_delta(args.delta).map_err(ToolError)?;
+
+        execute_tool(
+            &self.state,
+            &self.session_id,
+            ActionCommand::GoHistory { delta: args.delta },
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+pub struct WsCountElementsTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsCountElementsTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsCountElementsTool {
+    const NAME: &'static str = CountElementsTool::NAME;
+    type Error = ToolError;
+    type Args = CountElementsArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        CountElementsTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.selector.trim().is_empty() {
+            return Err(ToolError("Selector must not be empty".into()));
+        }
+
+        execute_tool(
+            &self.state,
+            &self.session_id,
+            ActionCommand::CountElements {
+                selector: args.selector,
+            },
+        )
+        .await
         .map_err(ToolError)
     }
 }
+
+pub struct WsHandleDialogTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsHandleDialogTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsHandleDialogTool {
+    const NAME: &'static str = HandleDialogTool::NAME;
+    type Error = ToolError;
+    type Args = HandleDialogArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        HandleDialogTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        execute_tool(
+            &self.state,
+            &self.session_id,
+            ActionCommand::HandleDialog {
+                action: args.action,
+                prompt_text: args.prompt_text,
+            },
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+pub struct WsGetSelectionTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsGetSelectionTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsGetSelectionTool {
+    const NAME: &'static str = GetSelectionTool::NAME;
+    type Error = ToolError;
+    type Args = GetSelectionArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        GetSelectionTool.definition(prompt).await
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        execute_tool(&self.state, &self.session_id, ActionCommand::GetSelection)
+            .await
+            .map_err(ToolError)
+    }
+}
+
+pub struct WsGetPerformanceTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsGetPerformanceTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsGetPerformanceTool {
+    const NAME: &'static str = GetPerformanceTool::NAME;
+    type Error = ToolError;
+    type Args = GetPerformanceArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        GetPerformanceTool.definition(prompt).await
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let data = execute_action(&self.state, &self.session_id, ActionCommand::GetPerformance)
+            .await
+            .map_err(ToolError)?;
+        let metrics: PerformanceMetrics = data
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| ToolError(format!("Invalid performance data: {}", e)))?
+            .unwrap_or_default();
+
+        Ok(format_performance(&metrics))
+    }
+}
+
+/// Summarizes performance metrics for the model, marking missing ones.
+fn format_performance(metrics: &PerformanceMetrics) -> String {
+    if !metrics.available {
+        return "Performance metrics are not available for this page (e.g. it was restored from cache or the browser doesn't expose Navigation Timing).".to_string();
+    }
+
+    let ms = |value: Option<u64>| value.map_or("n/a".to_string(), |v| format!("{} ms", v));
+    let mut summary = format!(
+        "TTFB: {}\nDOMContentLoaded: {}\nLoad: {}\nLCP: {}\nResources: {}",
+        ms(metrics.ttfb_ms),
+        ms(metrics.dom_content_loaded_ms),
+        ms(metrics.load_ms),
+        ms(metrics.lcp_ms),
+        metrics.resource_count
+    );
+    if let Some(bytes) = metrics.transfer_size {
+        summary.push_str(&format!("\nDocument size: {} KB", bytes / 1024));
+    }
+    if !metrics.slowest_resources.is_empty() {
+        summary.push_str("\nSlowest resources:");
+        for resource in &metrics.slowest_resources {
+            summary.push_str(&format!(
+                "\n- {} ({}): {} ms",
+                resource.url, resource.kind, resource.duration_ms
+            ));
+        }
+    }
+    summary
+}
+
+pub struct WsGetLiveRegionsTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsGetLiveRegionsTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsGetLiveRegionsTool {
+    const NAME: &'static str = GetLiveRegionsTool::NAME;
+    type Error = ToolError;
+    type Args = GetLiveRegionsArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        GetLiveRegionsTool.definition(prompt).await
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let data = execute_action(&self.state, &self.session_id, ActionCommand::GetLiveRegions)
+            .await
+            .map_err(ToolError)?;
+        let live: LiveRegions = data
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| ToolError(format!("Invalid live region data: {}", e)))?
+            .unwrap_or_default();
+
+        Ok(format_live_regions(&live))
+    }
+}
+
+pub struct WsGetConsoleLogsTool {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl WsGetConsoleLogsTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self { state, session_id }
+    }
+}
+
+impl Tool for WsGetConsoleLogsTool {
+    const NAME: &'static str = GetConsoleLogsTool::NAME;
+    type Error = ToolError;
+    type Args = GetConsoleLogsArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        GetConsoleLogsTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let data = execute_action(
+            &self.state,
+            &self.session_id,
+            ActionCommand::GetConsoleLogs {
+                levels: args.levels,
+            },
+        )
+        .await
+        .map_err(ToolError)?;
+        let mut logs: ConsoleLogs = data
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| ToolError(format!("Invalid console log data: {}", e)))?
+            .unwrap_or_default();
+
+        // The extension caps the list too, but don't trust it to
+        if logs.entries.len() > MAX_CONSOLE_LOGS {
+            let excess = logs.entries.len() - MAX_CONSOLE_LOGS;
+            logs.entries.drain(..excess);
+            logs.omitted += excess;
+        }
+        Ok(format_console_logs(&logs))
+    }
+}
+
+/// Lists console messages, oldest first, for the model.
+fn format_console_logs(logs: &ConsoleLogs) -> String {
+    if logs.entries.is_empty() {
+        return "No matching console messages since the page loaded.".to_string();
+    }
+
+    let mut summary = String::from("Console messages (most recent last):");
+    if logs.omitted > 0 {
+        summary.push_str(&format!("\n({} older messages omitted)", logs.omitted));
+    }
+    for entry in &logs.entries {
+        let level = serde_json::to_value(entry.level)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_uppercase))
+            .unwrap_or_default();
+        summary.push_str(&format!(
+            "\n- {:.1}s ago [{}] {}",
+            entry.age_ms as f64 / 1000.0,
+            level,
+            entry.message
+        ));
+        if let Some(source) = &entry.source {
+            summary.push_str(&format!(" ({})", source));
+        }
+    }
+    summary
+}
+
+/// Lists live regions and their announcements for the model.
+fn format_live_regions(live: &LiveRegions) -> String {
+    if live.regions.is_empty() && live.announcements.is_empty() {
+        return "The page has no ARIA live regions and nothing has been announced.".to_string();
+    }
+
+    let mut summary = String::from("Live regions:");
+    if live.regions.is_empty() {
+        summary.push_str(" none currently on the page");
+    }
+    for region in &live.regions {
+        let kind = match &region.role {
+            Some(role) => format!("{}, role={}", region.politeness, role),
+            None => region.politeness.clone(),
+        };
+        let text = if region.text.is_empty() {
+            "(empty)"
+        } else {
+            &region.text
+        };
+        summary.push_str(&format!("\n- [{}] {}", kind, text));
+    }
+
+    summary.push_str("\nRecent announcements:");
+    if live.announcements.is_empty() {
+        summary.push_str(" none");
+    }
+    for announcement in &live.announcements {
+        summary.push_str(&format!(
+            "\n- {:.1}s ago ({}): {}",
+            announcement.age_ms as f64 / 1000.0,
+            announcement.politeness,
+            announcement.text
+        ));
+    }
+    summary
+}
+
+pub struct WsCaptureRegionTool {
+    state: Arc<AppState>,
+    session_id: String,
+    images: PendingImages,
+}
+
+impl WsCaptureRegionTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self {
+            state,
+            session_id,
+            images: PendingImages::default(),
+        }
+    }
+
+    /// Queue the capture goes to, for the agent's next model call.
+    pub fn with_pending_images(mut self, images: PendingImages) -> Self {
+        self.images = images;
+        self
+    }
+}
+
+impl Tool for WsCaptureRegionTool {
+    const NAME: &'static str = CaptureRegionTool::NAME;
+    type Error = ToolError;
+    type Args = CaptureRegionArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        CaptureRegionTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let question = args
+            .question
+            .clone()
+            .unwrap_or_else(|| DEFAULT_REGION_QUESTION.to_string());
+        let command = capture_region_command(args).map_err(ToolError)?;
+
+        let data = execute_action(&self.state, &self.session_id, command)
+            .await
+            .map_err(ToolError)?;
+        attach_screenshot(
+            &self.state,
+            &self.session_id,
+            Self::NAME,
+            &self.images,
+            &question,
+            data.as_ref(),
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+pub struct WsCaptureElementTool {
+    state: Arc<AppState>,
+    session_id: String,
+    images: PendingImages,
+}
+
+impl WsCaptureElementTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self {
+            state,
+            session_id,
+            images: PendingImages::default(),
+        }
+    }
+
+    /// Queue the capture goes to, for the agent's next model call.
+    pub fn with_pending_images(mut self, images: PendingImages) -> Self {
+        self.images = images;
+        self
+    }
+}
+
+impl Tool for WsCaptureElementTool {
+    const NAME: &'static str = CaptureElementTool::NAME;
+    type Error = ToolError;
+    type Args = CaptureElementArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        CaptureElementTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let question = args
+            .question
+            .unwrap_or_else(|| DEFAULT_ELEMENT_QUESTION.to_string());
+        let command = ActionCommand::CaptureElement {
+            ref_id: args.ref_id,
+        };
+
+        let data = execute_action(&self.state, &self.session_id, command)
+            .await
+            .map_err(ToolError)?;
+        attach_screenshot(
+            &self.state,
+            &self.session_id,
+            Self::NAME,
+            &self.images,
+            &question,
+            data.as_ref(),
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+pub struct WsFullPageScreenshotTool {
+    state: Arc<AppState>,
+    session_id: String,
+    images: PendingImages,
+}
+
+impl WsFullPageScreenshotTool {
+    pub fn new(state: Arc<AppState>, session_id: String) -> Self {
+        Self {
+            state,
+            session_id,
+            images: PendingImages::default(),
+        }
+    }
+
+    /// Queue the capture goes to, for the agent's next model call.
+    pub fn with_pending_images(mut self, images: PendingImages) -> Self {
+        self.images = images;
+        self
+    }
+}
+
+impl Tool for WsFullPageScreenshotTool {
+    const NAME: &'static str = FullPageScreenshotTool::NAME;
+    type Error = ToolError;
+    type Args = FullPageScreenshotArgs;
+    type Output = String;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        FullPageScreenshotTool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let data = execute_action(
+            &self.state,
+            &self.session_id,
+            ActionCommand::FullPageScreenshot,
+        )
+        .await
+        .map_err(ToolError)?;
+        attach_screenshot(
+            &self.state,
+            &self.session_id,
+            Self::NAME,
+            &self.images,
+            &args.question,
+            data.as_ref(),
+        )
+        .await
+        .map_err(ToolError)
+    }
+}
+
+/// Queues a captured screenshot for the agent's next model call, where it is
+/// sent as an image part, and returns the tool result pointing the model to it.
+///
+/// Gemini only takes text tool results, so the image can't be returned from
+/// the tool itself. The capture is also sent to clients watching the
+/// session's screenshots.
+async fn attach_screenshot(
+    state: &Arc<AppState>,
+    session_id: &str,
+    tool: &str,
+    images: &PendingImages,
+    question: &str,
+    data: Option<&serde_json::Value>,
+) -> Result<String, String> {
+    let image = screenshot_image(data)?;
+    let event = ScreenshotEvent {
+        tool: tool.to_string(),
+        image: image.to_string(),
+        timestamp: unix_now_ms(),
+    };
+    state
+        .screenshot_feeds
+        .write()
+        .await
+        .publish(session_id, event);
+
+    images.push(pending_screenshot(
+        tool,
+        question,
+        image,
+        state.config.default_image_type.clone(),
+    ));
+    Ok(format!(
+        "Screenshot captured. It is attached to the next message as an image; look at it to answer: {}",
+        question
+    ))
+}
+
+/// A screenshot data URL as a queued image, downscaled to
+/// `MAX_SCREENSHOT_DIMENSION`.
+fn pending_screenshot(
+    tool: &str,
+    question: &str,
+    image: &str,
+    default_type: ImageMediaType,
+) -> PendingImage {
+    let image = downscale_image(image, MAX_SCREENSHOT_DIMENSION);
+    let (media_type, data) = prepare_image(&image, default_type);
+    PendingImage {
+        caption: format!("Screenshot from {} ({}):", tool, question),
+        media_type,
+        data,
+    }
+}
+
+/// Extracts the image data URL from a screenshot `ActionResult.data`.
+fn screenshot_image(data: Option<&serde_json::Value>) -> Result<&str, String> {
+    data.and_then(|d| d.get("image"))
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| "Extension returned no screenshot image".to_string())
+}
+
+/// Builds the `capture_region` command, rejecting empty or oversized regions.
+fn capture_region_command(args: CaptureRegionArgs) -> Result<ActionCommand, String> {
+    if args.x < 0 || args.y < 0 {
+        return Err("Region origin must not be negative".into());
+    }
+    if args.w == 0 || args.h == 0 {
+        return Err("Region width and height must be greater than 0".into());
+    }
+    if args.w > MAX_REGION_SIZE || args.h > MAX_REGION_SIZE {
+        return Err(format!(
+            "Region width and height must be at most {} pixels",
+            MAX_REGION_SIZE
+        ));
+    }
+    Ok(ActionCommand::CaptureRegion {
+        x: args.x,
+        y: args.y,
+        w: args.w,
+        h: args.h,
+    })
+}
+
+/// Rejects typing into an element whose known role doesn't accept text.
+/// Unknown refs are left for the extension to validate.
+fn check_editable(element_roles: &HashMap<i32, String>, ref_id: i32) -> Result<(), String> {
+    match element_roles.get(&ref_id) {
+        Some(role) if !EDITABLE_ROLES.contains(&role.as_str()) => Err(format!(
+            "Element with ref {} is not editable (role: {})",
+            ref_id, role
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_checkable(element_roles: &HashMap<i32, String>, ref_id: i32) -> Result<(), String> {
+    match element_roles.get(&ref_id) {
+        Some(role) if !CHECKABLE_ROLES.contains(&role.as_str()) => Err(format!(
+            "Element with ref {} is not a checkbox or radio button (role: {})",
+            ref_id, role
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the URL's host against `AGENT_ALLOWED_DOMAINS` and `NAV_BLOCKLIST`.
+/// An empty allow list allows every host not blocked; in both lists
+/// `*.example.com` matches example.com and all its subdomains.
+fn check_navigation_domain(
+    allowed_domains: &[String],
+    blocked_domains: &[String],
+    url: &str,
+) -> Result<(), String> {
+    if allowed_domains.is_empty() && blocked_domains.is_empty() {
+        return Ok(());
+    }
+
+    // The model sometimes omits the scheme
+    let host = reqwest::Url::parse(url)
+        .or_else(|_| reqwest::Url::parse(&format!("https://{}", url)))
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+        .ok_or_else(|| format!("Invalid URL: {}", url))?;
+
+    if blocked_domains
+        .iter()
+        .any(|pattern| host_matches(&host, pattern))
+    {
+        return Err(format!(
+            "Navigation to this site ({}) is blocked by the administrator",
+            host
+        ));
+    }
+    if allowed_domains.is_empty()
+        || allowed_domains
+            .iter()
+            .any(|pattern| host_matches(&host, pattern))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Navigation to {} is not allowed; the agent may only visit: {}",
+            host,
+            allowed_domains.join(", ")
+        ))
+    }
+}
+
+/// Whether a lowercase host matches `example.com` or `*.example.com`.
+/// A trailing dot (`example.com.`) names the same host, so it is ignored.
+fn host_matches(host: &str, pattern: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    let pattern = pattern.to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+        None => host == pattern,
+    }
+}
+
+fn validate_history_delta(delta: i32) -> Result<(), String> {
+    if delta == 0 {
+        return Err("History delta must not be 0".into());
+    }
+    // `unsigned_abs` because the model may send `i32::MIN`, whose `abs` overflows
+    if delta.unsigned_abs() > MAX_HISTORY_DELTA.unsigned_abs() {
+        return Err(format!(
+            "History delta must be between -{} and {}",
+            MAX_HISTORY_DELTA, MAX_HISTORY_DELTA
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_action_emits_start_and_end_log() {
+        use crate::config::AppConfig;
+        use crate::models::ws::ActionResult;
+        use tokio::sync::mpsc;
+
+        let state = Arc::new(AppState::new(AppConfig::for_tests()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        state.register_connection("s1".to_string(), tx).await;
+
+        // The extension: answers the request and collects everything it receives
+        let browser = async {
+            let mut received = Vec::new();
+            while let Some(msg) = rx.recv().await {
+                let done = matches!(
+                    msg,
+                    WsMessage::ActionLog { status, .. } if status != ActionLogStatus::Started
+                );
+                if let WsMessage::ActionRequest { request_id, .. } = &msg {
+                    let result = ActionResult {
+                        request_id: request_id.clone(),
+                        success: true,
+                        error: None,
+                        data: None,
+                    };
+                    state.complete_pending_action(request_id, result).await;
+                }
+                received.push(msg);
+                if done {
+                    break;
+                }
+            }
+            received
+        };
+        let (result, received) = tokio::join!(
+            execute_action(&state, "s1", ActionCommand::ClickElement { ref_id: 1 }),
+            browser
+        );
+        assert!(result.is_ok());
+
+        let logs: Vec<_> = received
+            .iter()
+            .filter_map(|msg| match msg {
+                WsMessage::ActionLog {
+                    request_id,
+                    tool,
+                    status,
+                    timestamp,
+                } => Some((request_id, tool.as_str(), *status, *timestamp)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].1, "click_element");
+        assert_eq!(logs[0].2, ActionLogStatus::Started);
+        assert_eq!(logs[1].2, ActionLogStatus::Succeeded);
+        assert_eq!(logs[0].0, logs[1].0);
+        assert!(logs[0].3 > 0 && logs[0].3 <= logs[1].3);
+    }
+
+    #[tokio::test]
+    async fn test_get_console_logs_dispatch() {
+        use crate::config::AppConfig;
+        use crate::models::ws::{ActionResult, ConsoleLevel};
+        use tokio::sync::mpsc;
+
+        let state = Arc::new(AppState::new(AppConfig::for_tests()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        state.register_connection("s1".to_string(), tx).await;
+
+        // The extension: returns more errors than the backend passes on
+        let browser = async {
+            while let Some(msg) = rx.recv().await {
+                let WsMessage::ActionRequest {
+                    request_id,
+                    command,
+                } = msg
+                else {
+                    continue;
+                };
+                assert!(matches!(
+                    command,
+                    ActionCommand::GetConsoleLogs { ref levels } if levels == &[ConsoleLevel::Error]
+                ));
+                let entries: Vec<_> = (0..MAX_CONSOLE_LOGS + 5)
+                    .map(|i| {
+                        serde_json::json!({
+                            "level": "error",
+                            "message": format!("Error {}", i),
+                            "source": null,
+                            "age_ms": 100
+                        })
+                    })
+                    .collect();
+                let result = ActionResult {
+                    request_id: request_id.clone(),
+                    success: true,
+                    error: None,
+                    data: Some(serde_json::json!({ "entries": entries, "omitted": 0 })),
+                };
+                state.complete_pending_action(&request_id, result).await;
+                break;
+            }
+        };
+        let tool = WsGetConsoleLogsTool::new(state.clone(), "s1".to_string());
+        let args = GetConsoleLogsArgs {
+            levels: vec![ConsoleLevel::Error],
+        };
+        let (summary, ()) = tokio::join!(tool.call(args), browser);
+        let summary = summary.unwrap();
+
+        assert!(summary.contains("(5 older messages omitted)"));
+        assert!(!summary.contains("Error 4\n"));
+        assert!(summary.contains("[ERROR] Error 5\n"));
+        assert!(summary.ends_with("[ERROR] Error 54"));
+    }
+
+    #[test]
+    fn test_validate_history_delta() {
+        assert!(validate_history_delta(-2).is_ok());
+        assert!(validate_history_delta(MAX_HISTORY_DELTA).is_ok());
+        assert!(validate_history_delta(0).is_err());
+        assert!(validate_history_delta(MAX_HISTORY_DELTA + 1).is_err());
+        assert!(validate_history_delta(-MAX_HISTORY_DELTA - 1).is_err());
+        assert!(validate_history_delta(i32::MIN).is_err());
+        assert!(validate_history_delta(i32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_check_editable() {
+        let roles = HashMap::from([(1, "textbox".to_string()), (2, "button".to_string())]);
+        assert!(check_editable(&roles, 1).is_ok());
+        // Unknown refs are validated by the extension instead
+        assert!(check_editable(&roles, 3).is_ok());
+
+        let err = check_editable(&roles, 2).unwrap_err();
+        assert_eq!(err, "Element with ref 2 is not editable (role: button)");
+    }
+
+    #[test]
+    fn test_allowed_domains() {
+        let allowed = vec!["example.com".to_string(), "*.corp.co.id".to_string()];
+        let check = |url| check_navigation_domain(&allowed, &[], url);
+        assert!(check("https://example.com/login").is_ok());
+        assert!(check("https://corp.co.id").is_ok());
+        assert!(check("https://hr.corp.co.id/cuti").is_ok());
+        assert!(check("hr.corp.co.id").is_ok());
+
+        assert!(check("https://www.example.com").is_err());
+        assert!(check("https://evilcorp.co.id").is_err());
+        assert!(check("https://example.com.evil.io").is_err());
+
+        // Unset: everything is allowed
+        assert!(check_navigation_domain(&[], &[], "https://anything.io").is_ok());
+    }
+
+    #[test]
+    fn test_blocked_domains() {
+        let blocked = vec!["admin.corp.co.id".to_string(), "*.facebook.com".to_string()];
+        let check = |url| check_navigation_domain(&[], &blocked, url);
+        assert!(check("https://facebook.com").is_err());
+        assert!(check("https://m.facebook.com/feed").is_err());
+        assert!(check("ADMIN.corp.co.id/users").is_err());
+        assert!(check("https://m.facebook.com./").is_err());
+        assert!(check("https://facebook.com.").is_err());
+        let err = check("https://www.facebook.com").unwrap_err();
+        assert!(err.contains("Navigation to this site (www.facebook.com) is blocked"));
+
+        assert!(check("https://corp.co.id").is_ok());
+        assert!(check("https://hr.corp.co.id").is_ok());
+        assert!(check("https://notfacebook.com").is_ok());
+
+        // Blocking wins over an allow list that includes the host
+        let allowed = vec!["*.corp.co.id".to_string()];
+        assert!(check_navigation_domain(&allowed, &blocked, "https://hr.corp.co.id").is_ok());
+        assert!(check_navigation_domain(&allowed, &blocked, "https://hr.corp.co.id.").is_ok());
+        let err =
+            check_navigation_domain(&allowed, &blocked, "https://admin.corp.co.id").unwrap_err();
+        assert!(err.contains("blocked"));
+    }
+
+    #[test]
+    fn test_check_checkable() {
+        let roles = HashMap::from([(1, "checkbox".to_string()), (2, "textbox".to_string())]);
+        assert!(check_checkable(&roles, 1).is_ok());
+        assert!(check_checkable(&roles, 3).is_ok());
+        assert!(check_checkable(&roles, 2).is_err());
+    }
+
+    #[test]
+    fn test_upload_payload() {
+        assert_eq!(
+            upload_payload("data:application/pdf;base64,JVBERi0="),
+            Ok((Some("application/pdf".to_string()), "JVBERi0=".to_string()))
+        );
+        assert_eq!(
+            upload_payload("JVBERi0=\n"),
+            Ok((None, "JVBERi0=".to_string()))
+        );
+        assert!(upload_payload("not base64!").is_err());
+
+        let too_large = BASE64_STANDARD.encode(vec![0u8; MAX_UPLOAD_BYTES + 1]);
+        let err = upload_payload(&too_large).unwrap_err();
+        assert!(err.contains("at most"), "{}", err);
+    }
+
+    #[test]
+    fn test_capture_region_command() {
+        let args = CaptureRegionArgs {
+            x: 0,
+            y: 1200,
+            w: 800,
+            h: 600,
+            question: None,
+        };
+        assert!(matches!(
+            capture_region_command(args),
+            Ok(ActionCommand::CaptureRegion {
+                x: 0,
+                y: 1200,
+                w: 800,
+                h: 600
+            })
+        ));
+
+        let region = |x, y, w, h| CaptureRegionArgs {
+            x,
+            y,
+            w,
+            h,
+            question: None,
+        };
+        assert!(capture_region_command(region(0, 0, 0, 100)).is_err());
+        assert!(capture_region_command(region(0, 0, 100, 0)).is_err());
+        assert!(capture_region_command(region(-1, 0, 100, 100)).is_err());
+        assert!(capture_region_command(region(0, 0, MAX_REGION_SIZE + 1, 100)).is_err());
+    }
+
+    #[test]
+    fn test_screenshot_image_requires_image_data() {
+        let data = serde_json::json!({ "image": "data:image/jpeg;base64,/9j/abc" });
+        assert_eq!(
+            screenshot_image(Some(&data)),
+            Ok("data:image/jpeg;base64,/9j/abc")
+        );
+        assert!(screenshot_image(Some(&serde_json::json!({ "image": "" }))).is_err());
+        assert!(screenshot_image(None).is_err());
+    }
+
+    #[test]
+    fn test_formatted_elements_are_stable() {
+        let elements: serde_json::Value = serde_json::from_str(
+            r#"{"elements":[{"id":1,"role":"button","name":"Masuk"},{"id":2,"role":"textbox","name":"Email"}]}"#,
+        )
+        .unwrap();
+        let reordered: serde_json::Value = serde_json::from_str(
+            r#"{"elements":[{"name":"Masuk","role":"button","id":1},{"role":"textbox","name":"Email","id":2}]}"#,
+        )
+        .unwrap();
+
+        let formatted = format_interactive_elements(Some(&elements), 100).unwrap();
+        assert_eq!(
+            formatted,
+            format_interactive_elements(Some(&elements), 100).unwrap()
+        );
+        assert_eq!(
+            formatted,
+            format_interactive_elements(Some(&reordered), 100).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_interactive_elements_become_a_readable_list() {
+        let elements = serde_json::json!({ "elements": [
+            { "id": 1, "role": "button", "name": "Masuk", "tag": "BUTTON", "bounds": { "x": 0 } },
+            { "id": 2, "role": "checkbox", "name": "Ingat saya", "checked": true }
+        ]});
+        assert_eq!(
+            format_interactive_elements(Some(&elements), 100).unwrap(),
+            "[ref=1] button \"Masuk\"\n[ref=2] checkbox \"Ingat saya\" (checked)"
+        );
+
+        // Data shaped for another command, or none at all, is an error
+        let malformed = serde_json::json!({ "count": 2 });
+        let err = format_interactive_elements(Some(&malformed), 100).unwrap_err();
+        assert!(err.contains("Invalid interactive elements data"), "{}", err);
+        assert!(format_interactive_elements(None, 100).is_err());
+    }
+
+    #[test]
+    fn test_interactive_elements_are_capped() {
+        // Every third element is disabled
+        let elements: Vec<_> = (1..=9)
+            .map(|id| {
+                serde_json::json!({
+                    "id": id,
+                    "role": "link",
+                    "name": format!("Produk {}", id),
+                    "disabled": id % 3 == 0
+                })
+            })
+            .collect();
+        let data = serde_json::json!({ "elements": elements });
+
+        let text = format_interactive_elements(Some(&data), 7).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 8);
+        // All six enabled elements, then the first disabled one, in document order
+        let refs: Vec<&str> = lines[..7]
+            .iter()
+            .map(|line| line.split(']').next().unwrap())
+            .collect();
+        assert_eq!(
+            refs,
+            [
+                "[ref=1", "[ref=2", "[ref=3", "[ref=4", "[ref=5", "[ref=7", "[ref=8"
+            ]
+        );
+        assert_eq!(lines[2], "[ref=3] link \"Produk 3\" (disabled)");
+        assert!(lines[7].contains("showing 7 of 9 elements"));
+
+        let text = format_interactive_elements(Some(&data), 9).unwrap();
+        assert_eq!(text.lines().count(), 9);
+        assert!(!text.contains("truncated"));
+    }
+
+    #[test]
+    fn test_page_content_is_passed_as_plain_text() {
+        let command = ActionCommand::GetPageContent { max_length: None };
+        let page = serde_json::json!({
+            "title": "Berita",
+            "url": "https://example.com/berita",
+            "text": "Harga \"kopi\" naik.\nBaca selengkapnya"
+        });
+        let text = format_action_data(&command, Some(&page)).unwrap();
+        assert_eq!(
+            text,
+            "Title: Berita\nURL: https://example.com/berita\n\nHarga \"kopi\" naik.\nBaca selengkapnya"
+        );
+        assert!(!text.contains("Some("));
+        assert!(!text.contains("String("));
+
+        assert_eq!(
+            action_error(Some("Element not found".to_string())),
+            "Element not found"
+        );
+        assert!(!action_error(None).contains("None"));
+    }
+
+    #[test]
+    fn test_commands_without_data_just_succeed() {
+        let command = ActionCommand::ClickElement { ref_id: 3 };
+        assert_eq!(format_action_data(&command, None).unwrap(), "Success.");
+
+        let command = ActionCommand::CountElements {
+            selector: "tr".to_string(),
+        };
+        let count = serde_json::json!({ "count": 12 });
+        assert_eq!(
+            format_action_data(&command, Some(&count)).unwrap(),
+            "12 element(s) match the selector."
+        );
+    }
+
+    #[test]
+    fn test_screenshot_is_queued_as_image() {
+        let image = "data:image/jpeg;base64,/9j/4AAQSkZJRg";
+        let pending = pending_screenshot(
+            "capture_region",
+            "Apa isi grafik ini?",
+            image,
+            ImageMediaType::PNG,
+        );
+
+        assert_eq!(
+            pending.caption,
+            "Screenshot from capture_region (Apa isi grafik ini?):"
+        );
+        assert!(matches!(pending.media_type, ImageMediaType::JPEG));
+        assert_eq!(pending.data, "/9j/4AAQSkZJRg");
+    }
+
+    #[test]
+    fn test_format_performance() {
+        let metrics = PerformanceMetrics {
+            available: true,
+            ttfb_ms: Some(250),
+            load_ms: Some(1500),
+            ..PerformanceMetrics::default()
+        };
+        let summary = format_performance(&metrics);
+        assert!(summary.contains("TTFB: 250 ms"));
+        assert!(summary.contains("LCP: n/a"));
+
+        let summary = format_performance(&PerformanceMetrics::default());
+        assert!(summary.contains("not available"));
+    }
+
+    #[test]
+    fn test_format_live_regions() {
+        let live: LiveRegions = serde_json::from_value(serde_json::json!({
+            "regions": [{ "politeness": "polite", "role": "status", "text": "" }],
+            "announcements": [{ "text": "Profile saved", "politeness": "polite", "age_ms": 1500 }]
+        }))
+        .unwrap();
+        let summary = format_live_regions(&live);
+        assert!(summary.contains("[polite, role=status] (empty)"));
+        assert!(summary.contains("1.5s ago (polite): Profile saved"));
+
+        let summary = format_live_regions(&LiveRegions::default());
+        assert!(summary.contains("no ARIA live regions"));
+    }
+
+    #[test]
+    fn test_format_console_logs() {
+        let logs: ConsoleLogs = serde_json::from_value(serde_json::json!({
+            "entries": [
+                { "level": "warn", "message": "Deprecated API", "age_ms": 4000 },
+                {
+                    "level": "error",
+                    "message": "TypeError: cart is undefined",
+                    "source": "app.js:12:5",
+                    "age_ms": 500
+                }
+            ],
+            "omitted": 2
+        }))
+        .unwrap();
+        let summary = format_console_logs(&logs);
+        assert!(summary.contains("(2 older messages omitted)"));
+        assert!(summary.contains("4.0s ago [WARN] Deprecated API"));
+        assert!(summary.contains("0.5s ago [ERROR] TypeError: cart is undefined (app.js:12:5)"));
+
+        let summary = format_console_logs(&ConsoleLogs::default());
+        assert!(summary.contains("No matching console messages"));
+    }
+}
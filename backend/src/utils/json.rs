@@ -0,0 +1,159 @@
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+
+use crate::error::{ErrorBody, JsonLocation, REQUEST_ID_HEADER, request_id};
+
+/// JSON body extractor like `axum::Json`, whose rejection says what is wrong
+/// with the body and where, so client integration bugs are easy to find.
+///
+/// Malformed JSON gets a 400 `malformed_json` error; JSON that doesn't match
+/// the expected fields gets a 422 `invalid_request_body` error.
+pub struct ApiJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json_content_type(req.headers()) {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected request with `Content-Type: application/json`",
+            )
+                .into_response());
+        }
+
+        let request_id = request_id(req.headers());
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        serde_json::from_slice(&bytes)
+            .map(ApiJson)
+            .map_err(|err| rejection(&bytes, &err, request_id))
+    }
+}
+
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .is_some_and(|mime| mime == "application/json" || mime.ends_with("+json"))
+}
+
+fn rejection(body: &[u8], err: &serde_json::Error, request_id: String) -> Response {
+    let (status, error) = match err.classify() {
+        Category::Syntax | Category::Eof => (StatusCode::BAD_REQUEST, "malformed_json"),
+        Category::Data | Category::Io => (StatusCode::UNPROCESSABLE_ENTITY, "invalid_request_body"),
+    };
+    let location = (err.line() > 0).then(|| JsonLocation {
+        line: err.line(),
+        column: err.column(),
+        offset: byte_offset(body, err.line(), err.column()),
+    });
+    tracing::info!("Rejected request {} body: {}", request_id, err);
+
+    let header = HeaderValue::from_str(&request_id).ok();
+    let body = ErrorBody {
+        error: error.to_string(),
+        message: err.to_string(),
+        request_id,
+        location,
+        retry_after_secs: None,
+    };
+    let mut response = (status, axum::Json(body)).into_response();
+    if let Some(value) = header {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Byte offset of serde_json's 1-based `line` and `column`, clamped to the body.
+fn byte_offset(body: &[u8], line: usize, column: usize) -> usize {
+    let line_start: usize = body
+        .split(|&b| b == b'\n')
+        .take(line.saturating_sub(1))
+        .map(|line| line.len() + 1)
+        .sum();
+    (line_start + column.saturating_sub(1)).min(body.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, routing::post};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct Query {
+        #[allow(dead_code)]
+        query: String,
+    }
+
+    async fn post_body(body: &str) -> (StatusCode, serde_json::Value) {
+        let app = Router::new().route(
+            "/run",
+            post(|ApiJson(_): ApiJson<Query>| async { StatusCode::OK }),
+        );
+        let response = app
+            .oneshot(
+                Request::post("/run")
+                    .header("content-type", "application/json")
+                    .header(REQUEST_ID_HEADER, "req-1")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_reports_location() {
+        let (status, body) = post_body("{\n  \"query\": }").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "malformed_json");
+        assert_eq!(body["request_id"], "req-1");
+        assert!(body["message"].as_str().unwrap().contains("expected value"));
+        assert_eq!(body["line"], 2);
+        assert_eq!(body["column"], 12);
+        // The offending `}`
+        assert_eq!(body["offset"], 13);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_fields_are_unprocessable() {
+        let (status, body) = post_body(r#"{"query": 5}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"], "invalid_request_body");
+        assert!(body["message"].as_str().unwrap().contains("invalid type"));
+
+        let (status, _) = post_body(r#"{"query": "halo"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_byte_offset() {
+        let body = b"{\n  \"a\": }";
+        assert_eq!(byte_offset(body, 1, 1), 0);
+        assert_eq!(byte_offset(body, 2, 8), 9);
+        assert_eq!(body[9], b'}');
+        assert_eq!(byte_offset(body, 9, 9), body.len());
+    }
+}
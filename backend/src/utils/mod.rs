@@ -1 +1,3 @@
+pub mod json;
+pub mod response;
 pub mod streaming;
@@ -0,0 +1,44 @@
+/// Separator placed between the configured prefix/suffix and the model output.
+pub const WRAP_SEPARATOR: &str = "\n\n";
+
+/// Wraps model output with the operator-configured prefix and suffix.
+///
+/// This happens outside the model so the text can't be suppressed by prompt injection.
+pub fn wrap_response(text: &str, prefix: Option<&str>, suffix: Option<&str>) -> String {
+    let mut out = String::new();
+    if let Some(prefix) = prefix {
+        out.push_str(prefix);
+        out.push_str(WRAP_SEPARATOR);
+    }
+    out.push_str(text);
+    if let Some(suffix) = suffix {
+        out.push_str(WRAP_SEPARATOR);
+        out.push_str(suffix);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_response_with_prefix_and_suffix() {
+        let wrapped = wrap_response(
+            "Buy low, sell high.",
+            Some("This is not financial advice."),
+            Some("Consult a professional."),
+        );
+        assert_eq!(
+            wrapped,
+            "This is not financial advice.\n\nBuy low, sell high.\n\nConsult a professional."
+        );
+    }
+
+    #[test]
+    fn test_wrap_response_without_config_is_unchanged() {
+        assert_eq!(wrap_response("Hello", None, None), "Hello");
+        assert_eq!(wrap_response("Hello", Some("Note"), None), "Note\n\nHello");
+        assert_eq!(wrap_response("Hello", None, Some("Bye")), "Hello\n\nBye");
+    }
+}
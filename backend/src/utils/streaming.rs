@@ -1,5 +1,9 @@
-use axum::response::sse::Event;
+use axum::BoxError;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures::Stream;
 use std::convert::Infallible;
+use std::time::Duration;
 
 #[allow(dead_code)]
 pub fn sse_event(data: &str) -> Result<Event, Infallible> {
@@ -10,3 +14,193 @@ pub fn sse_event(data: &str) -> Result<Event, Infallible> {
 pub fn sse_done() -> Result<Event, Infallible> {
     Ok(Event::default().data("[DONE]"))
 }
+
+/// How streamed model text is regrouped before it is sent as SSE events.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StreamGranularity {
+    /// Forward text as the model returns it
+    #[default]
+    Token,
+    /// Send whole sentences: up to `.`, `!` or `?` followed by whitespace, or a newline
+    Sentence,
+    /// Send chunks of this many characters
+    Chars(usize),
+}
+
+/// Regroups streamed text to a `StreamGranularity`, holding back the
+/// remainder until more text arrives or it is flushed.
+pub struct ChunkBuffer {
+    granularity: StreamGranularity,
+    pending: String,
+}
+
+impl ChunkBuffer {
+    pub fn new(granularity: StreamGranularity) -> Self {
+        Self {
+            granularity,
+            pending: String::new(),
+        }
+    }
+
+    /// Adds streamed text and returns the chunks that are now complete.
+    pub fn push(&mut self, text: &str) -> Vec<String> {
+        if self.granularity == StreamGranularity::Token {
+            return (!text.is_empty())
+                .then(|| text.to_string())
+                .into_iter()
+                .collect();
+        }
+
+        self.pending.push_str(text);
+        let mut chunks = Vec::new();
+        match self.granularity {
+            StreamGranularity::Token => {}
+            StreamGranularity::Sentence => {
+                if let Some(end) = sentence_end(&self.pending) {
+                    chunks.push(self.take_until(end));
+                }
+            }
+            StreamGranularity::Chars(size) => {
+                while size > 0 && self.pending.chars().count() >= size {
+                    let end = self
+                        .pending
+                        .char_indices()
+                        .nth(size)
+                        .map_or(self.pending.len(), |(i, _)| i);
+                    chunks.push(self.take_until(end));
+                }
+            }
+        }
+        chunks
+    }
+
+    /// Returns the text held back, e.g. before a tool event or at the end of the stream.
+    pub fn flush(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending))
+    }
+
+    fn take_until(&mut self, end: usize) -> String {
+        let rest = self.pending.split_off(end);
+        std::mem::replace(&mut self.pending, rest)
+    }
+}
+
+/// Byte index just past the last sentence boundary in `text`, including the
+/// whitespace after it. A `.` at the very end may be a decimal point, so it
+/// waits for the next character.
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut end = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\n' {
+            end = Some(i + 1);
+        } else if matches!(c, '.' | '!' | '?') {
+            match chars.peek() {
+                Some(&(j, next)) if next.is_whitespace() => end = Some(j + next.len_utf8()),
+                _ => {}
+            }
+        }
+    }
+    end
+}
+
+/// Builds an SSE response that sends a `:keepalive` comment after every
+/// `interval` without an event, so proxies don't close a long agent run as
+/// idle before its first output. A zero interval disables keepalives.
+pub fn sse_response<S, E>(stream: S, interval: Duration) -> Response
+where
+    S: Stream<Item = Result<Event, E>> + Send + 'static,
+    E: Into<BoxError>,
+{
+    let sse = Sse::new(stream);
+    if interval.is_zero() {
+        sse.into_response()
+    } else {
+        sse.keep_alive(KeepAlive::new().interval(interval).text("keepalive"))
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn rebuffer(granularity: StreamGranularity, tokens: &[&str]) -> Vec<String> {
+        let mut buffer = ChunkBuffer::new(granularity);
+        let mut chunks: Vec<String> = tokens.iter().flat_map(|t| buffer.push(t)).collect();
+        chunks.extend(buffer.flush());
+        chunks
+    }
+
+    #[test]
+    fn test_small_tokens_are_coalesced_into_sentences() {
+        let tokens = [
+            "Har", "ga", "nya ", "Rp", "1.", "5 juta", ". Stok", " ada! ", "Mau", " beli?",
+        ];
+        assert_eq!(
+            rebuffer(StreamGranularity::Sentence, &tokens),
+            vec!["Harganya Rp1.5 juta. ", "Stok ada! ", "Mau beli?"]
+        );
+    }
+
+    #[test]
+    fn test_small_tokens_are_coalesced_into_fixed_chunks() {
+        let tokens = ["Ha", "lo", " ", "dun", "ia", "!"];
+        assert_eq!(
+            rebuffer(StreamGranularity::Chars(4), &tokens),
+            vec!["Halo", " dun", "ia!"]
+        );
+        // Multi-byte characters count as one
+        assert_eq!(
+            rebuffer(StreamGranularity::Chars(2), &["é", "àü"]),
+            vec!["éà", "ü"]
+        );
+    }
+
+    #[test]
+    fn test_token_granularity_forwards_chunks_unchanged() {
+        let tokens = ["Ha", "lo", ""];
+        assert_eq!(
+            rebuffer(StreamGranularity::Token, &tokens),
+            vec!["Ha", "lo"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_stream_gets_keepalives_before_first_event() {
+        let slow = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Ok::<_, Infallible>(Event::default().data("hasil"))
+        });
+
+        let response = sse_response(slow, Duration::from_millis(50));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        let first_data = body.find("data: hasil").unwrap();
+        let keepalives = body[..first_data].matches(":keepalive").count();
+        assert!(
+            keepalives >= 2,
+            "expected keepalives before data, got {:?}",
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zero_interval_disables_keepalives() {
+        let slow = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok::<_, Infallible>(Event::default().data("hasil"))
+        })
+        .boxed();
+
+        let response = sse_response(slow, Duration::ZERO);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "data: hasil\n\n");
+    }
+}
@@ -34,6 +34,30 @@ mod tests {
         assert_eq!(elements[1].role, "link");
     }
 
+    #[test]
+    fn test_context_flags_keep_interactive_elements() {
+        let json_data = r#"{
+          "query": "Click Edit Profile",
+          "session_id": "s1",
+          "page_content": "Profile page",
+          "image": "data:image/png;base64,iVBORw0KGgo=",
+          "use_content": false,
+          "use_screenshot": false,
+          "interactive_elements": [
+            {"id": 1, "role": "button", "name": "Edit Profile"}
+          ]
+        }"#;
+
+        let mut req: AgentRequest = serde_json::from_str(json_data).unwrap();
+        req.strip_unused_context();
+
+        // The page text and screenshot go, but the agent can still act on the scan
+        assert_eq!(req.page_content, None);
+        assert_eq!(req.image, None);
+        assert_eq!(req.interactive_elements.as_ref().map(Vec::len), Some(1));
+        assert!(req.wants_tools());
+    }
+
     #[test]
     fn test_format_interactive_elements_for_prompt() {
         // This test defines the expected formatting for interactive elements in the prompt.
@@ -54,6 +54,97 @@ fn test_agent_request_deserialization_defaults() {
     assert_eq!(req.custom_instruction, None);
 }
 
+#[test]
+fn test_agent_request_empty_image_is_none() {
+    let json = r#"{"query": "Hello", "image": ""}"#;
+    let req: AgentRequest = serde_json::from_str(json).expect("Should accept empty image");
+    assert_eq!(req.image, None);
+
+    let json = r#"{"query": "Hello", "image": "   "}"#;
+    let req: AgentRequest =
+        serde_json::from_str(json).expect("Should accept whitespace-only image");
+    assert_eq!(req.image, None);
+
+    let json = r#"{"query": "Hello", "image": null}"#;
+    let req: AgentRequest = serde_json::from_str(json).expect("Should accept null image");
+    assert_eq!(req.image, None);
+}
+
+#[test]
+fn test_agent_request_tools_flag() {
+    // Tools are used by default when a session is available
+    let json = r#"{"query": "Hello", "session_id": "s1"}"#;
+    let req: AgentRequest = serde_json::from_str(json).unwrap();
+    assert!(req.tools);
+    assert!(req.wants_tools());
+
+    // Fast path: tools disabled even though a session is available
+    let json = r#"{"query": "Hello", "session_id": "s1", "tools": false}"#;
+    let req: AgentRequest = serde_json::from_str(json).unwrap();
+    assert!(!req.wants_tools());
+
+    // No session means no tools
+    let json = r#"{"query": "Hello"}"#;
+    let req: AgentRequest = serde_json::from_str(json).unwrap();
+    assert!(!req.wants_tools());
+}
+
+#[test]
+fn test_agent_request_selection() {
+    let json = r#"{"query": "jelaskan ini", "selection": "borrow checker"}"#;
+    let req: AgentRequest = serde_json::from_str(json).unwrap();
+    assert_eq!(req.selection, Some("borrow checker".to_string()));
+
+    // An empty selection means nothing is selected
+    let json = r#"{"query": "jelaskan ini", "selection": ""}"#;
+    let req: AgentRequest = serde_json::from_str(json).unwrap();
+    assert_eq!(req.selection, None);
+}
+
+#[test]
+fn test_agent_request_incognito() {
+    let json = r#"{"query": "Hello"}"#;
+    let req: AgentRequest = serde_json::from_str(json).unwrap();
+    assert!(!req.incognito);
+
+    let json = r#"{"query": "Hello", "incognito": true}"#;
+    let req: AgentRequest = serde_json::from_str(json).unwrap();
+    assert!(req.incognito);
+}
+
+#[test]
+fn test_agent_request_context_flags() {
+    let payload = json!({
+        "query": "jelaskan ini",
+        "page_content": "Isi halaman",
+        "selection": "borrow checker",
+        "image": "data:image/png;base64,AAAA"
+    });
+
+    // Everything is kept by default
+    let mut req: AgentRequest = serde_json::from_value(payload.clone()).unwrap();
+    req.strip_unused_context();
+    assert!(req.page_content.is_some());
+    assert!(req.selection.is_some());
+    assert!(req.image.is_some());
+
+    let mut without_content = payload.clone();
+    without_content["use_content"] = json!(false);
+    let mut req: AgentRequest = serde_json::from_value(without_content).unwrap();
+    req.strip_unused_context();
+    assert_eq!(req.page_content, None);
+    assert_eq!(req.selection, None);
+    assert!(req.image.is_some());
+
+    let mut without_screenshot = payload;
+    without_screenshot["use_screenshot"] = json!(false);
+    let mut req: AgentRequest = serde_json::from_value(without_screenshot).unwrap();
+    req.strip_unused_context();
+    assert_eq!(req.image, None);
+    assert!(req.page_content.is_some());
+    assert!(req.selection.is_some());
+}
+
 #[tokio::test]
 async fn test_agent_run_endpoint_mock() {
     // This test verifies that the axum router correctly deserializes the AgentRequest
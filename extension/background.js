@@ -2,6 +2,7 @@
 
 const BACKEND_WS_URL = 'wss://deafening-dyna-malas-eae50695.koyeb.app/ws';
 const CONTEXT_UPDATE_INTERVAL = 5000; // 5 seconds
+const WS_CHUNK_SIZE = 256 * 1024; // Characters per chunked_data frame
 
 let ws = null;
 let contextInterval = null;
@@ -9,6 +10,7 @@ let isConnected = false;
 let lastTabId = null;
 let lastUrl = null;
 let wsSessionId = null;
+let reconnectDelay = null; // Set by the backend's reconnect message
 
 // Setup side panel behavior
 chrome.sidePanel
@@ -33,8 +35,10 @@ function connectWebSocket() {
       isConnected = false;
       wsSessionId = null;
       stopContextUpdates();
-      // Attempt reconnection after 5 seconds
-      setTimeout(connectWebSocket, 5000);
+      // Attempt reconnection after the delay the backend asked for, or 5 seconds
+      const delay = reconnectDelay ?? 5000;
+      reconnectDelay = null;
+      setTimeout(connectWebSocket, delay);
     };
 
     ws.onerror = (error) => {
@@ -47,6 +51,8 @@ function connectWebSocket() {
 
         if (message.type === 'session_init') {
           wsSessionId = message.data.session_id;
+        } else if (message.type === 'reconnect') {
+          reconnectDelay = message.data.after_ms;
         } else if (message.type === 'action_request') {
           const { request_id, command } = message.data;
           // Forward action to sidepanel for UI display and execution
@@ -61,7 +67,7 @@ function connectWebSocket() {
               data: result.data || null,
             },
           });
-          ws.send(response);
+          sendChunked(request_id, response);
         }
       } catch (e) {
         console.error('[Background] Error processing message:', e);
@@ -73,6 +79,40 @@ function connectWebSocket() {
   }
 }
 
+/**
+ * Splits a message too large for one frame (e.g. a screenshot result) into
+ * chunked_data frames, which the backend joins back into the message
+ */
+function splitIntoChunks(text, chunkSize = WS_CHUNK_SIZE) {
+  const chunks = [];
+  let start = 0;
+  while (start < text.length) {
+    let end = Math.min(start + chunkSize, text.length);
+    // Don't split a surrogate pair across chunks
+    const last = text.charCodeAt(end - 1);
+    if (end < text.length && last >= 0xd800 && last <= 0xdbff) end -= 1;
+    chunks.push(text.substring(start, end));
+    start = end;
+  }
+  return chunks;
+}
+
+function sendChunked(requestId, text) {
+  if (text.length <= WS_CHUNK_SIZE) {
+    ws.send(text);
+    return;
+  }
+  const chunks = splitIntoChunks(text);
+  chunks.forEach((data, seq) => {
+    ws.send(
+      JSON.stringify({
+        type: 'chunked_data',
+        data: { request_id: requestId, seq, total: chunks.length, data },
+      })
+    );
+  });
+}
+
 // Start periodic context updates
 function startContextUpdates() {
   if (contextInterval) return;
@@ -109,6 +149,9 @@ async function dispatchToActiveTab(command) {
       return { success: false, error: 'No active tab found' };
     }
 
+    // Console capture starts with the agent's first command on a page
+    await installConsoleHook(tab.id);
+
     // For navigation commands, we handle specially
     if (command.type === 'navigate_to') {
       try {
@@ -123,36 +166,51 @@ async function dispatchToActiveTab(command) {
       }
 
       // Wait for page to finish loading (max 10 seconds)
-      const waitForLoad = () =>
-        new Promise((resolve) => {
-          let resolved = false;
-          const timeout = setTimeout(() => {
-            if (!resolved) {
-              resolved = true;
-              resolve();
-            }
-          }, 10000);
-
-          const listener = (tabId, changeInfo) => {
-            if (tabId === tab.id && changeInfo.status === 'complete') {
-              chrome.tabs.onUpdated.removeListener(listener);
-              clearTimeout(timeout);
-              if (!resolved) {
-                resolved = true;
-                resolve();
-              }
-            }
-          };
-          chrome.tabs.onUpdated.addListener(listener);
-        });
-
-      await waitForLoad();
+      await waitForTabLoad(tab.id);
       // Give the page a moment to fully render
       await new Promise((r) => setTimeout(r, 500));
 
       return { success: true, data: { navigated_to: command.url } };
     }
 
+    if (command.type === 'go_history') {
+      await chrome.scripting.executeScript({
+        target: { tabId: tab.id },
+        func: (delta) => history.go(delta),
+        args: [command.delta],
+      });
+
+      // history.go may not trigger a full load (bfcache), so use a shorter wait
+      await waitForTabLoad(tab.id, 5000);
+      const updatedTab = await chrome.tabs.get(tab.id);
+
+      return { success: true, data: { url: updatedTab.url } };
+    }
+
+    if (command.type === 'handle_dialog') {
+      return await handleDialog(tab.id, command);
+    }
+
+    if (command.type === 'capture_region') {
+      return await captureRegion(tab.id, command);
+    }
+
+    if (command.type === 'capture_element') {
+      return await captureElement(tab.id, command);
+    }
+
+    if (command.type === 'full_page_screenshot') {
+      // Pages too long to stitch fall back to the viewport
+      const fullPage = await captureFullPage(tab.id).catch(() => null);
+      const image =
+        fullPage ||
+        (await chrome.tabs.captureVisibleTab(null, {
+          format: 'jpeg',
+          quality: 50,
+        }));
+      return { success: true, data: { image, full_page: !!fullPage } };
+    }
+
     // For other commands, send to content script with injection fallback
     return await sendToContentScript(tab.id, command);
   } catch (e) {
@@ -160,6 +218,185 @@ async function dispatchToActiveTab(command) {
   }
 }
 
+/**
+ * Injects console-hook.js into the page's own world so get_console_logs can
+ * see its errors. The hook installs itself once per page; pages that can't
+ * be scripted (chrome://, the Web Store) are skipped.
+ * @param {number} tabId The tab the agent is working on
+ */
+async function installConsoleHook(tabId) {
+  try {
+    await chrome.scripting.executeScript({
+      target: { tabId },
+      files: ['console-hook.js'],
+      world: 'MAIN',
+    });
+  } catch {
+    // Not scriptable; get_console_logs reports no messages
+  }
+}
+
+/**
+ * Accepts or dismisses a pending native dialog via the debugger protocol.
+ * Content scripts can't reach dialogs since they block the page's JS.
+ * @param {number} tabId The tab showing the dialog
+ * @param {Object} command The handle_dialog command
+ * @returns {Promise<Object>} The ActionResult object
+ */
+async function handleDialog(tabId, command) {
+  const target = { tabId };
+  await chrome.debugger.attach(target, '1.3');
+  try {
+    await chrome.debugger.sendCommand(target, 'Page.handleJavaScriptDialog', {
+      accept: command.action === 'accept',
+      promptText: command.prompt_text || undefined,
+    });
+    return { success: true, data: { action: command.action } };
+  } catch (e) {
+    return { success: false, error: `No dialog to handle: ${e.message}` };
+  } finally {
+    await chrome.debugger.detach(target).catch(() => {});
+  }
+}
+
+/**
+ * Captures a region of the page given in page coordinates, scrolling it
+ * into view first and cropping the visible capture in the offscreen document.
+ * @param {number} tabId The tab to capture
+ * @param {Object} command The capture_region command
+ * @returns {Promise<Object>} The ActionResult object
+ */
+async function captureRegion(tabId, command) {
+  const metrics = await chrome.tabs.sendMessage(tabId, { action: 'getMetrics' });
+  const originalScroll = await chrome.tabs.sendMessage(tabId, {
+    action: 'getScrollPosition',
+  });
+
+  try {
+    await chrome.tabs.sendMessage(tabId, {
+      action: 'scrollTo',
+      x: command.x,
+      y: command.y,
+    });
+    await new Promise((r) => setTimeout(r, 300));
+
+    // The page may not scroll all the way near its edges
+    const scroll = await chrome.tabs.sendMessage(tabId, {
+      action: 'getScrollPosition',
+    });
+    const dataUrl = await chrome.tabs.captureVisibleTab(null, {
+      format: 'png',
+    });
+
+    await setupOffscreenDocument('offscreen.html');
+    const response = await chrome.runtime.sendMessage({
+      target: 'offscreen',
+      type: 'crop',
+      dataUrl,
+      x: command.x - scroll.x,
+      y: command.y - scroll.y,
+      width: Math.min(command.w, metrics.viewportWidth),
+      height: Math.min(command.h, metrics.viewportHeight),
+      viewportWidth: metrics.viewportWidth,
+    });
+
+    return { success: true, data: { image: response.result } };
+  } finally {
+    await chrome.tabs
+      .sendMessage(tabId, {
+        action: 'scrollTo',
+        x: originalScroll?.x || 0,
+        y: originalScroll?.y || 0,
+      })
+      .catch(() => {});
+  }
+}
+
+/**
+ * Captures a single element by ref, scrolling it into view first and
+ * cropping the visible capture to its box. Parts of elements larger than the
+ * viewport are clipped, reported as `clipped`.
+ * @param {number} tabId The tab to capture
+ * @param {Object} command The capture_element command
+ * @returns {Promise<Object>} The ActionResult object
+ */
+async function captureElement(tabId, command) {
+  const originalScroll = await chrome.tabs.sendMessage(tabId, {
+    action: 'getScrollPosition',
+  });
+
+  try {
+    const rect = await chrome.tabs.sendMessage(tabId, {
+      action: 'getElementRect',
+      ref: command.ref,
+    });
+    if (rect.error) {
+      return { success: false, error: rect.error };
+    }
+    await new Promise((r) => setTimeout(r, 300));
+
+    const dataUrl = await chrome.tabs.captureVisibleTab(null, {
+      format: 'png',
+    });
+
+    await setupOffscreenDocument('offscreen.html');
+    const response = await chrome.runtime.sendMessage({
+      target: 'offscreen',
+      type: 'crop',
+      dataUrl,
+      x: rect.x,
+      y: rect.y,
+      width: rect.width,
+      height: rect.height,
+      viewportWidth: rect.viewportWidth,
+    });
+
+    return {
+      success: true,
+      data: { image: response.result, clipped: rect.clipped },
+    };
+  } finally {
+    await chrome.tabs
+      .sendMessage(tabId, {
+        action: 'scrollTo',
+        x: originalScroll?.x || 0,
+        y: originalScroll?.y || 0,
+      })
+      .catch(() => {});
+  }
+}
+
+/**
+ * Resolves once the tab reports a 'complete' status or the timeout elapses
+ * @param {number} tabId The tab to watch
+ * @param {number} timeoutMs Maximum time to wait
+ * @returns {Promise<void>}
+ */
+function waitForTabLoad(tabId, timeoutMs = 10000) {
+  return new Promise((resolve) => {
+    let resolved = false;
+    const timeout = setTimeout(() => {
+      if (!resolved) {
+        resolved = true;
+        chrome.tabs.onUpdated.removeListener(listener);
+        resolve();
+      }
+    }, timeoutMs);
+
+    const listener = (updatedTabId, changeInfo) => {
+      if (updatedTabId === tabId && changeInfo.status === 'complete') {
+        chrome.tabs.onUpdated.removeListener(listener);
+        clearTimeout(timeout);
+        if (!resolved) {
+          resolved = true;
+          resolve();
+        }
+      }
+    };
+    chrome.tabs.onUpdated.addListener(listener);
+  });
+}
+
 /**
  * Sends a command to content script with injection fallback
  */
@@ -368,6 +605,10 @@ chrome.runtime.onMessage.addListener((message, sender, sendResponse) => {
       setTimeout(() => sendResponse({ success: true }), 100);
     });
     return true;
+  } else if (message.action === 'dispatchTabCommand') {
+    // Tab-level commands (history, captures) need the tabs API
+    dispatchToActiveTab(message.command).then(sendResponse);
+    return true;
   } else if (message.action === 'updateContextNoScreenshot') {
     captureAndSendContext({ forceUpdate: true, skipScreenshot: true }).then(
       () => {
@@ -0,0 +1,59 @@
+// Injected by background.js into the page's own JavaScript world (content
+// scripts can't see the page's console) once the agent starts working on a
+// tab. Forwards console errors and warnings and uncaught errors to
+// content.js, which keeps them for get_console_logs
+
+(() => {
+  if (window.__browserAiConsoleHook) return;
+  window.__browserAiConsoleHook = true;
+
+  const SOURCE = 'browser-ai-console';
+  const LEVELS = ['error', 'warn'];
+  const MAX_MESSAGE_LENGTH = 500;
+
+  // Only strings and errors are read; other objects aren't serialized
+  function describe(value) {
+    if (value instanceof Error) return value.stack || String(value);
+    if (typeof value === 'string') return value;
+    if (value === null || typeof value !== 'object') return String(value);
+    return Array.isArray(value) ? '[Array]' : '[Object]';
+  }
+
+  function forward(level, message, source) {
+    window.postMessage(
+      {
+        source: SOURCE,
+        level,
+        message: message.substring(0, MAX_MESSAGE_LENGTH),
+        location: source || null,
+      },
+      '*'
+    );
+  }
+
+  for (const level of LEVELS) {
+    const original = console[level];
+    if (typeof original !== 'function') continue;
+    console[level] = function (...args) {
+      try {
+        forward(level, args.map(describe).join(' '));
+      } catch {
+        // Never break the page's logging
+      }
+      return original.apply(this, args);
+    };
+  }
+
+  window.addEventListener('error', (event) => {
+    // Resource load failures (img, script) have no message
+    if (!event.message) return;
+    const location = event.filename
+      ? `${event.filename}:${event.lineno}:${event.colno}`
+      : null;
+    forward('error', `Uncaught ${event.message}`, location);
+  });
+
+  window.addEventListener('unhandledrejection', (event) => {
+    forward('error', `Unhandled rejection: ${describe(event.reason)}`);
+  });
+})();
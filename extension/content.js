@@ -175,6 +175,194 @@ function extractPageContent(maxLength = MAX_TEXT_LENGTH) {
   };
 }
 
+// --- Performance Metrics ---
+
+// LCP is only reported to observers, so record it from page load on
+let latestLcp = null;
+try {
+  new PerformanceObserver((list) => {
+    const entries = list.getEntries();
+    if (entries.length) latestLcp = entries[entries.length - 1];
+  }).observe({ type: 'largest-contentful-paint', buffered: true });
+} catch {
+  // LCP not supported in this browser/context
+}
+
+/**
+ * Collects Navigation Timing metrics and the slowest resources, in ms.
+ * Metrics the browser doesn't provide are null.
+ */
+function getPerformanceMetrics() {
+  const round = (value) =>
+    typeof value === 'number' && value > 0 ? Math.round(value) : null;
+  const entries = (type) =>
+    typeof performance.getEntriesByType === 'function'
+      ? performance.getEntriesByType(type)
+      : [];
+  const [nav] = entries('navigation');
+  const resources = entries('resource');
+
+  return {
+    available: !!nav,
+    ttfb_ms: nav ? round(nav.responseStart - nav.requestStart) : null,
+    dom_content_loaded_ms: nav ? round(nav.domContentLoadedEventEnd) : null,
+    load_ms: nav ? round(nav.loadEventEnd) : null,
+    lcp_ms: latestLcp ? round(latestLcp.startTime) : null,
+    transfer_size: nav ? nav.transferSize || null : null,
+    resource_count: resources.length,
+    slowest_resources: [...resources]
+      .sort((a, b) => b.duration - a.duration)
+      .slice(0, 5)
+      .map((r) => ({
+        url: r.name.substring(0, 200),
+        type: r.initiatorType,
+        duration_ms: Math.round(r.duration),
+      })),
+  };
+}
+
+// --- ARIA Live Regions ---
+
+const LIVE_REGION_SELECTOR =
+  '[aria-live]:not([aria-live="off"]), [role="alert"], [role="status"], [role="log"]';
+const MAX_LIVE_ANNOUNCEMENTS = 20;
+
+// Toasts often disappear before the agent looks, so keep what was announced
+const liveAnnouncements = [];
+
+function liveRegionText(region) {
+  const text = region.innerText || region.textContent || '';
+  return text.trim().substring(0, 500);
+}
+
+function livePoliteness(region) {
+  const live = region.getAttribute('aria-live');
+  if (live) return live;
+  return region.getAttribute('role') === 'alert' ? 'assertive' : 'polite';
+}
+
+function recordLiveAnnouncement(region) {
+  const text = liveRegionText(region);
+  if (!text) return;
+  const last = liveAnnouncements[liveAnnouncements.length - 1];
+  if (last && last.text === text) return;
+  liveAnnouncements.push({
+    text,
+    politeness: livePoliteness(region),
+    at: Date.now(),
+  });
+  if (liveAnnouncements.length > MAX_LIVE_ANNOUNCEMENTS) {
+    liveAnnouncements.shift();
+  }
+}
+
+let liveRegionObserver = null;
+
+/**
+ * Starts recording announcements, on the agent's first command to the page
+ * rather than at load, so pages the agent never works on aren't observed
+ */
+function observeLiveRegions() {
+  if (liveRegionObserver) return;
+  liveRegionObserver = new MutationObserver((mutations) => {
+    const changed = new Set();
+    for (const mutation of mutations) {
+      const target =
+        mutation.target.nodeType === Node.ELEMENT_NODE
+          ? mutation.target
+          : mutation.target.parentElement;
+      const region = target && target.closest(LIVE_REGION_SELECTOR);
+      if (region) changed.add(region);
+      // Regions inserted with content (e.g. a toast) announce it too
+      for (const node of mutation.addedNodes) {
+        if (node.nodeType !== Node.ELEMENT_NODE) continue;
+        if (node.matches(LIVE_REGION_SELECTOR)) changed.add(node);
+        node
+          .querySelectorAll(LIVE_REGION_SELECTOR)
+          .forEach((el) => changed.add(el));
+      }
+    }
+    changed.forEach(recordLiveAnnouncement);
+  });
+  liveRegionObserver.observe(document.documentElement, {
+    childList: true,
+    subtree: true,
+    characterData: true,
+  });
+}
+
+/**
+ * Returns the current text of live regions and the recent announcements,
+ * most recent last
+ */
+function getLiveRegions() {
+  const now = Date.now();
+  const regions = [...document.querySelectorAll(LIVE_REGION_SELECTOR)];
+  return {
+    regions: regions.map((region) => ({
+      politeness: livePoliteness(region),
+      role: region.getAttribute('role'),
+      text: liveRegionText(region),
+    })),
+    announcements: liveAnnouncements.map((a) => ({
+      text: a.text,
+      politeness: a.politeness,
+      age_ms: now - a.at,
+    })),
+  };
+}
+
+// --- Console Logs ---
+
+const CONSOLE_HOOK_SOURCE = 'browser-ai-console';
+const MAX_CONSOLE_BUFFER = 200;
+const MAX_CONSOLE_LOGS = 50;
+
+// Filled by console-hook.js, which background.js injects into the page's world
+const consoleBuffer = [];
+
+window.addEventListener('message', (event) => {
+  const entry = event.data;
+  if (
+    event.source !== window ||
+    !entry ||
+    entry.source !== CONSOLE_HOOK_SOURCE
+  ) {
+    return;
+  }
+  consoleBuffer.push({
+    level: String(entry.level),
+    message: String(entry.message),
+    source: entry.location ? String(entry.location) : null,
+    at: Date.now(),
+  });
+  if (consoleBuffer.length > MAX_CONSOLE_BUFFER) {
+    consoleBuffer.shift();
+  }
+});
+
+/**
+ * Returns the most recent console messages of the given levels (all when
+ * empty), oldest first, capped at MAX_CONSOLE_LOGS
+ */
+function getConsoleLogs(levels) {
+  const now = Date.now();
+  const wanted = Array.isArray(levels) && levels.length > 0 ? levels : null;
+  const matching = consoleBuffer.filter(
+    (entry) => !wanted || wanted.includes(entry.level)
+  );
+  const entries = matching.slice(-MAX_CONSOLE_LOGS);
+  return {
+    entries: entries.map((entry) => ({
+      level: entry.level,
+      message: entry.message,
+      source: entry.source,
+      age_ms: now - entry.at,
+    })),
+    omitted: matching.length - entries.length,
+  };
+}
+
 // --- DomTreeGenerator (Snapshot System) ---
 
 // Store ref-to-element mapping from last snapshot
@@ -357,12 +545,15 @@ function generateSnapshot(limit = 300) {
     if (isInteractive(element) && isElementVisible(element)) {
       const id = refId++;
       refToElementMap.set(id, element);
+      const checked = getCheckedState(element);
       tree.push({
         id: id,
         role: getElementRole(element),
         name: getAccessibleName(element),
         tag: element.tagName,
         bounds: getElementBounds(element),
+        ...(checked !== null && { checked }),
+        ...(isDisabled(element) && { disabled: true }),
       });
     }
 
@@ -588,6 +779,113 @@ function cleanupDebugListeners() {
   clearTimeout(debugRefreshTimeout);
 }
 
+/**
+ * Returns whether a checkbox, radio or switch is checked, or null for other elements
+ */
+function getCheckedState(el) {
+  if (el.tagName === 'INPUT' && (el.type === 'checkbox' || el.type === 'radio')) {
+    return el.checked;
+  }
+  const role = el.getAttribute('role');
+  if (['checkbox', 'radio', 'switch', 'menuitemcheckbox'].includes(role)) {
+    return el.getAttribute('aria-checked') === 'true';
+  }
+  return null;
+}
+
+/**
+ * Builds a File from base64 content sent by the backend
+ */
+function base64ToFile(data, name, type) {
+  const binary = atob(data);
+  const bytes = new Uint8Array(binary.length);
+  for (let i = 0; i < binary.length; i++) {
+    bytes[i] = binary.charCodeAt(i);
+  }
+  return new File([bytes], name, { type: type || '' });
+}
+
+/**
+ * Whether the element is disabled, natively or with aria-disabled
+ */
+function isDisabled(el) {
+  return el.disabled === true || el.getAttribute('aria-disabled') === 'true';
+}
+
+/**
+ * Finds the element or its nearest ancestor that can scroll in the requested
+ * direction, or null when only the window scrolls
+ */
+function findScrollableContainer(el, dx, dy) {
+  const scrollable = /(auto|scroll|overlay)/;
+  for (let node = el; node && node !== document.body; node = node.parentElement) {
+    const style = window.getComputedStyle(node);
+    const scrollsY =
+      dy !== 0 && scrollable.test(style.overflowY) && node.scrollHeight > node.clientHeight;
+    const scrollsX =
+      dx !== 0 && scrollable.test(style.overflowX) && node.scrollWidth > node.clientWidth;
+    if (scrollsY || scrollsX) return node;
+  }
+  return null;
+}
+
+/**
+ * Scrolls the element into view and returns its visible box in viewport
+ * coordinates, clipped to the viewport, or an error when it can't be captured
+ */
+function getElementRect(ref) {
+  const element = refToElementMap.get(ref);
+  if (!element) return { error: `Element with ref ${ref} not found` };
+
+  element.scrollIntoView({
+    block: 'center',
+    inline: 'center',
+    behavior: 'instant',
+  });
+  const rect = element.getBoundingClientRect();
+  const x = Math.max(rect.left, 0);
+  const y = Math.max(rect.top, 0);
+  const width = Math.min(rect.right, window.innerWidth) - x;
+  const height = Math.min(rect.bottom, window.innerHeight) - y;
+  if (width <= 0 || height <= 0) {
+    return { error: `Element with ref ${ref} is not visible` };
+  }
+
+  return {
+    x,
+    y,
+    width,
+    height,
+    viewportWidth: window.innerWidth,
+    clipped: width < rect.width || height < rect.height,
+  };
+}
+
+/**
+ * Checks whether an element accepts typed text
+ */
+function isEditableElement(el) {
+  if (el.isContentEditable) return true;
+  if (el.disabled || el.readOnly) return false;
+  if (el.tagName === 'TEXTAREA') return true;
+  if (el.tagName === 'INPUT') {
+    const nonTextTypes = [
+      'button',
+      'submit',
+      'reset',
+      'checkbox',
+      'radio',
+      'file',
+      'image',
+      'hidden',
+      'range',
+      'color',
+    ];
+    return !nonTextTypes.includes(el.type);
+  }
+  return false;
+}
+
 /**
  * Executes a browser action command
  */
@@ -620,17 +918,147 @@ function executeAction(command) {
             error: `Element with ref ${command.ref} not found`,
           };
         }
+        if (!isEditableElement(element)) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} is not editable`,
+          };
+        }
+        element.focus();
+        if (element.isContentEditable) {
+          element.textContent = command.text;
+        } else {
+          element.value = command.text;
+        }
+        element.dispatchEvent(new Event('input', { bubbles: true }));
+        element.dispatchEvent(new Event('change', { bubbles: true }));
+        return { success: true };
+      }
+
+      case 'clear_field': {
+        highlightElement(command.ref);
+        const element = refToElementMap.get(command.ref);
+        if (!element) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} not found`,
+          };
+        }
+        if (!isEditableElement(element)) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} is not editable`,
+          };
+        }
         element.focus();
-        element.value = command.text;
+        if (element.isContentEditable) {
+          element.textContent = '';
+        } else {
+          element.value = '';
+        }
         element.dispatchEvent(new Event('input', { bubbles: true }));
         element.dispatchEvent(new Event('change', { bubbles: true }));
         return { success: true };
       }
 
+      case 'set_checked': {
+        highlightElement(command.ref);
+        const element = refToElementMap.get(command.ref);
+        if (!element) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} not found`,
+          };
+        }
+        const current = getCheckedState(element);
+        if (current === null) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} is not a checkbox or radio button`,
+          };
+        }
+        if (current === command.checked) {
+          return { success: true, data: { checked: current, changed: false } };
+        }
+        if (!command.checked && element.type === 'radio') {
+          return {
+            success: false,
+            error: 'A radio button cannot be unchecked; check another option instead',
+          };
+        }
+        // Click rather than setting .checked so the page's handlers run
+        element.click();
+        const checked = getCheckedState(element);
+        if (checked !== command.checked) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} did not change state`,
+          };
+        }
+        return { success: true, data: { checked, changed: true } };
+      }
+
+      case 'upload_file': {
+        highlightElement(command.ref);
+        const element = refToElementMap.get(command.ref);
+        if (!element) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} not found`,
+          };
+        }
+        if (element.tagName !== 'INPUT' || element.type !== 'file') {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} is not a file input`,
+          };
+        }
+        const file = base64ToFile(
+          command.data_base64,
+          command.file_name,
+          command.mime_type
+        );
+        const transfer = new DataTransfer();
+        transfer.items.add(file);
+        element.files = transfer.files;
+        // Let the page's handlers see the file as if the user picked it
+        element.dispatchEvent(new Event('input', { bubbles: true }));
+        element.dispatchEvent(new Event('change', { bubbles: true }));
+        return { success: true, data: { file_name: file.name, size: file.size } };
+      }
+
       case 'scroll_to':
         window.scrollTo(command.x, command.y);
         return { success: true };
 
+      case 'scroll_element': {
+        const element = refToElementMap.get(command.ref);
+        if (!element) {
+          return {
+            success: false,
+            error: `Element with ref ${command.ref} not found`,
+          };
+        }
+        const dx = command.dx || 0;
+        const dy = command.dy || 0;
+        const container = findScrollableContainer(element, dx, dy);
+        if (!container) {
+          return {
+            success: false,
+            error: `No scrollable container found around ref ${command.ref}; use scroll_to to scroll the page`,
+          };
+        }
+        container.scrollBy(dx, dy);
+        return {
+          success: true,
+          data: {
+            scroll_top: container.scrollTop,
+            scroll_left: container.scrollLeft,
+            at_bottom: container.scrollTop + container.clientHeight >= container.scrollHeight,
+          },
+        };
+      }
+
       case 'get_page_content': {
         const maxLength = command.max_length || 15000;
         const content = extractPageContent(maxLength);
@@ -655,6 +1083,53 @@ function executeAction(command) {
         };
       }
 
+      case 'count_elements': {
+        const count = document.querySelectorAll(command.selector).length;
+        return {
+          success: true,
+          data: { count },
+        };
+      }
+
+      case 'get_selection': {
+        const selection = window.getSelection();
+        const text = selection ? selection.toString().trim() : '';
+        if (!text) {
+          return { success: true, data: { text: '', context: null } };
+        }
+
+        // Surrounding block text helps resolve what "this" refers to
+        let container = selection.getRangeAt(0).commonAncestorContainer;
+        if (container.nodeType !== Node.ELEMENT_NODE) {
+          container = container.parentElement;
+        }
+        const block =
+          container.closest('p, li, td, pre, blockquote, article, section') ||
+          container;
+
+        return {
+          success: true,
+          data: {
+            text,
+            context: {
+              tag: block.tagName.toLowerCase(),
+              text: (block.innerText || block.textContent || '')
+                .trim()
+                .substring(0, 1000),
+            },
+          },
+        };
+      }
+
+      case 'get_performance':
+        return { success: true, data: getPerformanceMetrics() };
+
+      case 'get_live_regions':
+        return { success: true, data: getLiveRegions() };
+
+      case 'get_console_logs':
+        return { success: true, data: getConsoleLogs(command.levels) };
+
       default:
         return {
           success: false,
@@ -702,6 +1177,8 @@ chrome.runtime.onMessage.addListener((message, sender, sendResponse) => {
       x: window.scrollX,
       y: window.scrollY,
     });
+  } else if (message.action === 'getElementRect') {
+    sendResponse(getElementRect(message.ref));
   } else if (message.action === 'scrollTo') {
     window.scrollTo(message.x, message.y);
     sendResponse({ success: true });
@@ -709,6 +1186,7 @@ chrome.runtime.onMessage.addListener((message, sender, sendResponse) => {
     const success = highlightElement(message.ref);
     sendResponse({ success });
   } else if (message.action === 'execute') {
+    observeLiveRegions();
     const result = executeAction(message.command);
     sendResponse(result);
   } else if (message.action === 'toggleDebug') {
@@ -17,6 +17,29 @@ chrome.runtime.onMessage.addListener((message, sender, sendResponse) => {
     };
     img.src = dataUrl;
     return true; // Keep channel open
+  } else if (message.type === 'crop') {
+    // Region is in CSS pixels; the capture is in device pixels
+    const { dataUrl, x, y, width, height, viewportWidth } = message;
+    const img = new Image();
+    img.onload = () => {
+      const scale = img.width / viewportWidth;
+      canvas.width = width * scale;
+      canvas.height = height * scale;
+      ctx.drawImage(
+        img,
+        x * scale,
+        y * scale,
+        width * scale,
+        height * scale,
+        0,
+        0,
+        canvas.width,
+        canvas.height
+      );
+      sendResponse({ result: canvas.toDataURL('image/jpeg', 0.8) });
+    };
+    img.src = dataUrl;
+    return true; // Keep channel open
   } else if (message.type === 'getResult') {
     // Return base64 JPEG
     const result = canvas.toDataURL('image/jpeg', 0.8);
@@ -219,6 +219,21 @@ function generateId() {
   return Date.now().toString(36) + Math.random().toString(36).substr(2);
 }
 
+// Stream errors are text, or JSON like { error: 'quota_exceeded', retry_after_secs }
+function describeStreamError(value) {
+  try {
+    const error = JSON.parse(value);
+    if (error.error === 'quota_exceeded') {
+      return error.retry_after_secs
+        ? `Kuota API Gemini habis. Coba lagi dalam ${error.retry_after_secs} detik.`
+        : 'Kuota API Gemini habis. Coba lagi nanti.';
+    }
+    return error.message || value;
+  } catch {
+    return value;
+  }
+}
+
 // Session Manager
 const SessionManager = {
   getSessions() {
@@ -381,6 +396,7 @@ document.addEventListener('DOMContentLoaded', () => {
   const settingScreenshotDefault = document.getElementById(
     'setting-screenshot-default'
   );
+  const settingsError = document.getElementById('settings-error');
 
   // Input & Attach Elements
   const imageUploadInput = document.getElementById('image-upload');
@@ -777,7 +793,14 @@ document.addEventListener('DOMContentLoaded', () => {
       if (!response.ok) {
         hideTyping();
         const errorText = await response.text();
-        throw new Error(errorText || 'Failed to get response');
+        let errorMessage = errorText;
+        try {
+          // Backend errors use { error, message, request_id }
+          errorMessage = JSON.parse(errorText).message || errorText;
+        } catch {
+          // Not JSON, use the raw text
+        }
+        throw new Error(errorMessage || 'Failed to get response');
       }
 
       // All responses are SSE streams (readSSEStream is always available)
@@ -862,7 +885,7 @@ document.addEventListener('DOMContentLoaded', () => {
           console.error('Stream error:', event.value);
           // Show error in bubble if we have one
           if (bubbleDiv) {
-            fullText += `\n\n⚠️ ${event.value}`;
+            fullText += `\n\n⚠️ ${describeStreamError(event.value)}`;
             updateAssistantBubble(bubbleDiv, fullText);
           }
         } else if (event.type === 'done') {
@@ -968,6 +991,31 @@ document.addEventListener('DOMContentLoaded', () => {
     setInterval(checkConnectionStatus, 5000);
   }
 
+  /**
+   * Checks an instruction with the backend's /instruction/validate.
+   * Returns why it was rejected, or null when it is valid, empty, or can't
+   * be checked right now (the backend checks it again on every request).
+   */
+  async function validateInstruction(instruction) {
+    if (!instruction.trim()) return null;
+    try {
+      const response = await fetch(
+        'https://deafening-dyna-malas-eae50695.koyeb.app/instruction/validate',
+        {
+          method: 'POST',
+          headers: { 'Content-Type': 'application/json' },
+          body: JSON.stringify({ instruction }),
+          signal: AbortSignal.timeout(5000),
+        }
+      );
+      if (response.status !== 400) return null;
+      const error = await response.json();
+      return error.message || 'Instruksi ditolak';
+    } catch {
+      return null;
+    }
+  }
+
   // Check connection status
   async function checkConnectionStatus() {
     try {
@@ -1046,6 +1094,7 @@ document.addEventListener('DOMContentLoaded', () => {
           currentSession && currentSession.customInstruction
             ? currentSession.customInstruction
             : '';
+        settingsError.hidden = true;
         openModal(settingsModal);
       } catch (e) {
         console.error('Error opening settings:', e);
@@ -1055,7 +1104,22 @@ document.addEventListener('DOMContentLoaded', () => {
 
   closeSettingsBtn.addEventListener('click', () => closeModal(settingsModal));
 
-  saveSettingsBtn.addEventListener('click', () => {
+  saveSettingsBtn.addEventListener('click', async () => {
+    // The backend rejects every request carrying an invalid instruction, so
+    // refuse to save one
+    settingsError.hidden = true;
+    for (const instruction of [
+      globalInstructionInput.value,
+      sessionInstructionInput.value,
+    ]) {
+      const problem = await validateInstruction(instruction);
+      if (problem) {
+        settingsError.textContent = problem;
+        settingsError.hidden = false;
+        return;
+      }
+    }
+
     const settings = {
       globalInstruction: globalInstructionInput.value,
       screenshotDefault: settingScreenshotDefault.checked,
@@ -1413,12 +1477,36 @@ document.addEventListener('DOMContentLoaded', () => {
           detail: `"${action.text}" → ref: ${action.ref}`,
           icon: ACTION_ICONS.type,
         };
+      case 'clear_field':
+        return {
+          label: 'Kosongkan Field',
+          detail: `ref: ${action.ref}`,
+          icon: ACTION_ICONS.type,
+        };
+      case 'set_checked':
+        return {
+          label: action.checked ? 'Centang' : 'Hapus Centang',
+          detail: `ref: ${action.ref}`,
+          icon: ACTION_ICONS.click,
+        };
+      case 'upload_file':
+        return {
+          label: 'Unggah File',
+          detail: `${action.file_name} → ref: ${action.ref}`,
+          icon: ACTION_ICONS.action,
+        };
       case 'scroll_to':
         return {
           label: 'Scroll',
           detail: `posisi (${action.x}, ${action.y})`,
           icon: ACTION_ICONS.scroll,
         };
+      case 'scroll_element':
+        return {
+          label: 'Scroll Area',
+          detail: `ref: ${action.ref}, geser (${action.dx || 0}, ${action.dy || 0})`,
+          icon: ACTION_ICONS.scroll,
+        };
       case 'get_page_content':
         return {
           label: 'Membaca Halaman',
@@ -1431,6 +1519,66 @@ document.addEventListener('DOMContentLoaded', () => {
           detail: 'mengambil elemen interaktif...',
           icon: ACTION_ICONS.search,
         };
+      case 'go_history':
+        return {
+          label: action.delta < 0 ? 'Kembali' : 'Maju',
+          detail: `${Math.abs(action.delta)} halaman`,
+          icon: ACTION_ICONS.navigate,
+        };
+      case 'handle_dialog':
+        return {
+          label: action.action === 'accept' ? 'Terima Dialog' : 'Tutup Dialog',
+          detail: action.prompt_text ? `"${action.prompt_text}"` : 'dialog browser',
+          icon: ACTION_ICONS.click,
+        };
+      case 'full_page_screenshot':
+        return {
+          label: 'Tangkap Halaman Penuh',
+          detail: action.question || 'seluruh halaman',
+          icon: ACTION_ICONS.read,
+        };
+      case 'capture_region':
+        return {
+          label: 'Tangkap Area',
+          detail: `${action.w}×${action.h} di (${action.x}, ${action.y})`,
+          icon: ACTION_ICONS.read,
+        };
+      case 'capture_element':
+        return {
+          label: 'Tangkap Elemen',
+          detail: `ref: ${action.ref}`,
+          icon: ACTION_ICONS.read,
+        };
+      case 'get_performance':
+        return {
+          label: 'Mengukur Performa',
+          detail: 'waktu muat halaman...',
+          icon: ACTION_ICONS.search,
+        };
+      case 'get_live_regions':
+        return {
+          label: 'Membaca Pengumuman',
+          detail: 'region aria-live...',
+          icon: ACTION_ICONS.read,
+        };
+      case 'get_console_logs':
+        return {
+          label: 'Membaca Konsol',
+          detail: 'pesan & error JavaScript...',
+          icon: ACTION_ICONS.read,
+        };
+      case 'get_selection':
+        return {
+          label: 'Membaca Seleksi',
+          detail: 'mengambil teks yang dipilih...',
+          icon: ACTION_ICONS.read,
+        };
+      case 'count_elements':
+        return {
+          label: 'Menghitung Elemen',
+          detail: action.selector,
+          icon: ACTION_ICONS.search,
+        };
       default:
         return {
           label: 'Aksi',
@@ -1525,6 +1673,15 @@ document.addEventListener('DOMContentLoaded', () => {
     };
   }
 
+  // Commands that need the tabs API are executed by the background worker
+  const TAB_COMMANDS = [
+    'go_history',
+    'handle_dialog',
+    'capture_region',
+    'capture_element',
+    'full_page_screenshot',
+  ];
+
   async function performAction(action) {
     // Show executing status
     const statusMessage = renderActionStatus(action, 'executing');
@@ -1549,6 +1706,11 @@ document.addEventListener('DOMContentLoaded', () => {
           limit: action.limit,
         });
         response = { success: true, data: snapshot };
+      } else if (TAB_COMMANDS.includes(action.type)) {
+        response = await chrome.runtime.sendMessage({
+          action: 'dispatchTabCommand',
+          command: action,
+        });
       } else {
         response = await chrome.tabs.sendMessage(tab.id, {
           action: 'execute',
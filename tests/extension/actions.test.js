@@ -103,6 +103,234 @@ describe('Action Executor', () => {
     expect(changeEventSpy).toHaveBeenCalled();
   });
 
+  test('type_text should reject non-editable elements', () => {
+    document.body.innerHTML = '<button id="target">Submit</button>';
+
+    // Generate snapshot to populate refToElementMap
+    generateSnapshot();
+
+    const command = { type: 'type_text', ref: 1, text: 'Hello' };
+    const result = executeAction(command);
+
+    expect(result.success).toBe(false);
+    expect(result.error).toContain('not editable');
+  });
+
+  test('clear_field should empty the value and dispatch events', () => {
+    document.body.innerHTML = '<input type="text" id="target" value="old">';
+    const input = document.getElementById('target');
+    const inputEventSpy = jest.fn();
+    input.addEventListener('input', inputEventSpy);
+
+    // Generate snapshot to populate refToElementMap
+    generateSnapshot();
+
+    const result = executeAction({ type: 'clear_field', ref: 1 });
+
+    expect(result.success).toBe(true);
+    expect(input.value).toBe('');
+    expect(inputEventSpy).toHaveBeenCalled();
+  });
+
+  test('clear_field should reject non-editable elements', () => {
+    document.body.innerHTML = '<button id="target">Submit</button>';
+
+    // Generate snapshot to populate refToElementMap
+    generateSnapshot();
+
+    const result = executeAction({ type: 'clear_field', ref: 1 });
+
+    expect(result.success).toBe(false);
+    expect(result.error).toContain('not editable');
+  });
+
+  test('set_checked should set a checkbox to the requested state', () => {
+    document.body.innerHTML = '<input type="checkbox" id="terms">';
+    const checkbox = document.getElementById('terms');
+    const changeSpy = jest.fn();
+    checkbox.addEventListener('change', changeSpy);
+
+    // Generate snapshot to populate refToElementMap
+    const snapshot = generateSnapshot();
+    expect(snapshot.tree[0].checked).toBe(false);
+
+    const result = executeAction({ type: 'set_checked', ref: 1, checked: true });
+    expect(result.success).toBe(true);
+    expect(result.data).toEqual({ checked: true, changed: true });
+    expect(checkbox.checked).toBe(true);
+    expect(changeSpy).toHaveBeenCalled();
+
+    // Already in the requested state: nothing changes
+    const again = executeAction({ type: 'set_checked', ref: 1, checked: true });
+    expect(again.data).toEqual({ checked: true, changed: false });
+    expect(checkbox.checked).toBe(true);
+  });
+
+  test('set_checked should reject elements that are not checkable', () => {
+    document.body.innerHTML = '<button id="target">Submit</button>';
+
+    // Generate snapshot to populate refToElementMap
+    generateSnapshot();
+
+    const result = executeAction({ type: 'set_checked', ref: 1, checked: true });
+
+    expect(result.success).toBe(false);
+    expect(result.error).toContain('not a checkbox');
+  });
+
+  test('upload_file should attach the file to a file input', () => {
+    document.body.innerHTML = `
+      <input type="file" id="cv">
+      <input type="text" id="name">
+    `;
+    const input = document.getElementById('cv');
+    // jsdom has no DataTransfer and only accepts a real FileList
+    global.DataTransfer = class {
+      constructor() {
+        this.files = [];
+        this.items = { add: (file) => this.files.push(file) };
+      }
+    };
+    Object.defineProperty(input, 'files', { value: null, writable: true });
+    const changeSpy = jest.fn();
+    input.addEventListener('change', changeSpy);
+
+    // Generate snapshot to populate refToElementMap
+    generateSnapshot();
+
+    const result = executeAction({
+      type: 'upload_file',
+      ref: 1,
+      file_name: 'cv.txt',
+      mime_type: 'text/plain',
+      data_base64: btoa('halo'),
+    });
+    expect(result.success).toBe(true);
+    expect(result.data).toEqual({ file_name: 'cv.txt', size: 4 });
+    expect(input.files[0].name).toBe('cv.txt');
+    expect(input.files[0].type).toBe('text/plain');
+    expect(changeSpy).toHaveBeenCalled();
+
+    const notFile = executeAction({
+      type: 'upload_file',
+      ref: 2,
+      file_name: 'cv.txt',
+      data_base64: btoa('halo'),
+    });
+    expect(notFile.success).toBe(false);
+    expect(notFile.error).toContain('not a file input');
+    delete global.DataTransfer;
+  });
+
+  test('get_performance should report navigation timing', () => {
+    const original = performance.getEntriesByType;
+    performance.getEntriesByType = jest.fn((type) =>
+        type === 'navigation'
+          ? [
+              {
+                requestStart: 100,
+                responseStart: 350,
+                domContentLoadedEventEnd: 900,
+                loadEventEnd: 1500,
+                transferSize: 2048,
+              },
+            ]
+          : [
+              { name: 'https://cdn.test/a.js', initiatorType: 'script', duration: 40 },
+              { name: 'https://cdn.test/b.png', initiatorType: 'img', duration: 400 },
+            ],
+      );
+
+    const result = executeAction({ type: 'get_performance' });
+
+    expect(result.success).toBe(true);
+    expect(result.data).toMatchObject({
+      available: true,
+      ttfb_ms: 250,
+      dom_content_loaded_ms: 900,
+      load_ms: 1500,
+      resource_count: 2,
+    });
+    expect(result.data.slowest_resources[0].url).toBe('https://cdn.test/b.png');
+    performance.getEntriesByType = original;
+  });
+
+  test('get_performance should handle missing metrics', () => {
+    const original = performance.getEntriesByType;
+    performance.getEntriesByType = jest.fn(() => []);
+
+    const result = executeAction({ type: 'get_performance' });
+
+    expect(result.success).toBe(true);
+    expect(result.data.available).toBe(false);
+    expect(result.data.ttfb_ms).toBeNull();
+    performance.getEntriesByType = original;
+  });
+
+  test('get_live_regions should report regions and announcements', async () => {
+    document.body.innerHTML =
+      '<div role="status" id="status"></div><div aria-live="off">Ignored</div>';
+    document.getElementById('status').textContent = 'Profile saved';
+    await Promise.resolve();
+
+    // A toast inserted with its text is announced even after it is removed
+    const toast = document.createElement('div');
+    toast.setAttribute('role', 'alert');
+    toast.textContent = 'Session expiring';
+    document.body.appendChild(toast);
+    await Promise.resolve();
+    toast.remove();
+
+    const result = executeAction({ type: 'get_live_regions' });
+
+    expect(result.success).toBe(true);
+    expect(result.data.regions).toEqual([
+      { politeness: 'polite', role: 'status', text: 'Profile saved' },
+    ]);
+    const announced = result.data.announcements.map((a) => [
+      a.text,
+      a.politeness,
+    ]);
+    expect(announced).toEqual(
+      expect.arrayContaining([
+        ['Profile saved', 'polite'],
+        ['Session expiring', 'assertive'],
+      ])
+    );
+  });
+
+  test('get_console_logs should filter by level and cap the messages', () => {
+    const log = (level, message, location = null) =>
+      window.dispatchEvent(
+        new MessageEvent('message', {
+          source: window,
+          data: { source: 'browser-ai-console', level, message, location },
+        })
+      );
+    log('error', 'Uncaught TypeError: cart is undefined', 'app.js:12:5');
+    log('log', 'Rendered');
+    for (let i = 0; i < 60; i++) log('warn', `Slow frame ${i}`);
+
+    const errors = executeAction({
+      type: 'get_console_logs',
+      levels: ['error'],
+    });
+    expect(errors.success).toBe(true);
+    expect(errors.data.omitted).toBe(0);
+    expect(errors.data.entries).toEqual([
+      expect.objectContaining({
+        level: 'error',
+        message: 'Uncaught TypeError: cart is undefined',
+        source: 'app.js:12:5',
+      }),
+    ]);
+
+    const all = executeAction({ type: 'get_console_logs', levels: [] });
+    expect(all.data.entries).toHaveLength(50);
+    expect(all.data.omitted).toBe(12);
+    expect(all.data.entries[49].message).toBe('Slow frame 59');
+  });
+
   test('scroll_to should call window.scrollTo', () => {
     const command = { type: 'scroll_to', x: 0, y: 500 };
     const result = executeAction(command);
@@ -111,6 +339,38 @@ describe('Action Executor', () => {
     expect(global.scrollTo).toHaveBeenCalledWith(0, 500);
   });
 
+  test('scroll_element should scroll the nearest scrollable container', () => {
+    document.body.innerHTML =
+      '<div id="results"><ul><li><a href="#1">Result 1</a></li></ul></div>';
+    const results = document.getElementById('results');
+    Object.defineProperty(results, 'scrollHeight', { value: 2000 });
+    Object.defineProperty(results, 'clientHeight', { value: 400 });
+    results.scrollBy = jest.fn();
+    const baseStyle = window.getComputedStyle;
+    window.getComputedStyle = (el) => ({
+      ...baseStyle(el),
+      overflowX: 'visible',
+      overflowY: el === results ? 'auto' : 'visible',
+    });
+
+    // The link inside the panel is the anchor; the panel itself has no ref
+    generateSnapshot();
+    const result = executeAction({ type: 'scroll_element', ref: 1, dy: 300 });
+
+    expect(result.success).toBe(true);
+    expect(results.scrollBy).toHaveBeenCalledWith(0, 300);
+    expect(global.scrollTo).not.toHaveBeenCalled();
+  });
+
+  test('scroll_element should fail when nothing around the ref scrolls', () => {
+    document.body.innerHTML = '<button>Submit</button>';
+    generateSnapshot();
+
+    const result = executeAction({ type: 'scroll_element', ref: 1, dy: 300 });
+    expect(result.success).toBe(false);
+    expect(result.error).toContain('No scrollable container');
+  });
+
   test('should return error if ref is not found', () => {
     // Empty map
     generateSnapshot(); // No interactive elements
@@ -122,6 +382,53 @@ describe('Action Executor', () => {
     expect(result.error).toContain('999 not found');
   });
 
+  test('count_elements should return the number of matching elements', () => {
+    document.body.innerHTML = `
+      <table>
+        <tr><td>1</td></tr>
+        <tr><td>2</td></tr>
+        <tr><td>3</td></tr>
+      </table>
+    `;
+
+    const command = { type: 'count_elements', selector: 'tr' };
+    const result = executeAction(command);
+
+    expect(result.success).toBe(true);
+    expect(result.data.count).toBe(3);
+  });
+
+  test('count_elements should return error for an invalid selector', () => {
+    const command = { type: 'count_elements', selector: '[[invalid' };
+    const result = executeAction(command);
+
+    expect(result.success).toBe(false);
+  });
+
+  test('get_selection should return the selected text and its block', () => {
+    document.body.innerHTML = `<p id="para">Hello <b>brave</b> new world</p>`;
+    const range = document.createRange();
+    range.selectNodeContents(document.querySelector('b'));
+    window.getSelection().removeAllRanges();
+    window.getSelection().addRange(range);
+
+    const result = executeAction({ type: 'get_selection' });
+
+    expect(result.success).toBe(true);
+    expect(result.data.text).toBe('brave');
+    expect(result.data.context.tag).toBe('p');
+    expect(result.data.context.text).toContain('Hello brave new world');
+  });
+
+  test('get_selection should return empty text when nothing is selected', () => {
+    window.getSelection().removeAllRanges();
+
+    const result = executeAction({ type: 'get_selection' });
+
+    expect(result.success).toBe(true);
+    expect(result.data.text).toBe('');
+  });
+
   test('should return error for unknown action type', () => {
     const command = { type: 'invalid_action' };
     const result = executeAction(command);
@@ -163,6 +163,19 @@ describe('Snapshot Generation', () => {
     });
   });
 
+  test('should mark disabled elements', () => {
+    document.body.innerHTML = `
+      <button disabled>Kirim</button>
+      <div role="button" aria-disabled="true">Lanjut</div>
+      <button>Batal</button>
+    `;
+
+    const snapshot = generateSnapshot();
+    expect(snapshot.tree[0].disabled).toBe(true);
+    expect(snapshot.tree[1].disabled).toBe(true);
+    expect(snapshot.tree[2]).not.toHaveProperty('disabled');
+  });
+
   test('should handle aria-label and other accessible names', () => {
     document.body.innerHTML = `
       <button aria-label="Close Dialog">X</button>